@@ -0,0 +1,64 @@
+use specs::prelude::*;
+use specs_derive::*;
+
+#[derive(PartialEq, Component, Debug, Clone, Copy)]
+pub struct Position {
+    pub x: i32,
+    pub y: i32,
+}
+
+#[derive(Component)]
+pub struct Viewshed {
+    pub visible_tiles : Vec<rltk::Point>,
+    pub range : i32,
+    pub dirty : bool
+}
+
+#[derive(Component, Debug)]
+pub struct BlocksTile {}
+
+#[derive(Component)]
+pub struct Renderable {
+    pub glyph: rltk::FontCharType,
+    pub fg: rltk::RGB,
+    pub bg: rltk::RGB,
+}
+
+#[derive(Component, Debug)]
+pub struct Player {}
+
+#[derive(Component, Debug)]
+pub struct Monster {}
+
+#[derive(Component, Debug)]
+pub struct Name {
+    pub name : String
+}
+
+/// Hp and ep changes gathered over a turn and applied together by the DamageSystem, so every
+/// action in a pass sees the same stats.  Negative amounts heal or recover.
+#[derive(Component, Debug, Clone)]
+pub struct SufferDamage {
+    pub hp_amount : Vec<i32>,
+    pub ep_amount : Vec<i32>
+}
+
+impl SufferDamage {
+    pub fn new_hp_damage(store: &mut WriteStorage<SufferDamage>, victim: Entity, amount: i32) {
+        if let Some(suffering) = store.get_mut(victim) {
+            suffering.hp_amount.push(amount);
+        } else {
+            let dmg = SufferDamage { hp_amount : vec![amount], ep_amount: vec![] };
+            store.insert(victim, dmg).expect("Unable to insert damage");
+        }
+    }
+
+    pub fn new_ep_damage(store: &mut WriteStorage<SufferDamage>, victim: Entity, amount: i32) {
+        if let Some(suffering) = store.get_mut(victim) {
+            suffering.ep_amount.push(amount);
+        } else {
+            let dmg = SufferDamage { hp_amount : vec![], ep_amount: vec![amount] };
+            store.insert(victim, dmg).expect("Unable to insert ep damage");
+        }
+    }
+}
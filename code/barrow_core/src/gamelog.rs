@@ -0,0 +1,163 @@
+use std::collections::VecDeque;
+
+/// Oldest entries are dropped once the log holds this many lines
+pub const LOG_CAPACITY : usize = 500;
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum LogCategory { Combat, Loot, Movement, System }
+
+pub const LOG_CATEGORIES : [LogCategory; 4] = [LogCategory::Combat, LogCategory::Loot, LogCategory::Movement, LogCategory::System];
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct LogEntry {
+    pub text: String,
+    pub category: LogCategory,
+    pub count: i32
+}
+
+impl LogEntry {
+    /// The entry as displayed, with repeats collapsed into "xN"
+    pub fn display(&self) -> String {
+        if self.count > 1 {
+            format!("{} #[grey]x{}#[]", self.text, self.count)
+        } else {
+            self.text.clone()
+        }
+    }
+}
+
+pub struct GameLog {
+    pub entries : VecDeque<LogEntry>
+}
+
+impl GameLog {
+    pub fn new() -> GameLog {
+        GameLog { entries: VecDeque::with_capacity(LOG_CAPACITY) }
+    }
+
+    pub fn push<S: ToString>(&mut self, entry: S) {
+        self.push_category(LogCategory::System, entry);
+    }
+
+    pub fn push_category<S: ToString>(&mut self, category: LogCategory, entry: S) {
+        let text = entry.to_string();
+        if let Some(last) = self.entries.back_mut() {
+            if last.text == text {
+                last.count += 1;
+                return;
+            }
+        }
+        if self.entries.len() >= LOG_CAPACITY {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(LogEntry { text: text, category: category, count: 1 });
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+impl Default for GameLog {
+    fn default() -> GameLog {
+        GameLog::new()
+    }
+}
+
+/// Filter and search settings for the message history screen
+pub struct LogView {
+    pub shown: Vec<LogCategory>,
+    pub search: String,
+    pub searching: bool
+}
+
+impl LogView {
+    pub fn new() -> LogView {
+        LogView { shown: LOG_CATEGORIES.to_vec(), search: String::new(), searching: false }
+    }
+
+    pub fn toggle(&mut self, category: LogCategory) {
+        if self.shown.contains(&category) {
+            self.shown.retain(|c| *c != category);
+        } else {
+            self.shown.push(category);
+        }
+    }
+
+    pub fn matches(&self, entry: &LogEntry) -> bool {
+        self.shown.contains(&entry.category) &&
+            (self.search.is_empty() || strip_markup(&entry.text).to_lowercase().contains(&self.search.to_lowercase()))
+    }
+}
+
+impl Default for LogView {
+    fn default() -> LogView {
+        LogView::new()
+    }
+}
+
+/// The text of a line with any #[color] markup removed
+pub fn strip_markup(text: &str) -> String {
+    let mut plain = String::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '#' && chars.peek() == Some(&'[') {
+            for m in chars.by_ref() {
+                if m == ']' { break; }
+            }
+        } else {
+            plain.push(c);
+        }
+    }
+    plain
+}
+
+/// Number of printed characters in a line, ignoring #[color] markup
+pub fn visible_len(text: &str) -> usize {
+    strip_markup(text).chars().count()
+}
+
+/// Word-wraps a line with #[color] markup to the given width.  The colour that is active
+/// at a break is re-opened at the start of the next line, so wrapped text keeps its colour.
+pub fn wrap_markup(text: &str, width: usize) -> Vec<String> {
+    if text.contains('\n') {
+        return text.split('\n').flat_map(|line| wrap_markup(line, width)).collect();
+    }
+
+    let mut lines : Vec<String> = vec![];
+    let mut current = String::new();
+    let mut current_len = 0;
+    let mut active_color = String::new();
+
+    for word in text.split(' ') {
+        let word_len = visible_len(word);
+        if current_len > 0 && current_len + 1 + word_len > width {
+            lines.push(current);
+            current = active_color.clone();
+            current_len = 0;
+        }
+        if current_len > 0 {
+            current.push(' ');
+            current_len += 1;
+        }
+        current.push_str(word);
+        current_len += word_len;
+
+        // track the last colour tag opened in this word
+        let mut rest = word;
+        while let Some(start) = rest.find("#[") {
+            match rest[start..].find(']') {
+                Some(end) => {
+                    let tag = &rest[start..start + end + 1];
+                    active_color = if tag == "#[]" { String::new() } else { tag.to_string() };
+                    rest = &rest[start + end + 1..];
+                }
+                None => break
+            }
+        }
+    }
+    if current_len > 0 || lines.is_empty() {
+        lines.push(current);
+    }
+    lines
+}
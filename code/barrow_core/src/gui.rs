@@ -0,0 +1,165 @@
+use rltk::{ RGB, RGBA, Rltk, Point, VirtualKeyCode };
+use specs::prelude::*;
+use bracket_terminal::prelude::TextAlign;
+use super::{Name, Position, MapGrid, gamelog::{GameLog, strip_markup}, shell::{Game, Screen}};
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum MainMenuSelection { NewGame, Quit }
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum MainMenuResult { NoSelection{ selected : MainMenuSelection }, Selected{ selected: MainMenuSelection } }
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum GameOverResult { NoSelection, QuitToMenu }
+
+/// Draws everything around the map: the version's sidebar, the log, the mouse cursor and the
+/// tooltip for whatever is under it
+pub fn draw_ui<G: Game>(game: &G, ecs: &World, ctx : &mut Rltk) {
+    match G::SCREEN {
+        Screen::Boxed => {
+            ctx.draw_box(0, 43, 49, 16, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK));
+            ctx.draw_box(50, 0, 29, 59, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK));
+            game.draw_sidebar(ecs, ctx);
+            draw_log(ecs, ctx, G::SCREEN);
+        }
+        Screen::Layered => {
+            ctx.set_active_console(1);
+            game.draw_sidebar(ecs, ctx);
+            draw_log(ecs, ctx, G::SCREEN);
+            ctx.set_active_console(0);
+        }
+    }
+
+    // Draw mouse cursor
+    let mouse_pos = ctx.mouse_pos();
+    ctx.set_bg(mouse_pos.0, mouse_pos.1, RGB::named(rltk::MAGENTA));
+    draw_tooltips::<G::Map>(ecs, ctx);
+}
+
+/// The newest log entries, bottom up: plain text inside the log box on a boxed screen, with
+/// their colours under the map on a layered one
+fn draw_log(ecs: &World, ctx : &mut Rltk, screen: Screen) {
+    let log = ecs.fetch::<GameLog>();
+    let (mut y, top) = match screen {
+        Screen::Boxed => (58, 44),
+        Screen::Layered => (29, 21)
+    };
+    for entry in log.entries.iter().rev() {
+        if y < top { break; }
+        match screen {
+            Screen::Boxed => ctx.print(2, y, &strip_markup(&entry.display())),
+            Screen::Layered => ctx.printer(1, y, format!("#[white]{}", entry.display()), TextAlign::Left, Some(RGBA::named(rltk::BLACK)))
+        }
+        y -= 1;
+    }
+}
+
+fn draw_tooltips<M: MapGrid>(ecs: &World, ctx : &mut Rltk) {
+    let map = ecs.fetch::<M>();
+    let map = map.grid();
+    let names = ecs.read_storage::<Name>();
+    let positions = ecs.read_storage::<Position>();
+
+    let mouse_pos = ctx.mouse_pos();
+    if mouse_pos.0 >= map.width || mouse_pos.1 >= map.height { return; }
+    let mut tooltip : Vec<String> = Vec::new();
+    for (name, position) in (&names, &positions).join() {
+        let idx = map.xy_idx(position.x, position.y);
+        if position.x == mouse_pos.0 && position.y == mouse_pos.1 && map.visible_tiles[idx] {
+            tooltip.push(name.name.to_string());
+        }
+    }
+
+    if !tooltip.is_empty() {
+        let mut width :i32 = 0;
+        for s in tooltip.iter() {
+            if width < s.len() as i32 { width = s.len() as i32; }
+        }
+        width += 3;
+
+        if mouse_pos.0 > 40 {
+            let arrow_pos = Point::new(mouse_pos.0 - 2, mouse_pos.1);
+            let left_x = mouse_pos.0 - width;
+            let mut y = mouse_pos.1;
+            for s in tooltip.iter() {
+                ctx.print_color(left_x, y, RGB::named(rltk::WHITE), RGB::named(rltk::GREY), s);
+                let padding = (width - s.len() as i32)-1;
+                for i in 0..padding {
+                    ctx.print_color(arrow_pos.x - i, y, RGB::named(rltk::WHITE), RGB::named(rltk::GREY), &" ".to_string());
+                }
+                y += 1;
+            }
+            ctx.print_color(arrow_pos.x, arrow_pos.y, RGB::named(rltk::WHITE), RGB::named(rltk::GREY), &"->".to_string());
+        } else {
+            let arrow_pos = Point::new(mouse_pos.0 + 1, mouse_pos.1);
+            let left_x = mouse_pos.0 +3;
+            let mut y = mouse_pos.1;
+            for s in tooltip.iter() {
+                ctx.print_color(left_x + 1, y, RGB::named(rltk::WHITE), RGB::named(rltk::GREY), s);
+                let padding = (width - s.len() as i32)-1;
+                for i in 0..padding {
+                    ctx.print_color(arrow_pos.x + 1 + i, y, RGB::named(rltk::WHITE), RGB::named(rltk::GREY), &" ".to_string());
+                }
+                y += 1;
+            }
+            ctx.print_color(arrow_pos.x, arrow_pos.y, RGB::named(rltk::WHITE), RGB::named(rltk::GREY), &"<-".to_string());
+        }
+    }
+}
+
+/// The title screen.  A layered screen puts it on the text console, where rows are twice as tall.
+pub fn main_menu(selection: MainMenuSelection, title: &str, screen: Screen, ctx : &mut Rltk) -> MainMenuResult {
+    let (title_y, new_game_y, quit_y) = match screen {
+        Screen::Boxed => (15, 24, 26),
+        Screen::Layered => {
+            ctx.set_active_console(1);
+            (8, 12, 13)
+        }
+    };
+
+    ctx.print_color_centered(title_y, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), title);
+
+    if selection == MainMenuSelection::NewGame {
+        ctx.print_color_centered(new_game_y, RGB::named(rltk::MAGENTA), RGB::named(rltk::BLACK), "Begin New Game (press Enter)");
+    } else {
+        ctx.print_color_centered(new_game_y, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), "Begin New Game");
+    }
+
+    if selection == MainMenuSelection::Quit {
+        ctx.print_color_centered(quit_y, RGB::named(rltk::MAGENTA), RGB::named(rltk::BLACK), "Quit (press Enter)");
+    } else {
+        ctx.print_color_centered(quit_y, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), "Quit");
+    }
+
+    match ctx.key {
+        None => MainMenuResult::NoSelection{ selected: selection },
+        Some(key) => {
+            match key {
+                VirtualKeyCode::Space => MainMenuResult::Selected{ selected: MainMenuSelection::NewGame },
+                VirtualKeyCode::Up | VirtualKeyCode::Down => {
+                    let newselection = match selection {
+                        MainMenuSelection::NewGame => MainMenuSelection::Quit,
+                        MainMenuSelection::Quit => MainMenuSelection::NewGame
+                    };
+                    MainMenuResult::NoSelection{ selected: newselection }
+                }
+                VirtualKeyCode::Return => MainMenuResult::Selected{ selected : selection },
+                _ => MainMenuResult::NoSelection{ selected: selection }
+            }
+        }
+    }
+}
+
+/// Waits for Escape once the player has died.  A boxed screen says so over the map; a layered
+/// one leaves it to the log.
+pub fn game_over(screen: Screen, ctx : &mut Rltk) -> GameOverResult {
+    if screen == Screen::Boxed {
+        ctx.print_color_centered(15, RGB::named(rltk::RED), RGB::named(rltk::BLACK), "You died!");
+        ctx.print_color_centered(17, RGB::named(rltk::MAGENTA), RGB::named(rltk::BLACK), "Press ESCAPE to return to the menu.");
+    }
+
+    match ctx.key {
+        Some(VirtualKeyCode::Escape) => GameOverResult::QuitToMenu,
+        _ => GameOverResult::NoSelection
+    }
+}
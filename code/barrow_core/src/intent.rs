@@ -0,0 +1,188 @@
+use specs::prelude::*;
+use std::collections::HashSet;
+use std::marker::PhantomData;
+use super::{SufferDamage, Ruleset};
+
+/// The intent model from the untitled prototypes: a plain attack, a strong attack that costs
+/// extra energy, and a guard that can be raised or lowered.  There are no reactions and no
+/// stance changes forced by attacks; energy only decides whether an attack can be made at all.
+/// A version plugs in its own stats, actions and log through an `IntentModel`.
+pub struct IntentRules<M>(pub M);
+
+/// The energy a strong attack costs on top of the fighter's usual attack cost
+pub const STRONG_ATTACK_COST : i32 = 10;
+
+/// What the intent rules need from a version's combat stats
+pub trait IntentStats : Component {
+    fn hp(&self) -> i32;
+    fn ep(&self) -> i32;
+    fn power(&self) -> i32;
+    fn defense(&self) -> i32;
+    fn attack_cost(&self) -> i32;
+    fn hp_regen(&self) -> i32;
+    fn ep_regen(&self) -> i32;
+    /// Whether the fighter has someone in sight to fight, which slows its recovery
+    fn in_combat(&self) -> bool;
+    fn guarding(&self) -> bool;
+    fn stunned(&self) -> bool;
+    fn set_guard(&mut self, up: bool);
+}
+
+/// What a fighter wants to do this pass, as the intent rules see it
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum Intent {
+    Attack { target: Entity, strong: bool },
+    Rest,
+    SetGuard(bool),
+    Move
+}
+
+/// A version's queued action, read as an intent.  Actions the rules have no say in, or that
+/// make no sense to them, give `None` and are left alone.
+pub trait IntentAction : Component {
+    fn intent(&self) -> Option<Intent>;
+}
+
+/// What happened while the intents were resolved, for the version to log
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum IntentEvent {
+    Attempted { entity: Entity, strong: bool },
+    Hit { attacker: Entity, target: Entity, strong: bool, damage: i32 },
+    /// Too tired or too stunned to attack, so the fighter rested instead
+    Exhausted { entity: Entity },
+    Rested { entity: Entity },
+    GuardChanged { entity: Entity, up: bool }
+}
+
+/// The parts of the intent rules that differ between versions
+pub trait IntentModel : Sync {
+    type Stats: IntentStats;
+    type Action: IntentAction;
+
+    fn run_ai(&self, ecs: &World);
+
+    /// Logs the pass that has just been resolved
+    fn report(&self, ecs: &World, events: &[IntentEvent]);
+
+    /// Clears up after the pass; `resolved` holds everyone whose action the rules acted on
+    fn settle(&self, ecs: &World, resolved: &[Entity]);
+}
+
+impl<M: IntentModel> Ruleset for IntentRules<M> {
+    fn run_ai(&self, ecs: &World) {
+        self.0.run_ai(ecs);
+    }
+
+    fn resolve(&self, ecs: &World) {
+        let mut intents = IntentSystem::<M::Stats, M::Action>::new();
+        intents.run_now(ecs);
+        self.0.report(ecs, &intents.events);
+        self.0.settle(ecs, &intents.resolved);
+    }
+}
+
+/// Resolves attacks, rests and guard changes.  Everything lands through `SufferDamage`, so the
+/// stats every fighter sees stay the same for the whole pass.
+pub struct IntentSystem<S, A> {
+    pub events: Vec<IntentEvent>,
+    pub resolved: Vec<Entity>,
+    marker: PhantomData<(S, A)>
+}
+
+impl<S, A> IntentSystem<S, A> {
+    pub fn new() -> IntentSystem<S, A> {
+        IntentSystem { events: Vec::new(), resolved: Vec::new(), marker: PhantomData }
+    }
+}
+
+impl<S, A> Default for IntentSystem<S, A> {
+    fn default() -> IntentSystem<S, A> {
+        IntentSystem::new()
+    }
+}
+
+impl<'a, S: IntentStats, A: IntentAction> System<'a> for IntentSystem<S, A> {
+    type SystemData = ( Entities<'a>,
+                        ReadStorage<'a, A>,
+                        WriteStorage<'a, S>,
+                        WriteStorage<'a, SufferDamage>,
+                        WriteExpect<'a, rltk::RandomNumberGenerator>
+                    );
+
+    fn run(&mut self, data : Self::SystemData) {
+        let (entities, actions, mut combat_stats, mut inflict_damage, mut rng) = data;
+        // a guard raised or lowered this pass only counts from the next one
+        let guarding : HashSet<Entity> = (&entities, &combat_stats).join().filter(|(_, stats)| stats.guarding()).map(|(entity, _)| entity).collect();
+
+        for (entity, action) in (&entities, &actions).join() {
+            let intent = match action.intent() {
+                Some(intent) => intent,
+                None => continue
+            };
+            let stats = match combat_stats.get_mut(entity) {
+                Some(stats) => stats,
+                None => continue
+            };
+            self.resolved.push(entity);
+
+            match intent {
+                Intent::Attack { target, strong } => {
+                    self.events.push(IntentEvent::Attempted { entity, strong });
+                    if stats.hp() <= 0 { continue; }
+                    let cost = stats.attack_cost() + if strong { STRONG_ATTACK_COST } else { 0 };
+                    if stats.ep() < cost || stats.stunned() {
+                        self.events.push(IntentEvent::Exhausted { entity });
+                        rest(entity, stats, &mut inflict_damage);
+                        continue;
+                    }
+                    let (power, target_stats) = (stats.power(), combat_stats.get(target));
+                    let target_stats = match target_stats {
+                        Some(target_stats) if target_stats.hp() > 0 => target_stats,
+                        _ => continue
+                    };
+                    let def_adj = if guarding.contains(&target) { 1 } else { 0 };
+                    let pow_adj = if strong { 1 } else { 0 };
+                    let damage = damage_formula(&mut rng, power + pow_adj, target_stats.defense() + def_adj);
+                    self.events.push(IntentEvent::Hit { attacker: entity, target, strong, damage });
+                    SufferDamage::new_hp_damage(&mut inflict_damage, target, damage);
+                    SufferDamage::new_ep_damage(&mut inflict_damage, entity, cost);
+                }
+                Intent::SetGuard(up) if up != stats.guarding() => {
+                    stats.set_guard(up);
+                    self.events.push(IntentEvent::GuardChanged { entity, up });
+                }
+                Intent::SetGuard(_) | Intent::Rest => {
+                    self.events.push(IntentEvent::Rested { entity });
+                    rest(entity, stats, &mut inflict_damage);
+                }
+                Intent::Move => move_regen(entity, stats, &mut inflict_damage)
+            }
+        }
+    }
+}
+
+/// Power less defense, plus a random bonus of up to two thirds of that again
+pub fn damage_formula(rng: &mut rltk::RandomNumberGenerator, attacker_pow:i32, target_def:i32) -> i32 {
+    let eff_atk = i32::max(0, attacker_pow - target_def);
+    let random_atk_max = 2 + ((2_f32 * eff_atk as f32)/3_f32).ceil() as i32;
+    let random_atk = rng.range(0,random_atk_max);
+    eff_atk + random_atk
+}
+
+/// Out of combat a rest heals and recovers energy quickly; in combat it only recovers energy
+fn rest<S: IntentStats>(entity: Entity, stats: &S, inflict_damage: &mut WriteStorage<SufferDamage>) {
+    if !stats.in_combat() {
+        SufferDamage::new_hp_damage(inflict_damage, entity, stats.hp_regen());
+        SufferDamage::new_ep_damage(inflict_damage, entity, 4 * stats.ep_regen());
+    }
+    SufferDamage::new_ep_damage(inflict_damage, entity, stats.ep_regen());
+}
+
+/// Walking recovers some energy, half as much with an enemy in sight
+fn move_regen<S: IntentStats>(entity: Entity, stats: &S, inflict_damage: &mut WriteStorage<SufferDamage>) {
+    if !stats.in_combat() {
+        SufferDamage::new_ep_damage(inflict_damage, entity, stats.ep_regen());
+    } else {
+        SufferDamage::new_ep_damage(inflict_damage, entity, stats.ep_regen() / 2);
+    }
+}
@@ -0,0 +1,28 @@
+//! The pieces every version of the game shares: room geometry, the basic components, the
+//! visibility and map indexing systems, the message log and spawning helpers.  Each game
+//! crate keeps its own `Map` and rules, and builds its map on the shared `GridMap`.
+//!
+//! The earlier versions also share a whole game shell: a version describes itself with a
+//! `shell::Game` (its map, spawns, `Ruleset`, sidebar and keys) and `shell::run` does the
+//! rest.  The intent rules from the untitled prototypes live in `intent`, for any version
+//! that offers them.
+
+mod rect;
+pub use rect::Rect;
+mod components;
+pub use components::*;
+mod map;
+pub use map::{Tile, GridMap, MapGrid};
+mod visibility_system;
+pub use visibility_system::VisibilitySystem;
+mod map_indexing_system;
+pub use map_indexing_system::MapIndexingSystem;
+pub mod gamelog;
+pub use gamelog::GameLog;
+pub mod spawner;
+mod ruleset;
+pub use ruleset::{Ruleset, Fighter};
+pub mod intent;
+pub mod player;
+pub mod gui;
+pub mod shell;
@@ -0,0 +1,223 @@
+use specs::prelude::*;
+use rltk::{BaseMap, Algorithm2D, Point, RandomNumberGenerator};
+use std::cmp::{max, min, Ordering};
+use super::Rect;
+
+/// A version's tile type.  The grid asks it which tiles stand in the way of movement and sight,
+/// and which ones to carve rooms out of.
+pub trait Tile: Copy + PartialEq + Send + Sync + 'static {
+    const WALL: Self;
+    const FLOOR: Self;
+
+    /// Whether the terrain alone stops anything from entering the tile
+    fn blocks(&self) -> bool;
+
+    fn opaque(&self) -> bool {
+        *self == Self::WALL
+    }
+}
+
+/// The grid of tiles every version's map is built on, with what lies on it, what the player
+/// has seen and the rooms carved into it
+#[derive(Clone)]
+pub struct GridMap<T: Tile> {
+    pub tiles : Vec<T>,
+    pub rooms : Vec<Rect>,
+    pub width : i32,
+    pub height : i32,
+    pub revealed_tiles : Vec<bool>,
+    pub visible_tiles : Vec<bool>,
+    pub blocked : Vec<bool>,
+    pub tile_content : Vec<Vec<Entity>>
+}
+
+impl<T: Tile> GridMap<T> {
+    /// A grid of solid wall
+    pub fn new(width: i32, height: i32) -> GridMap<T> {
+        let count = (width * height) as usize;
+        GridMap{
+            tiles : vec![T::WALL; count],
+            rooms : Vec::new(),
+            width : width,
+            height: height,
+            revealed_tiles : vec![false; count],
+            visible_tiles : vec![false; count],
+            blocked : vec![false; count],
+            tile_content : vec![Vec::new(); count]
+        }
+    }
+
+    pub fn xy_idx(&self, x: i32, y: i32) -> usize {
+        (y as usize * self.width as usize) + x as usize
+    }
+
+    pub fn is_exit_valid(&self, x:i32, y:i32) -> bool {
+        if x < 1 || x > self.width-1 || y < 1 || y > self.height-1 { return false; }
+        let idx = self.xy_idx(x, y);
+        !self.blocked[idx]
+    }
+
+    pub fn apply_room_to_map(&mut self, room : &Rect) {
+        for y in room.y1 +1 ..= room.y2 {
+            for x in room.x1 + 1 ..= room.x2 {
+                let idx = self.xy_idx(x, y);
+                self.tiles[idx] = T::FLOOR;
+            }
+        }
+    }
+
+    pub fn apply_horizontal_tunnel(&mut self, x1:i32, x2:i32, y:i32) {
+        for x in min(x1,x2) ..= max(x1,x2) {
+            let idx = self.xy_idx(x, y);
+            if idx > 0 && idx < self.width as usize * self.height as usize {
+                self.tiles[idx as usize] = T::FLOOR;
+            }
+        }
+    }
+
+    pub fn apply_vertical_tunnel(&mut self, y1:i32, y2:i32, x:i32) {
+        for y in min(y1,y2) ..= max(y1,y2) {
+            let idx = self.xy_idx(x, y);
+            if idx > 0 && idx < self.width as usize * self.height as usize {
+                self.tiles[idx as usize] = T::FLOOR;
+            }
+        }
+    }
+
+    /// The algorithm from http://rogueliketutorials.com/tutorials/tcod/part-3/: a handful of
+    /// random rooms, each joined to the one placed before it by a corridor
+    pub fn carve_rooms_and_corridors(&mut self, rng: &mut RandomNumberGenerator, min_size: i32, max_size: i32) {
+        const MAX_ROOMS : i32 = 30;
+
+        for _i in 0..MAX_ROOMS {
+            let w = rng.range(min_size, max_size);
+            let h = rng.range(min_size, max_size);
+            let x = rng.roll_dice(1, self.width - w - 1) - 1;
+            let y = rng.roll_dice(1, self.height - h - 1) - 1;
+            let new_room = Rect::new(x, y, w, h);
+            let mut ok = true;
+            for other_room in self.rooms.iter() {
+                if new_room.intersect(other_room) { ok = false }
+            }
+            if ok {
+                self.apply_room_to_map(&new_room);
+
+                if !self.rooms.is_empty() {
+                    let (new_x, new_y) = new_room.center();
+                    let (prev_x, prev_y) = self.rooms[self.rooms.len()-1].center();
+                    if rng.range(0,2) == 1 {
+                        self.apply_horizontal_tunnel(prev_x, new_x, prev_y);
+                        self.apply_vertical_tunnel(prev_y, new_y, new_x);
+                    } else {
+                        self.apply_vertical_tunnel(prev_y, new_y, prev_x);
+                        self.apply_horizontal_tunnel(prev_x, new_x, new_y);
+                    }
+                }
+
+                self.rooms.push(new_room);
+            }
+        }
+    }
+
+    /// Orders the rooms by distance from the first, so the stairs end up far apart
+    pub fn sort_rooms_from_first(&mut self) {
+        let (first_x, first_y) = self.rooms[0].center();
+        let distance = |r: &Rect| {
+            let (x, y) = r.center();
+            rltk::DistanceAlg::Pythagoras.distance2d(Point::new(x, y), Point::new(first_x, first_y))
+        };
+        self.rooms.sort_by(|a, b| distance(a).partial_cmp(&distance(b)).unwrap_or(Ordering::Equal));
+    }
+}
+
+impl<T: Tile> BaseMap for GridMap<T> {
+    fn is_opaque(&self, idx:usize) -> bool {
+        self.tiles[idx].opaque()
+    }
+
+    fn get_pathing_distance(&self, idx1:usize, idx2:usize) -> f32 {
+        let w = self.width as usize;
+        let p1 = Point::new(idx1 % w, idx1 / w);
+        let p2 = Point::new(idx2 % w, idx2 / w);
+        rltk::DistanceAlg::Pythagoras.distance2d(p1, p2)
+    }
+
+    fn get_available_exits(&self, idx:usize) -> rltk::SmallVec<[(usize, f32); 10]> {
+        let mut exits = rltk::SmallVec::new();
+        let x = idx as i32 % self.width;
+        let y = idx as i32 / self.width;
+        let w = self.width as usize;
+
+        // Cardinal directions
+        if self.is_exit_valid(x-1, y) { exits.push((idx-1, 1.0)) };
+        if self.is_exit_valid(x+1, y) { exits.push((idx+1, 1.0)) };
+        if self.is_exit_valid(x, y-1) { exits.push((idx-w, 1.0)) };
+        if self.is_exit_valid(x, y+1) { exits.push((idx+w, 1.0)) };
+
+        // Diagonals
+        if self.is_exit_valid(x-1, y-1) { exits.push(((idx-w)-1, 1.45)); }
+        if self.is_exit_valid(x+1, y-1) { exits.push(((idx-w)+1, 1.45)); }
+        if self.is_exit_valid(x-1, y+1) { exits.push(((idx+w)-1, 1.45)); }
+        if self.is_exit_valid(x+1, y+1) { exits.push(((idx+w)+1, 1.45)); }
+
+        exits
+    }
+}
+
+impl<T: Tile> Algorithm2D for GridMap<T> {
+    fn dimensions(&self) -> Point {
+        Point::new(self.width, self.height)
+    }
+}
+
+/// What the shared systems need from a game's map: the grid it is built on.  A version whose
+/// map is nothing more than the grid uses `GridMap` directly; one with more to it wraps a grid
+/// and hands it out here.
+pub trait MapGrid: Send + Sync + 'static {
+    type Tile: Tile;
+
+    fn grid(&self) -> &GridMap<Self::Tile>;
+    fn grid_mut(&mut self) -> &mut GridMap<Self::Tile>;
+
+    /// Resets blocking to what the terrain alone blocks
+    fn populate_blocked(&mut self) {
+        let grid = self.grid_mut();
+        for (i,tile) in grid.tiles.iter().enumerate() {
+            grid.blocked[i] = tile.blocks();
+        }
+    }
+
+    fn clear_content_index(&mut self) {
+        for content in self.grid_mut().tile_content.iter_mut() {
+            content.clear();
+        }
+    }
+
+    fn set_blocked(&mut self, idx: usize) {
+        self.grid_mut().blocked[idx] = true;
+    }
+
+    fn add_content(&mut self, idx: usize, entity: Entity) {
+        self.grid_mut().tile_content[idx].push(entity);
+    }
+
+    /// Called with the player's field of view whenever it changes, and marks those tiles seen.
+    /// Versions where sight also depends on light override this to do nothing and mark visible
+    /// tiles themselves.
+    fn reveal(&mut self, visible: &[Point]) {
+        let grid = self.grid_mut();
+        for t in grid.visible_tiles.iter_mut() { *t = false };
+        for vis in visible.iter() {
+            let idx = grid.xy_idx(vis.x, vis.y);
+            grid.revealed_tiles[idx] = true;
+            grid.visible_tiles[idx] = true;
+        }
+    }
+}
+
+impl<T: Tile> MapGrid for GridMap<T> {
+    type Tile = T;
+
+    fn grid(&self) -> &GridMap<T> { self }
+    fn grid_mut(&mut self) -> &mut GridMap<T> { self }
+}
@@ -0,0 +1,46 @@
+use specs::prelude::*;
+use std::marker::PhantomData;
+use super::{Position, BlocksTile, MapGrid};
+
+/// Rebuilds the blocked tiles and per-tile entity lists of map `M`
+pub struct MapIndexingSystem<M> {
+    marker: PhantomData<M>
+}
+
+impl<M> MapIndexingSystem<M> {
+    pub fn new() -> MapIndexingSystem<M> {
+        MapIndexingSystem { marker: PhantomData }
+    }
+}
+
+impl<M> Default for MapIndexingSystem<M> {
+    fn default() -> MapIndexingSystem<M> {
+        MapIndexingSystem::new()
+    }
+}
+
+impl<'a, M: MapGrid> System<'a> for MapIndexingSystem<M> {
+    type SystemData = ( WriteExpect<'a, M>,
+                        ReadStorage<'a, Position>,
+                        ReadStorage<'a, BlocksTile>,
+                        Entities<'a>,);
+
+    fn run(&mut self, data : Self::SystemData) {
+        let (mut map, position, blockers, entities) = data;
+
+        map.populate_blocked();
+        map.clear_content_index();
+        for (entity, position) in (&entities, &position).join() {
+            let idx = map.grid().xy_idx(position.x, position.y);
+
+            // If they block, update the blocking list
+            if blockers.get(entity).is_some() {
+                map.set_blocked(idx);
+            }
+
+            // Push the entity to the appropriate index slot. It's a Copy
+            // type, so we don't need to clone it (we want to avoid moving it out of the ECS!)
+            map.add_content(idx, entity);
+        }
+    }
+}
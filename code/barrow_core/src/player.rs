@@ -0,0 +1,152 @@
+use rltk::{VirtualKeyCode, Point};
+use specs::prelude::*;
+use super::{Position, Viewshed, Player, Monster, MapGrid, Fighter};
+
+/// Where one step of the player's leads
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum Step {
+    /// Into whoever is standing there
+    Attack(Entity),
+    /// Onto a free tile
+    Move(Position),
+    /// Nowhere: the edge of the map or something in the way
+    Stay
+}
+
+/// The step a movement key asks for: the arrows, the number pad, WASD, and QEZC for the
+/// diagonals
+pub fn movement(key: VirtualKeyCode) -> Option<(i32, i32)> {
+    match key {
+        VirtualKeyCode::Left | VirtualKeyCode::Numpad4 | VirtualKeyCode::A => Some((-1, 0)),
+        VirtualKeyCode::Right | VirtualKeyCode::Numpad6 | VirtualKeyCode::D => Some((1, 0)),
+        VirtualKeyCode::Up | VirtualKeyCode::Numpad8 | VirtualKeyCode::W => Some((0, -1)),
+        VirtualKeyCode::Down | VirtualKeyCode::Numpad2 | VirtualKeyCode::S => Some((0, 1)),
+
+        // Diagonals
+        VirtualKeyCode::Numpad7 | VirtualKeyCode::Q => Some((-1, -1)),
+        VirtualKeyCode::Numpad9 | VirtualKeyCode::E => Some((1, -1)),
+        VirtualKeyCode::Numpad3 | VirtualKeyCode::C => Some((1, 1)),
+        VirtualKeyCode::Numpad1 | VirtualKeyCode::Z => Some((-1, 1)),
+        _ => None
+    }
+}
+
+/// The vi keys, for versions that leave them free: HJKL, and YUBN for the diagonals
+pub fn vi_movement(key: VirtualKeyCode) -> Option<(i32, i32)> {
+    match key {
+        VirtualKeyCode::H => Some((-1, 0)),
+        VirtualKeyCode::L => Some((1, 0)),
+        VirtualKeyCode::K => Some((0, -1)),
+        VirtualKeyCode::J => Some((0, 1)),
+        VirtualKeyCode::Y => Some((-1, -1)),
+        VirtualKeyCode::U => Some((1, -1)),
+        VirtualKeyCode::N => Some((1, 1)),
+        VirtualKeyCode::B => Some((-1, 1)),
+        _ => None
+    }
+}
+
+/// The target a number key picks out of the sidebar list, counting from one
+pub fn target_number(key: VirtualKeyCode) -> Option<usize> {
+    match key {
+        VirtualKeyCode::Key1 => Some(1),
+        VirtualKeyCode::Key2 => Some(2),
+        VirtualKeyCode::Key3 => Some(3),
+        VirtualKeyCode::Key4 => Some(4),
+        VirtualKeyCode::Key5 => Some(5),
+        VirtualKeyCode::Key6 => Some(6),
+        VirtualKeyCode::Key7 => Some(7),
+        VirtualKeyCode::Key8 => Some(8),
+        VirtualKeyCode::Key9 => Some(9),
+        _ => None
+    }
+}
+
+/// Where a step of the player's by the given offset would take them.  Anything with combat
+/// stats `S` on the tile is attacked rather than walked into.
+pub fn step<M: MapGrid, S: Fighter>(ecs: &World, delta_x: i32, delta_y: i32) -> Step {
+    let player_entity = ecs.fetch::<Entity>();
+    let positions = ecs.read_storage::<Position>();
+    let combat_stats = ecs.read_storage::<S>();
+    let map = ecs.fetch::<M>();
+    let grid = map.grid();
+
+    let pos = match positions.get(*player_entity) {
+        Some(pos) => pos,
+        None => return Step::Stay
+    };
+    let (x, y) = (pos.x + delta_x, pos.y + delta_y);
+    if x < 1 || x > grid.width-1 || y < 1 || y > grid.height-1 { return Step::Stay; }
+    let destination_idx = grid.xy_idx(x, y);
+
+    for potential_target in grid.tile_content[destination_idx].iter() {
+        if combat_stats.get(*potential_target).is_some() {
+            return Step::Attack(*potential_target);
+        }
+    }
+
+    if grid.blocked[destination_idx] {
+        Step::Stay
+    } else {
+        Step::Move(Position { x, y })
+    }
+}
+
+/// Puts the player straight onto the given tile, for versions where moving is not an action
+pub fn move_player(ecs: &mut World, to: Position) {
+    let player_entity = *ecs.fetch::<Entity>();
+    if let Some(pos) = ecs.write_storage::<Position>().get_mut(player_entity) {
+        *pos = to;
+    }
+    if let Some(viewshed) = ecs.write_storage::<Viewshed>().get_mut(player_entity) {
+        viewshed.dirty = true;
+    }
+    *ecs.write_resource::<Point>() = Point::new(to.x, to.y);
+}
+
+/// Lists the monsters the player can see, and keeps the current target while it stays in
+/// sight, picking the first one seen when there is none
+pub fn update_targeting<M: MapGrid, S: Fighter>(ecs: &World) {
+    let mut combat_stats = ecs.write_storage::<S>();
+    let players = ecs.read_storage::<Player>();
+    let monsters = ecs.read_storage::<Monster>();
+    let positions = ecs.read_storage::<Position>();
+    let entities = ecs.entities();
+    let map = ecs.fetch::<M>();
+    let grid = map.grid();
+
+    for (_player, player_stats) in (&players, &mut combat_stats).join() {
+        let mut current_target = player_stats.current_target();
+        let mut current_target_seen = false;
+        let visible_targets = player_stats.visible_targets();
+        visible_targets.clear();
+
+        for (entity, _monster, position) in (&entities, &monsters, &positions).join() {
+            let idx = grid.xy_idx(position.x, position.y);
+            if grid.visible_tiles[idx] {
+                if current_target == None {
+                    current_target = Some(entity);
+                    current_target_seen = true;
+                } else if current_target == Some(entity) {
+                    current_target_seen = true;
+                }
+                visible_targets.push(entity);
+            }
+        }
+
+        player_stats.set_current_target(if current_target_seen { current_target } else { None });
+    }
+}
+
+/// Makes the numbered entry of the visible targets the current one, if there is such an entry
+pub fn select_target<S: Fighter>(ecs: &World, selection: usize) {
+    let mut combat_stats = ecs.write_storage::<S>();
+    let players = ecs.read_storage::<Player>();
+
+    for (_player, stats) in (&players, &mut combat_stats).join() {
+        let target = stats.visible_targets().get(selection - 1).copied();
+        if target.is_some() {
+            stats.set_current_target(target);
+        }
+    }
+}
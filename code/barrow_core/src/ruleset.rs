@@ -0,0 +1,18 @@
+use specs::prelude::*;
+
+/// A combat model: how the monsters choose what to do each turn, and how what everyone chose
+/// is settled.  A version picks one and the shell runs it between the visibility and map
+/// indexing passes.
+pub trait Ruleset : Sync {
+    fn run_ai(&self, ecs: &World);
+    fn resolve(&self, ecs: &World);
+}
+
+/// What the shell reads and writes on a version's combat stats: whether the fighter is still
+/// standing, and which of the monsters it can see it has singled out
+pub trait Fighter : Component {
+    fn hp(&self) -> i32;
+    fn current_target(&self) -> Option<Entity>;
+    fn set_current_target(&mut self, target: Option<Entity>);
+    fn visible_targets(&mut self) -> &mut Vec<Entity>;
+}
@@ -0,0 +1,281 @@
+use rltk::{GameState, Rltk, RGB, Point, VirtualKeyCode, register_palette_color};
+use specs::prelude::*;
+use super::{Position, Renderable, Player, Monster, Name, Viewshed, BlocksTile, MapGrid, Ruleset, Fighter, VisibilitySystem, MapIndexingSystem, gamelog::GameLog, gui, player};
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum RunState {
+    AwaitingInput,
+    PreRun,
+    PlayerTurn,
+    MonsterTurn,
+    MainMenu { menu_selection : gui::MainMenuSelection },
+    GameOver
+}
+
+/// How a version lays out its window
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum Screen {
+    /// Everything on one 80x60 console, with the sidebar and the log boxed in beside and
+    /// below the map
+    Boxed,
+    /// The map on the 80x60 console, and the sidebar, log and menus in an 8x16 font on a
+    /// sparse console laid over it
+    Layered
+}
+
+/// What sets one version apart from the others: its map, its fighters, the rules they fight
+/// by and what the sidebar shows.  The shell runs the menus, turns, log, targeting and
+/// tooltips the same way for all of them.
+pub trait Game : 'static {
+    type Map: MapGrid;
+    type Stats: Fighter;
+
+    const TITLE: &'static str;
+    const WELCOME: &'static str;
+    const SCREEN: Screen;
+
+    fn ruleset(&self) -> &dyn Ruleset;
+
+    /// Registers the components the version adds to the shared ones, its `Stats` among them
+    fn register(&self, ecs: &mut World);
+
+    fn new_map(&self) -> Self::Map;
+    fn spawn_player(&self, ecs: &mut World, x: i32, y: i32) -> Entity;
+
+    /// Spawns the monster for the centre of room `room` out of `rooms`.  The first room is
+    /// the player's and never gets one.
+    fn spawn_monster(&self, ecs: &mut World, room: usize, rooms: usize, x: i32, y: i32);
+
+    fn draw_map(&self, ecs: &World, ctx: &mut Rltk);
+
+    /// Draws the player's stats, targets and commands down the right of the screen, on the
+    /// text console of a layered screen
+    fn draw_sidebar(&self, ecs: &World, ctx: &mut Rltk);
+
+    /// Acts on a key pressed while the game waits for the player
+    fn player_input(&self, ecs: &mut World, key: VirtualKeyCode) -> RunState;
+}
+
+pub struct State<G: Game> {
+    pub ecs: World,
+    pub game: G
+}
+
+impl<G: Game> State<G> {
+    pub fn new(game: G) -> State<G> {
+        let mut ecs = World::new();
+        ecs.register::<Position>();
+        ecs.register::<Renderable>();
+        ecs.register::<Player>();
+        ecs.register::<Viewshed>();
+        ecs.register::<Monster>();
+        ecs.register::<Name>();
+        ecs.register::<BlocksTile>();
+        game.register(&mut ecs);
+
+        ecs.insert(rltk::RandomNumberGenerator::new());
+        ecs.insert(GameLog::new());
+        ecs.insert(RunState::MainMenu{ menu_selection: gui::MainMenuSelection::NewGame });
+
+        let mut gs = State { ecs, game };
+        gs.new_game();
+        gs
+    }
+
+    fn run_systems(&mut self) {
+        self.sync_player_position();
+        let mut vis = VisibilitySystem::<G::Map, Player>::new();
+        vis.run_now(&self.ecs);
+        let rules = self.game.ruleset();
+        rules.run_ai(&self.ecs);
+        rules.resolve(&self.ecs);
+        let mut mapindex = MapIndexingSystem::<G::Map>::new();
+        mapindex.run_now(&self.ecs);
+        self.ecs.maintain();
+    }
+
+    /// Keeps the player's position resource, which the monsters chase, on the player
+    fn sync_player_position(&mut self) {
+        let player_entity = *self.ecs.fetch::<Entity>();
+        let pos = self.ecs.read_storage::<Position>().get(player_entity).copied();
+        if let Some(pos) = pos {
+            *self.ecs.write_resource::<Point>() = Point::new(pos.x, pos.y);
+        }
+    }
+
+    /// Clears out the last run and lays out a fresh level: the player at the centre of the
+    /// first room and a monster at the centre of every other
+    fn new_game(&mut self) {
+        self.ecs.delete_all();
+        {
+            let mut log = self.ecs.write_resource::<GameLog>();
+            log.clear();
+            log.push(G::WELCOME);
+        }
+
+        let map = self.game.new_map();
+        let rooms = map.grid().rooms.clone();
+        let (player_x, player_y) = rooms[0].center();
+        let player_entity = self.game.spawn_player(&mut self.ecs, player_x, player_y);
+        for (i, room) in rooms.iter().enumerate().skip(1) {
+            let (x, y) = room.center();
+            self.game.spawn_monster(&mut self.ecs, i, rooms.len(), x, y);
+        }
+
+        self.ecs.insert(map);
+        self.ecs.insert(Point::new(player_x, player_y));
+        self.ecs.insert(player_entity);
+    }
+
+    fn draw_renderables(&self, ctx : &mut Rltk) {
+        let positions = self.ecs.read_storage::<Position>();
+        let renderables = self.ecs.read_storage::<Renderable>();
+        let map = self.ecs.fetch::<G::Map>();
+        let map = map.grid();
+
+        for (pos, render) in (&positions, &renderables).join() {
+            let idx = map.xy_idx(pos.x, pos.y);
+            if map.visible_tiles[idx] { ctx.set(pos.x, pos.y, render.fg, render.bg, render.glyph) }
+        }
+    }
+}
+
+impl<G: Game> GameState for State<G> {
+    fn tick(&mut self, ctx : &mut Rltk) {
+        let mut newrunstate = *self.ecs.fetch::<RunState>();
+
+        if G::SCREEN == Screen::Layered {
+            ctx.set_active_console(1);
+            ctx.cls();
+            ctx.set_active_console(0);
+        }
+        ctx.cls();
+
+        match newrunstate {
+            RunState::MainMenu{..} => {}
+            _ => {
+                self.game.draw_map(&self.ecs, ctx);
+                self.draw_renderables(ctx);
+                player::update_targeting::<G::Map, G::Stats>(&self.ecs);
+                gui::draw_ui(&self.game, &self.ecs, ctx);
+            }
+        }
+
+        match newrunstate {
+            RunState::PreRun => {
+                self.run_systems();
+                newrunstate = RunState::AwaitingInput;
+            }
+            RunState::AwaitingInput => {
+                newrunstate = match ctx.key {
+                    None => RunState::AwaitingInput,
+                    Some(key) => self.game.player_input(&mut self.ecs, key)
+                };
+            }
+            RunState::PlayerTurn => {
+                self.run_systems();
+                newrunstate = RunState::MonsterTurn;
+            }
+            RunState::MonsterTurn => {
+                self.run_systems();
+                newrunstate = RunState::AwaitingInput;
+            }
+            RunState::MainMenu{ menu_selection } => {
+                match gui::main_menu(menu_selection, G::TITLE, G::SCREEN, ctx) {
+                    gui::MainMenuResult::NoSelection{ selected } => newrunstate = RunState::MainMenu{ menu_selection: selected },
+                    gui::MainMenuResult::Selected{ selected } => {
+                        match selected {
+                            gui::MainMenuSelection::NewGame => newrunstate = RunState::PreRun,
+                            gui::MainMenuSelection::Quit => { ::std::process::exit(0); }
+                        }
+                    }
+                }
+            }
+            RunState::GameOver => {
+                match gui::game_over(G::SCREEN, ctx) {
+                    gui::GameOverResult::NoSelection => {}
+                    gui::GameOverResult::QuitToMenu => {
+                        self.new_game();
+                        newrunstate = RunState::MainMenu{ menu_selection: gui::MainMenuSelection::NewGame };
+                    }
+                }
+            }
+        }
+
+        {
+            let mut runwriter = self.ecs.write_resource::<RunState>();
+            *runwriter = newrunstate;
+        }
+        delete_the_dead::<G::Stats>(&mut self.ecs);
+    }
+}
+
+/// Removes the fallen monsters, and ends the run if the player is among them
+fn delete_the_dead<S: Fighter>(ecs : &mut World) {
+    let mut dead : Vec<Entity> = Vec::new();
+    // Using a scope to make the borrow checker happy
+    {
+        let combat_stats = ecs.read_storage::<S>();
+        let players = ecs.read_storage::<Player>();
+        let names = ecs.read_storage::<Name>();
+        let entities = ecs.entities();
+        let mut log = ecs.write_resource::<GameLog>();
+        for (entity, stats) in (&entities, &combat_stats).join() {
+            if stats.hp() < 1 {
+                match players.get(entity) {
+                    None => {
+                        if let Some(victim_name) = names.get(entity) {
+                            log.push(format!("#[orange]{}#[] is dead", &victim_name.name));
+                        }
+                        dead.push(entity)
+                    }
+                    Some(_) => {
+                        let mut runstate = ecs.write_resource::<RunState>();
+                        if *runstate != RunState::GameOver {
+                            log.push("#[red]You died!");
+                            log.push("#[pink]Press ESCAPE to return to the menu.");
+                            *runstate = RunState::GameOver;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    for victim in dead {
+        ecs.delete_entity(victim).expect("Unable to delete");
+    }
+}
+
+/// Opens the window `game` asks for and runs it until the player quits
+pub fn run<G: Game>(game: G) -> rltk::BError {
+    use rltk::RltkBuilder;
+    let context = match G::SCREEN {
+        Screen::Boxed => {
+            RltkBuilder::simple(80,60)
+                .unwrap()
+                .with_title(G::TITLE)
+                .build()?
+        }
+        Screen::Layered => {
+            let context = RltkBuilder::simple(80,60)
+                .unwrap()
+                .with_font("vga8x16.png", 8u32, 16u32)
+                .with_sparse_console(80u32, 30u32, "vga8x16.png")
+                .with_title(G::TITLE)
+                .build()?;
+
+            register_palette_color("grey", RGB::named(rltk::GREY));
+            register_palette_color("pink", RGB::named(rltk::MAGENTA));
+            register_palette_color("red", RGB::named(rltk::RED));
+            register_palette_color("orange", RGB::named(rltk::ORANGE));
+            register_palette_color("yellow", RGB::named(rltk::YELLOW));
+            register_palette_color("green", RGB::named(rltk::GREEN));
+            register_palette_color("cyan", RGB::named(rltk::CYAN));
+            register_palette_color("blue", RGB::named(rltk::BLUE));
+            context
+        }
+    };
+
+    rltk::main_loop(context, State::new(game))
+}
@@ -0,0 +1,77 @@
+use rltk::{RGB, RandomNumberGenerator};
+use specs::prelude::*;
+use super::{Rect, Position, Renderable, Player, Monster, Name, Viewshed, BlocksTile};
+
+/// Starts building the player at the given spot: a yellow @ that sees eight tiles around it.
+/// The version adds its combat stats and builds it.
+pub fn player(ecs : &mut World, player_x : i32, player_y : i32) -> EntityBuilder<'_> {
+    ecs
+        .create_entity()
+        .with(Position { x: player_x, y: player_y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('@'),
+            fg: RGB::named(rltk::YELLOW),
+            bg: RGB::named(rltk::BLACK),
+        })
+        .with(Player{})
+        .with(Viewshed{ visible_tiles : Vec::new(), range: 8, dirty: true })
+        .with(Name{name: "Player".to_string() })
+}
+
+/// Starts building a monster at the given spot: drawn in red, seeing eight tiles around it
+/// and blocking the tile it stands on
+pub fn monster<S : ToString>(ecs: &mut World, x: i32, y: i32, glyph : rltk::FontCharType, name : S) -> EntityBuilder<'_> {
+    ecs.create_entity()
+        .with(Position{ x, y })
+        .with(Renderable{
+            glyph,
+            fg: RGB::named(rltk::RED),
+            bg: RGB::named(rltk::BLACK),
+        })
+        .with(Viewshed{ visible_tiles : Vec::new(), range: 8, dirty: true })
+        .with(Monster{})
+        .with(Name{ name : name.to_string() })
+        .with(BlocksTile{})
+}
+
+/// Spawn points for a room: its centre first, then random tiles inside it.  Two points may
+/// land on the same tile; use `gen_distinct_spawn_points` where that matters.
+pub fn gen_spawn_points(room: &Rect, count: i32, rng: &mut RandomNumberGenerator) -> Vec<(i32, i32)> {
+    let mut res : Vec<(i32, i32)> = vec![];
+    res.push(room.center());
+    while (res.len() as i32) < count {
+        let x = rng.range(room.x1 + 1, room.x2 + 1);
+        let y = rng.range(room.y1 + 1, room.y2 + 1);
+        res.push((x,y));
+    }
+
+    res
+}
+
+/// Like `gen_spawn_points`, but no two points share a tile, so a crowded room gets as many
+/// points as were asked for or as fit
+pub fn gen_distinct_spawn_points(room: &Rect, count: i32, rng: &mut RandomNumberGenerator) -> Vec<(i32, i32)> {
+    let mut res : Vec<(i32, i32)> = vec![];
+    res.push(room.center());
+    let (w, h) = (room.x2 - room.x1, room.y2 - room.y1);
+    while (res.len() as i32) < i32::min(count, w * h) {
+        let x = rng.range(room.x1 + 1, room.x2 + 1);
+        let y = rng.range(room.y1 + 1, room.y2 + 1);
+        // a tile that is already taken gives way to the next free one along the room
+        let start = (y - room.y1 - 1) * w + (x - room.x1 - 1);
+        let free = (0..w * h)
+            .map(|i| (start + i) % (w * h))
+            .map(|i| (room.x1 + 1 + i % w, room.y1 + 1 + i / w))
+            .find(|point| !res.contains(point));
+        if let Some(point) = free {
+            res.push(point);
+        }
+    }
+
+    res
+}
+
+/// Random tags, one per spawned entity, so each can be told apart from its neighbours
+pub fn gen_tags(count: i32, rng: &mut RandomNumberGenerator) -> Vec<u64> {
+    (0..count).map(|_| rng.next_u64()).collect()
+}
@@ -0,0 +1,47 @@
+use specs::prelude::*;
+use rltk::{field_of_view, Point, Algorithm2D};
+use std::marker::PhantomData;
+use super::{Viewshed, Position, MapGrid};
+
+/// Recomputes dirty viewsheds on map `M`, and lets the map reveal whatever an entity with the
+/// player component `P` can see
+pub struct VisibilitySystem<M, P> {
+    marker: PhantomData<(M, P)>
+}
+
+impl<M, P> VisibilitySystem<M, P> {
+    pub fn new() -> VisibilitySystem<M, P> {
+        VisibilitySystem { marker: PhantomData }
+    }
+}
+
+impl<M, P> Default for VisibilitySystem<M, P> {
+    fn default() -> VisibilitySystem<M, P> {
+        VisibilitySystem::new()
+    }
+}
+
+impl<'a, M: MapGrid, P: Component> System<'a> for VisibilitySystem<M, P> {
+    type SystemData = ( WriteExpect<'a, M>,
+                        Entities<'a>,
+                        WriteStorage<'a, Viewshed>,
+                        ReadStorage<'a, Position>,
+                        ReadStorage<'a, P>);
+
+    fn run(&mut self, data : Self::SystemData) {
+        let (mut map, entities, mut viewshed, pos, player) = data;
+
+        for (ent,viewshed,pos) in (&entities, &mut viewshed, &pos).join() {
+            if viewshed.dirty {
+                viewshed.dirty = false;
+                viewshed.visible_tiles = field_of_view(Point::new(pos.x, pos.y), viewshed.range, map.grid());
+                viewshed.visible_tiles.retain(|p| map.grid().in_bounds(*p));
+
+                // If this is the player, reveal what they can see
+                if player.get(ent).is_some() {
+                    map.reveal(&viewshed.visible_tiles);
+                }
+            }
+        }
+    }
+}
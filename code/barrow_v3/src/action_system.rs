@@ -1,5 +1,5 @@
 use specs::prelude::*;
-use super::{CombatStats, Action, ActionType, Attack, CombatStance, Name, Player, Position, gamelog::GameLog, RunState, Map, Viewshed};
+use super::{CombatStats, Action, ActionType, Attack, CombatStance, Name, Position, gamelog::GameLog, Map, Viewshed};
 // use rltk::console;
 
 pub struct ActionSystem {}
@@ -25,7 +25,7 @@ impl<'a> System<'a> for ActionSystem {
             {
                 let mut subject_stats = combat_stats.get_mut(entity).unwrap(); 
                 if subject_stats.stance == CombatStance::GuardBreak {
-                    log.entries.push(format!("#[red]{}'s guard is broken! Recovering...#[].", &name.name));
+                    log.push(format!("#[red]{}'s guard is broken! Recovering...#[].", &name.name));
     
                     rest_or_default(&mut subject_stats);
                     actions.clear();
@@ -62,7 +62,7 @@ impl<'a> System<'a> for ActionSystem {
                         rest_or_default( subject_stats);
                     } else {
                         let target_name = names.get(*target).unwrap();
-                        log.entries.push(format!("{} hits #[orange]{}#[] for #[orange]{} hp#[].", &name.name, &target_name.name, raw_damage));
+                        log.push(format!("{} hits #[orange]{}#[] for #[orange]{} hp#[].", &name.name, &target_name.name, raw_damage));
                         {
                             let subject_stats = combat_stats.get_mut(entity).unwrap();
                             apply_ep_damage(subject_stats,ep_cost);
@@ -74,7 +74,7 @@ impl<'a> System<'a> for ActionSystem {
                     }
                 }
                 Action{ action_type: ActionType::Attack, attack: Some(Attack::StrongMelee), target: Some(target), .. } => {
-                    // log.entries.push(format!("{} will try to STRONG attack", name.name));
+                    // log.push(format!("{} will try to STRONG attack", name.name));
                     let raw_damage: i32;
                     let ep_damage: i32;
                     let ep_cost: i32;
@@ -100,7 +100,7 @@ impl<'a> System<'a> for ActionSystem {
                         rest_or_default( subject_stats);
                     } else {
                         let target_name = names.get(*target).unwrap();
-                        log.entries.push(format!("{} hits #[orange]{}#[] fiercely for #[orange]{} hp#[].", &name.name, &target_name.name, raw_damage));
+                        log.push(format!("{} hits #[orange]{}#[] fiercely for #[orange]{} hp#[].", &name.name, &target_name.name, raw_damage));
                         {
                             let subject_stats = combat_stats.get_mut(entity).unwrap();
                             apply_ep_damage(subject_stats,ep_cost);
@@ -132,7 +132,7 @@ impl<'a> System<'a> for ActionSystem {
                     move_regen(&mut subject_stats);
                 }
                 _ => {
-                    log.entries.push(format!("Anomaly: {} has an incoherent intent", name.name));
+                    log.push(format!("Anomaly: {} has an incoherent intent", name.name));
                 }
             }
         }
@@ -190,42 +190,4 @@ pub fn rest_or_default(stats: &mut CombatStats) {
     } else {
         apply_ep_damage(stats, stats.ep_regen);
     }
-} 
-
-pub fn delete_the_dead(ecs : &mut World) {
-    let mut dead : Vec<Entity> = Vec::new();
-    // Using a scope to make the borrow checker happy
-    {
-        let combat_stats = ecs.read_storage::<CombatStats>();
-        let players = ecs.read_storage::<Player>();
-        let names = ecs.read_storage::<Name>();
-        let entities = ecs.entities();
-        let mut log = ecs.write_resource::<GameLog>();
-        for (entity, stats) in (&entities, &combat_stats).join() {
-            if stats.hp < 1 {
-                let player = players.get(entity);
-                match player {
-                    None => {
-                        let victim_name = names.get(entity);
-                        if let Some(victim_name) = victim_name {
-                            log.entries.push(format!("#[orange]{}#[] is dead", &victim_name.name));
-                        }
-                        dead.push(entity)
-                    }
-                    Some(_) => {
-                        let mut runstate = ecs.write_resource::<RunState>();
-                        if *runstate != RunState::GameOver {
-                            log.entries.push(format!("#[red]You died! #[pink]Press ESCAPE to return to the menu."));
-
-                            *runstate = RunState::GameOver;
-                        }
-                    }
-                }
-            }
-        }
-    }
-
-    for victim in dead {
-        ecs.delete_entity(victim).expect("Unable to delete");
-    }
 }
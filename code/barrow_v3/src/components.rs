@@ -1,40 +1,7 @@
 use specs::prelude::*;
 use specs_derive::*;
-use rltk::{RGB};
-
-#[derive(PartialEq, Component, Debug, Clone, Copy)]
-pub struct Position {
-    pub x: i32,
-    pub y: i32,
-}
-
-#[derive(Component)]
-pub struct Renderable {
-    pub glyph: rltk::FontCharType,
-    pub fg: RGB,
-    pub bg: RGB,
-}
-
-#[derive(Component, Debug)]
-pub struct Player {}
-
-#[derive(Component)]
-pub struct Viewshed {
-    pub visible_tiles : Vec<rltk::Point>,
-    pub range : i32,
-    pub dirty : bool
-}
-
-#[derive(Component, Debug)]
-pub struct Monster {}
-
-#[derive(Component, Debug)]
-pub struct Name {
-    pub name : String
-}
-
-#[derive(Component, Debug)]
-pub struct BlocksTile {}
+pub use barrow_core::{Position, Viewshed, BlocksTile, Renderable, Player, Monster, Name};
+use barrow_core::Fighter;
 
 #[derive(PartialEq, Copy, Clone, Debug)]
 pub enum CombatStance { GuardUp, GuardDown, GuardBreak }
@@ -55,6 +22,13 @@ pub struct CombatStats {
     pub current_target : Option<Entity>
 }
 
+impl Fighter for CombatStats {
+    fn hp(&self) -> i32 { self.hp }
+    fn current_target(&self) -> Option<Entity> { self.current_target }
+    fn set_current_target(&mut self, target: Option<Entity>) { self.current_target = target; }
+    fn visible_targets(&mut self) -> &mut Vec<Entity> { &mut self.visible_targets }
+}
+
 #[derive(PartialEq, Copy, Clone, Debug)]
 pub enum ActionType { Move, Wait, Attack}
 
@@ -32,11 +32,11 @@ impl<'a> System<'a> for MonsterAI {
             stats.visible_targets.clear();                    
 
             if smart_monster.target_location == Some(*pos) {
-                // log.entries.push(format!("{:?} reached destination, giving up", entity));
+                // log.push(format!("{:?} reached destination, giving up", entity));
                 smart_monster.target_location = None;
             }
             if viewshed.visible_tiles.contains(&player_pos) {
-                // log.entries.push(format!("{:?} can see you", entity));
+                // log.push(format!("{:?} can see you", entity));
                 smart_monster.target_location = Some(Position { x: player_pos.x, y: player_pos.y});
                 stats.current_target = Some(*player_entity);
                 stats.visible_targets.push(*player_entity);
@@ -56,7 +56,7 @@ impl<'a> System<'a> for MonsterAI {
                 let path = rltk::a_star_search(
                     map.xy_idx(pos.x, pos.y),
                     map.xy_idx(player_pos.x, player_pos.y),
-                    &mut *map
+                    &map.grid
                 );
                 if path.success && path.steps.len()>1 {
                     let idx = map.xy_idx(pos.x, pos.y);
@@ -72,7 +72,7 @@ impl<'a> System<'a> for MonsterAI {
                 let path = rltk::a_star_search(
                     map.xy_idx(pos.x, pos.y),
                     map.xy_idx(targ_loc.x, targ_loc.y),
-                    &mut *map
+                    &map.grid
                 );
                 let dice_roll = rng.range(0.0,smart_monster.chase_chance);
                 if path.success && path.steps.len()>1 && dice_roll < smart_monster.chase_chance {
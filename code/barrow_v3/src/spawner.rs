@@ -1,20 +1,11 @@
-use rltk::{ RGB, RandomNumberGenerator };
+use rltk::RandomNumberGenerator;
 use specs::prelude::*;
-use super::{CombatStats, CombatStance, Player, Renderable, Name, Position, Viewshed, Attack, Monster, BlocksTile, SmartMonster, SmartMonsterState };
+use barrow_core::spawner;
+use super::{CombatStats, CombatStance, Attack, SmartMonster, SmartMonsterState };
 
 /// Spawns the player and returns his/her entity object.
 pub fn player(ecs : &mut World, player_x : i32, player_y : i32) -> Entity {
-    ecs
-        .create_entity()
-        .with(Position { x: player_x, y: player_y })
-        .with(Renderable {
-            glyph: rltk::to_cp437('@'),
-            fg: RGB::named(rltk::YELLOW),
-            bg: RGB::named(rltk::BLACK),
-        })
-        .with(Player{})
-        .with(Viewshed{ visible_tiles : Vec::new(), range: 8, dirty: true })
-        .with(Name{name: "Player".to_string() })
+    spawner::player(ecs, player_x, player_y)
         .with(CombatStats{ max_hp: 30, hp: 30, hp_regen: -10, max_ep: 40, ep: 40, ep_regen: -5, defense: 0, power: 3, attack_cost: 5, stance: CombatStance::GuardDown, current_target: None, visible_targets: vec![] })
         .build()
 }
@@ -39,17 +30,7 @@ fn goblin(ecs: &mut World, x: i32, y: i32) { monster(ecs, x, y, rltk::to_cp437('
 fn goblin_knight(ecs: &mut World, x: i32, y: i32) { monster(ecs, x, y, rltk::to_cp437('G'), 25, 40, 3, 1, "Goblin Knight", CombatStance::GuardDown, Attack::Melee, 0.5, 20); }
 
 fn monster<S : ToString>(ecs: &mut World, x: i32, y: i32, glyph : rltk::FontCharType, hp:i32, ep:i32, pow:i32, def:i32, name : S, stance: CombatStance, attack: Attack, chase_chance: f32, ep_threshold: i32) {
-    ecs.create_entity()
-        .with(Position{ x, y })
-        .with(Renderable{
-            glyph,
-            fg: RGB::named(rltk::RED),
-            bg: RGB::named(rltk::BLACK),
-        })
-        .with(Viewshed{ visible_tiles : Vec::new(), range: 8, dirty: true })
-        .with(Monster{})
-        .with(Name{ name : name.to_string() })
-        .with(BlocksTile{})
+    spawner::monster(ecs, x, y, glyph, name)
         .with(CombatStats{ max_hp: hp, hp: hp, hp_regen:-5, max_ep: ep, ep: ep, ep_regen:-5, defense: def, power: pow, attack_cost: 5, stance: CombatStance::GuardDown, current_target: None, visible_targets: vec![] })
         .with(SmartMonster{ 
             state: SmartMonsterState::Asleep,
@@ -1,5 +1,5 @@
 use specs::prelude::*;
-use super::{CombatStats, Action, WaitMove, CombatStance, Name, Player, Position, gamelog::GameLog, RunState, Map, TileType, Viewshed};
+use super::{CombatStats, Action, WaitMove, CombatStance, Name, Position, gamelog::GameLog, Map, TileType, Viewshed};
 use super::Command::*;
 use super::AttackMove::*;
 use super::WaitMove::*;
@@ -32,14 +32,14 @@ impl<'a> System<'a> for ActionSystem {
                 // Check here for any conditions that would override the selected action
                 let subject_stats = combat_stats.get_mut(entity).unwrap(); 
                 if subject_stats.stance == Stun {
-                    log.entries.push(format!("#[red]{} is stunned#[], recovering...", &name.name));
+                    log.push(format!("#[red]{} is stunned#[], recovering...", &name.name));
                     // TODO: use proper command/regen
                     let a = &Action { command: WaitCommand(Wait), cost: -10, stance_after: subject_stats.stance, target: None, position: None };
                     eff_action = *a;
                     // return
                 }    
                 else if action.cost > subject_stats.ep {
-                    log.entries.push(format!("#[yellow]{}#[] has insufficient ep, recovering...", &name.name));    
+                    log.push(format!("#[yellow]{}#[] has insufficient ep, recovering...", &name.name));    
                     // TODO: user proper command/regen
                     let a = &Action { command: WaitCommand(Wait), cost: -10, stance_after: subject_stats.stance, target: None, position: None };
                     eff_action = *a;
@@ -105,9 +105,9 @@ impl<'a> System<'a> for ActionSystem {
                     let ep_damage = attack_ep_damage + reaction_ep_damage;
 
                     if ep_damage != 0 {
-                        log.entries.push(format!("{} {} #[orange]{}#[] for #[orange]{} hp#[] ({} ep).", &name.name, attack_verb_string, &target_name.name, raw_damage, ep_damage));
+                        log.push(format!("{} {} #[orange]{}#[] for #[orange]{} hp#[] ({} ep).", &name.name, attack_verb_string, &target_name.name, raw_damage, ep_damage));
                     } else {
-                        log.entries.push(format!("{} {} #[orange]{}#[] for #[orange]{} hp#[].", &name.name, attack_verb_string, &target_name.name, raw_damage));
+                        log.push(format!("{} {} #[orange]{}#[] for #[orange]{} hp#[].", &name.name, attack_verb_string, &target_name.name, raw_damage));
                     }
 
                     {
@@ -128,7 +128,7 @@ impl<'a> System<'a> for ActionSystem {
                     // TODO: wait move ep recovery
                     if *ep_cost != 0 && subject_stats.ep != subject_stats.max_ep {
                         let ep_string = format!("{}", *ep_cost).replace("-","");
-                        log.entries.push(format!("{} recovers {} ep.", &name.name, ep_string));
+                        log.push(format!("{} recovers {} ep.", &name.name, ep_string));
                     }
                     rest_or_default(&mut subject_stats, *w, *ep_cost);
                     if subject_stats.stance != Stun {
@@ -156,7 +156,7 @@ impl<'a> System<'a> for ActionSystem {
                     subject_stats.last_command = Some(MoveCommand);
                 }
                 _ => {
-                    log.entries.push(format!("Anomaly: {} has an incoherent intent", name.name));
+                    log.push(format!("Anomaly: {} has an incoherent intent", name.name));
                 }
             }
         }
@@ -216,45 +216,4 @@ pub fn rest_or_default(stats: &mut CombatStats, _wait_move: WaitMove, cost: i32)
     } else {
         apply_ep_damage(stats, cost);
     }
-} 
-
-pub fn delete_the_dead(ecs : &mut World) {
-    let mut dead : Vec<Entity> = Vec::new();
-    // Using a scope to make the borrow checker happy
-    {
-        let combat_stats = ecs.read_storage::<CombatStats>();
-        let players = ecs.read_storage::<Player>();
-        let names = ecs.read_storage::<Name>();
-        let entities = ecs.entities();
-        let positions = ecs.read_storage::<Position>();
-        let map = ecs.read_resource::<Map>();
-        let mut log = ecs.write_resource::<GameLog>();
-        for (entity, stats, position) in (&entities, &combat_stats, &positions).join() {
-            let player = players.get(entity);
-            if stats.hp < 1 {
-                match player {
-                    None => {
-                        let victim_name = names.get(entity);
-                        if let Some(victim_name) = victim_name {
-                            log.entries.push(format!("#[orange]{}#[] is dead", &victim_name.name));
-                        }
-                        dead.push(entity)
-                    }
-                    Some(_) => {
-                        let tile_type = map.tiles[map.xy_idx(position.x, position.y)];
-                        let mut runstate = ecs.write_resource::<RunState>();
-                        if *runstate != RunState::GameOver {
-                            log.entries.push(format!("#[red]You died! "));
-                            log.entries.push(format!("#[pink]Press ESCAPE to return to the menu."));
-                            *runstate = RunState::GameOver;
-                        }                         
-                    }
-                }
-            }
-        }
-    }
-
-    for victim in dead {
-        ecs.delete_entity(victim).expect("Unable to delete");
-    }
 }
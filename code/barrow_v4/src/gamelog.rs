@@ -1,3 +0,0 @@
-pub struct GameLog {
-    pub entries : Vec<String>
-}
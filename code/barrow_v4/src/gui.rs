@@ -1,26 +1,18 @@
-use rltk::{ RGB, RGBA, Rltk, Point, VirtualKeyCode };
+use rltk::{ RGB, RGBA, Rltk };
 use rltk::console;
 use specs::prelude::*;
 use super::player::get_available_moves;
-use super::{CombatStats, Player, Monster, gamelog::GameLog, Map, Name, Position, RunState, State, Command, MenuCommand};
+use super::{CombatStats, Player, Monster, Map, Name, Position, Command, MenuCommand};
 use super::Command::*;
 use super::AttackMove::*;
 use super::WaitMove::*;
 use super::CombatStance::*;
 use bracket_terminal::prelude::TextAlign;
 
-#[derive(PartialEq, Copy, Clone)]
-pub enum MainMenuSelection { NewGame, Quit }
-
-#[derive(PartialEq, Copy, Clone)]
-pub enum MainMenuResult { NoSelection{ selected : MainMenuSelection }, Selected{ selected: MainMenuSelection } }
-
-#[derive(PartialEq, Copy, Clone)]
-pub enum GameOverResult { NoSelection, QuitToMenu }
-
 // pub fn draw_stat_bar(text, current_stat, max_stat, x, y, w, text_color,ctx: &mut Rltk)
 
-pub fn draw_ui(ecs: &World, ctx : &mut Rltk) {
+/// Draws on the text console, with the popup for whichever line of it is under the mouse
+pub fn draw_sidebar(ecs: &World, ctx : &mut Rltk) {
     // ctx.draw_box(0, 43, 49, 16, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK));
     // ctx.draw_box(50, 0, 29, 42, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK));
 
@@ -36,10 +28,6 @@ pub fn draw_ui(ecs: &World, ctx : &mut Rltk) {
     let mut target_offset = 1;
     let mut gui_offset = 1;
 
-    let mouse_pos = ctx.mouse_pos();
-
-    ctx.set_active_console(1);
-    ctx.cls();
     let menu_mouse_pos = ctx.mouse_pos();
     let menu_y = menu_mouse_pos.1;
     let mut info_popup : Option<String> = None;
@@ -135,25 +123,6 @@ pub fn draw_ui(ecs: &World, ctx : &mut Rltk) {
             move_offset += 1;           
         }
     }
-    // ctx.target(0);
-
-    let log = ecs.fetch::<GameLog>();
-    // ctx.set_active_console(1);
-    // ctx.cls();
-    let mut y = 29;
-    for s in log.entries.iter().rev() {
-        if y >= 21 { 
-            ctx.printer(1, y, format!("#[white]{}",s), TextAlign::Left,Some(RGBA::named(rltk::BLACK))); 
-        }
-        y -= 1;
-    }
-    ctx.set_active_console(0);
-
-    // Draw mouse cursor
-    ctx.set_bg(mouse_pos.0, mouse_pos.1, RGB::named(rltk::MAGENTA));
-    draw_tooltips(ecs, ctx);
-
-    ctx.set_active_console(1);
 
     // Draw mouse cursor
     if menu_mouse_pos.0 > 50 && info_popup != None {
@@ -205,113 +174,3 @@ fn print_command(command: &MenuCommand) -> String {
     let cost_str = format!("{:3}",command.cost).replace("-","+");
     return format!("{:5} > {:?}  ({})", command_str, command.stance_after, cost_str)
 }
-
-fn draw_tooltips(ecs: &World, ctx : &mut Rltk) {
-    let map = ecs.fetch::<Map>();
-    let names = ecs.read_storage::<Name>();
-    let positions = ecs.read_storage::<Position>();
-
-    let mouse_pos = ctx.mouse_pos();
-    if mouse_pos.0 >= map.width || mouse_pos.1 >= map.height { return; }
-    let mut tooltip : Vec<String> = Vec::new();
-    for (name, position) in (&names, &positions).join() {
-        let idx = map.xy_idx(position.x, position.y);
-        if position.x == mouse_pos.0 && position.y == mouse_pos.1 && map.visible_tiles[idx] {
-            tooltip.push(name.name.to_string());
-        }
-    }
-
-    if !tooltip.is_empty() {
-        let mut width :i32 = 0;
-        for s in tooltip.iter() {
-            if width < s.len() as i32 { width = s.len() as i32; }
-        }
-        width += 3;
-
-        if mouse_pos.0 > 40 {
-            let arrow_pos = Point::new(mouse_pos.0 - 2, mouse_pos.1);
-            let left_x = mouse_pos.0 - width;
-            let mut y = mouse_pos.1;
-            for s in tooltip.iter() {
-                ctx.print_color(left_x, y, RGB::named(rltk::WHITE), RGB::named(rltk::GREY), s);
-                let padding = (width - s.len() as i32)-1;
-                for i in 0..padding {
-                    ctx.print_color(arrow_pos.x - i, y, RGB::named(rltk::WHITE), RGB::named(rltk::GREY), &" ".to_string());
-                }
-                y += 1;
-            }
-            ctx.print_color(arrow_pos.x, arrow_pos.y, RGB::named(rltk::WHITE), RGB::named(rltk::GREY), &"->".to_string());
-        } else {
-            let arrow_pos = Point::new(mouse_pos.0 + 1, mouse_pos.1);
-            let left_x = mouse_pos.0 +3;
-            let mut y = mouse_pos.1;
-            for s in tooltip.iter() {
-                ctx.print_color(left_x + 1, y, RGB::named(rltk::WHITE), RGB::named(rltk::GREY), s);
-                let padding = (width - s.len() as i32)-1;
-                for i in 0..padding {
-                    ctx.print_color(arrow_pos.x + 1 + i, y, RGB::named(rltk::WHITE), RGB::named(rltk::GREY), &" ".to_string());
-                }
-                y += 1;
-            }
-            ctx.print_color(arrow_pos.x, arrow_pos.y, RGB::named(rltk::WHITE), RGB::named(rltk::GREY), &"<-".to_string());
-        }
-    }
-}
-
-pub fn main_menu(gs : &mut State, ctx : &mut Rltk) -> MainMenuResult {
-    let runstate = gs.ecs.fetch::<RunState>();
-
-    ctx.set_active_console(1);
-    ctx.print_color_centered(8, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), "Barrow");
-
-    if let RunState::MainMenu{ menu_selection : selection } = *runstate {
-        if selection == MainMenuSelection::NewGame {
-            ctx.print_color_centered(12, RGB::named(rltk::MAGENTA), RGB::named(rltk::BLACK), "Begin New Game (press Enter)");
-        } else {
-            ctx.print_color_centered(12, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), "Begin New Game");
-        }
-
-        if selection == MainMenuSelection::Quit {
-            ctx.print_color_centered(13, RGB::named(rltk::MAGENTA), RGB::named(rltk::BLACK), "Quit (press Enter)");
-        } else {
-            ctx.print_color_centered(13, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), "Quit");
-        }
-
-        match ctx.key {
-            None => return MainMenuResult::NoSelection{ selected: selection },
-            Some(key) => {
-                match key {
-                    VirtualKeyCode::Space => { return MainMenuResult::Selected{ selected: MainMenuSelection::NewGame } }
-                    VirtualKeyCode::Up => {
-                        let newselection;
-                        match selection {
-                            MainMenuSelection::NewGame => newselection = MainMenuSelection::Quit,
-                            MainMenuSelection::Quit => newselection = MainMenuSelection::NewGame
-                        }
-                        return MainMenuResult::NoSelection{ selected: newselection }
-                    }
-                    VirtualKeyCode::Down => {
-                        let newselection;
-                        match selection {
-                            MainMenuSelection::NewGame => newselection = MainMenuSelection::Quit,
-                            MainMenuSelection::Quit => newselection = MainMenuSelection::NewGame
-                        }
-                        return MainMenuResult::NoSelection{ selected: newselection }
-                    }
-                    VirtualKeyCode::Return => return MainMenuResult::Selected{ selected : selection },
-                    _ => return MainMenuResult::NoSelection{ selected: selection }
-                }
-            }
-        }
-    }
-
-    MainMenuResult::NoSelection { selected: MainMenuSelection::NewGame }
-}
-
-pub fn game_over(ctx : &mut Rltk) -> GameOverResult {
-    match ctx.key {
-        None => GameOverResult::NoSelection,
-        Some(VirtualKeyCode::Escape) => { return GameOverResult::QuitToMenu }
-        Some(_) => GameOverResult::NoSelection
-    }
-}
\ No newline at end of file
@@ -1,146 +1,57 @@
-use std::cmp::Ordering;
-use rltk::{ RGB, Rltk, RandomNumberGenerator, BaseMap, Algorithm2D, Point, FastNoise};
-use super::{Rect, Position};
-use std::cmp::{max, min};
+use rltk::{ RGB, Rltk, RandomNumberGenerator, Point, FastNoise};
+use super::Position;
+use std::ops::{Deref, DerefMut};
 use specs::prelude::*;
+use barrow_core::{Tile, GridMap, MapGrid};
 
-const MAPWIDTH : usize = 50;
-const MAPHEIGHT : usize = 43;
-const MAPCOUNT : usize = MAPHEIGHT * MAPWIDTH;
+const MAPWIDTH : i32 = 50;
+const MAPHEIGHT : i32 = 43;
 
 #[derive(PartialEq, Copy, Clone, Debug)]
 pub enum TileType {
     Wall, Floor, StairsDown
 }
 
-#[derive(Default, Clone)]
+impl Tile for TileType {
+    const WALL: TileType = TileType::Wall;
+    const FLOOR: TileType = TileType::Floor;
+
+    fn blocks(&self) -> bool {
+        *self == TileType::Wall
+    }
+}
+
+/// The dungeon grid, plus the noise that makes the lit walls flicker
+#[derive(Clone)]
 pub struct Map {
-    pub tiles : Vec<TileType>,
-    pub rooms : Vec<Rect>,
-    pub width : i32,
-    pub height : i32,
-    pub revealed_tiles : Vec<bool>,
-    pub visible_tiles : Vec<bool>,
-    pub blocked : Vec<bool>,
-    pub tile_content : Vec<Vec<Entity>>,
+    pub grid : GridMap<TileType>,
     pub noise_seed : u64,
     pub frame_count : u64
 }
 
-impl Map {
-    pub fn xy_idx(&self, x: i32, y: i32) -> usize {
-        (y as usize * self.width as usize) + x as usize
-    }
-
-    fn apply_room_to_map(&mut self, room : &Rect) {
-        for y in room.y1 +1 ..= room.y2 {
-            for x in room.x1 + 1 ..= room.x2 {
-                let idx = self.xy_idx(x, y);
-                self.tiles[idx] = TileType::Floor;
-            }
-        }
-    }
-
-    fn apply_horizontal_tunnel(&mut self, x1:i32, x2:i32, y:i32) {
-        for x in min(x1,x2) ..= max(x1,x2) {
-            let idx = self.xy_idx(x, y);
-            if idx > 0 && idx < self.width as usize * self.height as usize {
-                self.tiles[idx as usize] = TileType::Floor;
-            }
-        }
-    }
-
-    fn apply_vertical_tunnel(&mut self, y1:i32, y2:i32, x:i32) {
-        for y in min(y1,y2) ..= max(y1,y2) {
-            let idx = self.xy_idx(x, y);
-            if idx > 0 && idx < self.width as usize * self.height as usize {
-                self.tiles[idx as usize] = TileType::Floor;
-            }
-        }
-    }
-
-    fn is_exit_valid(&self, x:i32, y:i32) -> bool {
-        if x < 1 || x > self.width-1 || y < 1 || y > self.height-1 { return false; }
-        let idx = self.xy_idx(x, y);
-        !self.blocked[idx]
-    }
-
-    pub fn populate_blocked(&mut self) {
-        for (i,tile) in self.tiles.iter_mut().enumerate() {
-            self.blocked[i] = *tile == TileType::Wall;
-        }
-    }
+impl Deref for Map {
+    type Target = GridMap<TileType>;
+    fn deref(&self) -> &GridMap<TileType> { &self.grid }
+}
 
-    pub fn clear_content_index(&mut self) {
-        for content in self.tile_content.iter_mut() {
-            content.clear();
-        }
-    }
+impl DerefMut for Map {
+    fn deref_mut(&mut self) -> &mut GridMap<TileType> { &mut self.grid }
+}
 
+impl Map {
     /// Makes a new map using the algorithm from http://rogueliketutorials.com/tutorials/tcod/part-3/
     /// This gives a handful of random rooms and corridors joining them together.
     pub fn new_map_rooms_and_corridors() -> Map {
         let mut rng = RandomNumberGenerator::new();
 
         let mut map = Map{
-            tiles : vec![TileType::Wall; MAPCOUNT],
-            rooms : Vec::new(),
-            width : MAPWIDTH as i32,
-            height: MAPHEIGHT as i32,
-            revealed_tiles : vec![false; MAPCOUNT],
-            visible_tiles : vec![false; MAPCOUNT],
-            blocked : vec![false; MAPCOUNT],
-            tile_content : vec![Vec::new(); MAPCOUNT],
+            grid : GridMap::new(MAPWIDTH, MAPHEIGHT),
             noise_seed : 0,
             frame_count : 0
         };
+        map.carve_rooms_and_corridors(&mut rng, 6, 10);
 
-        const MAX_ROOMS : i32 = 30;
-        const MIN_SIZE : i32 = 6;
-        const MAX_SIZE : i32 = 10;
-
-        for _i in 0..MAX_ROOMS {
-            let w = rng.range(MIN_SIZE, MAX_SIZE);
-            let h = rng.range(MIN_SIZE, MAX_SIZE);
-            let x = rng.roll_dice(1, map.width - w - 1) - 1;
-            let y = rng.roll_dice(1, map.height - h - 1) - 1;
-            let new_room = Rect::new(x, y, w, h);
-            let mut ok = true;
-            for other_room in map.rooms.iter() {
-                if new_room.intersect(other_room) { ok = false }
-            }
-            if ok {
-                map.apply_room_to_map(&new_room);
-
-                if !map.rooms.is_empty() {
-                    let (new_x, new_y) = new_room.center();
-                    let (prev_x, prev_y) = map.rooms[map.rooms.len()-1].center();
-                    if rng.range(0,2) == 1 {
-                        map.apply_horizontal_tunnel(prev_x, new_x, prev_y);
-                        map.apply_vertical_tunnel(prev_y, new_y, new_x);
-                    } else {
-                        map.apply_vertical_tunnel(prev_y, new_y, prev_x);
-                        map.apply_horizontal_tunnel(prev_x, new_x, new_y);
-                    }
-                }
-
-                map.rooms.push(new_room);
-            }
-        }
-
-        let first_room = map.rooms[0];
-        let first_room_center = first_room.center();
-
-        let cmp_room_dist = |a:&Rect, b:&Rect| -> Ordering {
-            let a_center = a.center();
-            let b_center = b.center();
-            let a_distance = rltk::DistanceAlg::Pythagoras.distance2d(Point::new(a_center.0, a_center.1), Point::new(first_room_center.0, first_room_center.1));
-            let b_distance = rltk::DistanceAlg::Pythagoras.distance2d(Point::new(b_center.0, b_center.1), Point::new(first_room_center.0, first_room_center.1));
-            return a_distance.partial_cmp(&b_distance).unwrap_or(Ordering::Equal);
-        };
-        // let mut spawn_rooms = map.rooms.clone();
-        map.rooms.sort_by(&cmp_room_dist);
-
+        map.sort_rooms_from_first();
         let stairs_position = map.rooms[map.rooms.len()-1].center();
         let stairs_idx = map.xy_idx(stairs_position.0, stairs_position.1);
         map.tiles[stairs_idx] = TileType::StairsDown;
@@ -149,45 +60,11 @@ impl Map {
     }
 }
 
-impl BaseMap for Map {
-    fn is_opaque(&self, idx:usize) -> bool {
-        self.tiles[idx] == TileType::Wall
-    }
-
-
-    fn get_pathing_distance(&self, idx1:usize, idx2:usize) -> f32 {
-        let w = self.width as usize;
-        let p1 = Point::new(idx1 % w, idx1 / w);
-        let p2 = Point::new(idx2 % w, idx2 / w);
-        rltk::DistanceAlg::Pythagoras.distance2d(p1, p2)
-    }
-
-    fn get_available_exits(&self, idx:usize) -> rltk::SmallVec<[(usize, f32); 10]> {
-        let mut exits = rltk::SmallVec::new();
-        let x = idx as i32 % self.width;
-        let y = idx as i32 / self.width;
-        let w = self.width as usize;
+impl MapGrid for Map {
+    type Tile = TileType;
 
-        // Cardinal directions
-        if self.is_exit_valid(x-1, y) { exits.push((idx-1, 1.0)) };
-        if self.is_exit_valid(x+1, y) { exits.push((idx+1, 1.0)) };
-        if self.is_exit_valid(x, y-1) { exits.push((idx-w, 1.0)) };
-        if self.is_exit_valid(x, y+1) { exits.push((idx+w, 1.0)) };
-
-        // Diagonals
-        if self.is_exit_valid(x-1, y-1) { exits.push(((idx-w)-1, 1.45)); }
-        if self.is_exit_valid(x+1, y-1) { exits.push(((idx-w)+1, 1.45)); }
-        if self.is_exit_valid(x-1, y+1) { exits.push(((idx+w)-1, 1.45)); }
-        if self.is_exit_valid(x+1, y+1) { exits.push(((idx+w)+1, 1.45)); }
-
-        exits
-    }
-}
-
-impl Algorithm2D for Map {
-    fn dimensions(&self) -> Point {
-        Point::new(self.width, self.height)
-    }
+    fn grid(&self) -> &GridMap<TileType> { &self.grid }
+    fn grid_mut(&mut self) -> &mut GridMap<TileType> { &mut self.grid }
 }
 
 pub fn draw_map(ecs: &World, ctx : &mut Rltk) {
@@ -286,7 +163,7 @@ pub fn draw_map(ecs: &World, ctx : &mut Rltk) {
 
         // Move the coordinates
         x += 1;
-        if x > MAPWIDTH as i32-1 {
+        if x > MAPWIDTH-1 {
             x = 0;
             y += 1;
         }
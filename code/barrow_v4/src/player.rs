@@ -1,82 +1,31 @@
-use rltk::{VirtualKeyCode, Rltk, Point, console};
+use rltk::{VirtualKeyCode, Point, console};
 use specs::prelude::*;
-use std::cmp::{max, min};
-use super::{Position, Player, State, CombatStats, GameLog, Map, Monster, RunState, Action, MenuCommand, Command, TileType };
+use barrow_core::player::{self, Step};
+use super::{Position, Player, CombatStats, GameLog, Map, RunState, Action, MenuCommand, Command, TileType };
 use super::Command::*;
 use super::AttackMove::*;
 use super::WaitMove::*;
 use super::CombatStance::*;
 
+/// Moving is an action like any other, so a step only asks to be taken
 pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
-    let mut positions = ecs.write_storage::<Position>();
-    let players = ecs.read_storage::<Player>();
-    let entities = ecs.entities();
-    let combat_stats = ecs.read_storage::<CombatStats>();
-    let map = ecs.fetch::<Map>();
-    let mut actions = ecs.write_storage::<Action>();
-
-    for (entity, _player, pos) in (&entities, &players, &mut positions).join() {
-        if pos.x + delta_x < 1 || pos.x + delta_x > map.width-1 || pos.y + delta_y < 1 || pos.y + delta_y > map.height-1 { return; }
-        let destination_idx = map.xy_idx(pos.x + delta_x, pos.y + delta_y);
-
-        for potential_target in map.tile_content[destination_idx].iter() {
-            let target = combat_stats.get(*potential_target);
-            if let Some(_target) = target {
-                actions.insert(entity, Action{ 
-                    command: AttackCommand(Melee),
-                    cost: 0,
-                    stance_after: Ready,
-                    target: Some(*potential_target), 
-                    position: None }).expect("Add target failed");
-                return;
-            }
-        }
-
-        if !map.blocked[destination_idx] {
-            let new_x = min(79 , max(0, pos.x + delta_x));
-            let new_y = min(49, max(0, pos.y + delta_y));
-            actions.insert(entity, Action{ 
-                command: MoveCommand,
-                cost: -2,
-                stance_after: Ready,
-                target: None, 
-                position: Some(Position { x: new_x, y: new_y })
-            }).expect("Move intent failed");
-        }
-    }
-}
-
-pub fn update_targeting(ecs: &World, _ctx: &mut Rltk) {
-    let mut combat_stats = ecs.write_storage::<CombatStats>();
-    let mut players = ecs.write_storage::<Player>();
-
-    let monsters = ecs.read_storage::<Monster>();
-    let positions = ecs.read_storage::<Position>();
-    let entities = ecs.entities();
-    let map = ecs.fetch::<Map>();
-
-    for (_player, player_stats) in (&mut players, &mut combat_stats).join() {
-        let mut current_target_seen = false;
-        player_stats.visible_targets.clear();
-    
-        for (entity, _monster, position) in (&entities, &monsters, &positions).join() {
-            let idx = map.xy_idx(position.x, position.y);
-            if map.visible_tiles[idx] == true {
-                if player_stats.current_target == None {
-                    player_stats.current_target = Some(entity);
-                    current_target_seen = true;
-                } else if player_stats.current_target == Some(entity) {
-                    current_target_seen = true;
-                }
-                player_stats.visible_targets.push(entity);
-            }
-        }    
-
-        if current_target_seen == false && player_stats.current_target != None {
-            player_stats.current_target = None;
-        }    
-    }
-    return; 
+    let player_entity = *ecs.fetch::<Entity>();
+    let action = match player::step::<Map, CombatStats>(ecs, delta_x, delta_y) {
+        Step::Attack(target) => Action{
+            command: AttackCommand(Melee),
+            cost: 0,
+            stance_after: Ready,
+            target: Some(target),
+            position: None },
+        Step::Move(to) => Action{
+            command: MoveCommand,
+            cost: -2,
+            stance_after: Ready,
+            target: None,
+            position: Some(to) },
+        Step::Stay => return
+    };
+    ecs.write_storage::<Action>().insert(player_entity, action).expect("Move intent failed");
 }
 
 pub fn try_descend(ecs: &World) -> RunState {
@@ -91,9 +40,9 @@ pub fn try_descend(ecs: &World) -> RunState {
     let tile_type = map.tiles[map.xy_idx(player_pos.x, player_pos.y)];
 
     if map.tiles[map.xy_idx(player_pos.x, player_pos.y)] == TileType::StairsDown {
-        log.entries.push(format!("You see descend deeper into the barrow..."));
-        log.entries.push(format!("#[cyan](This is the end for now)#[]"));
-        log.entries.push(format!("#[pink]Press ESCAPE to return to the menu"));
+        log.push(format!("You see descend deeper into the barrow..."));
+        log.push(format!("#[cyan](This is the end for now)#[]"));
+        log.push(format!("#[pink]Press ESCAPE to return to the menu"));
         return RunState::GameOver;    
     } else {
         return RunState::AwaitingInput
@@ -101,24 +50,6 @@ pub fn try_descend(ecs: &World) -> RunState {
 }
 
 
-pub fn try_select_target(selection: usize, ecs: &World) -> RunState {
-    let mut combat_stats = ecs.write_storage::<CombatStats>();
-    let players = ecs.read_storage::<Player>();
-
-    // console::log(format!("selected target {}", selection));
-    for (_player,stats) in (&players, &mut combat_stats).join() {
-        if stats.visible_targets.len() >= selection {
-            stats.current_target = Some(stats.visible_targets[selection - 1]);
-            // console::log(format!("selection ok"));
-
-        } else {
-            // console::log(format!("selection failed, only {} targets visible", stats.visible_targets.len()));
-        }
-    }
-
-    return RunState::AwaitingInput
-}
-
 pub fn get_available_moves(player_stats: &CombatStats) -> Vec<MenuCommand> {
     match player_stats.stance {
         Ready => {
@@ -253,69 +184,36 @@ pub fn try_attack_menu(offset:usize, ecs: &World) -> RunState {
 
 }
 
-pub fn player_input(gs: &mut State, ctx: &mut Rltk) -> RunState {
+pub fn player_input(ecs: &mut World, key: VirtualKeyCode) -> RunState {
     // Player movement
-    match ctx.key {
-        None => { return RunState::AwaitingInput } // Nothing happened
-        Some(key) => match key {
-            VirtualKeyCode::Left |
-            VirtualKeyCode::Numpad4 |
-            VirtualKeyCode::A => try_move_player(-1, 0, &mut gs.ecs),
-
-            VirtualKeyCode::Right |
-            VirtualKeyCode::Numpad6 |
-            VirtualKeyCode::D => try_move_player(1, 0, &mut gs.ecs),
-
-            VirtualKeyCode::Up |
-            VirtualKeyCode::Numpad8 |
-            VirtualKeyCode::W => try_move_player(0, -1, &mut gs.ecs),
-
-            VirtualKeyCode::Down |
-            VirtualKeyCode::Numpad2 |
-            VirtualKeyCode::S => try_move_player(0, 1, &mut gs.ecs),
-
-            // Diagonals
-            VirtualKeyCode::Numpad7 |
-            VirtualKeyCode::Q => try_move_player(-1, -1, &mut gs.ecs),
-
-            VirtualKeyCode::Numpad9 |
-            VirtualKeyCode::E => try_move_player(1, -1, &mut gs.ecs),
-
-            VirtualKeyCode::Numpad3 |
-            VirtualKeyCode::C => try_move_player(1, 1, &mut gs.ecs),
-
-            VirtualKeyCode::Numpad1 |
-            VirtualKeyCode::Z => try_move_player(-1, 1, &mut gs.ecs),
+    if let Some((delta_x, delta_y)) = player::movement(key) {
+        try_move_player(delta_x, delta_y, ecs);
+        return RunState::PlayerTurn;
+    }
 
-            // Num Keys
-            VirtualKeyCode::Key1 => return try_select_target(1, &gs.ecs),
-            VirtualKeyCode::Key2 => return try_select_target(2, &gs.ecs),
-            VirtualKeyCode::Key3 => return try_select_target(3, &gs.ecs),
-            VirtualKeyCode::Key4 => return try_select_target(4, &gs.ecs),
-            VirtualKeyCode::Key5 => return try_select_target(5, &gs.ecs),
-            VirtualKeyCode::Key6 => return try_select_target(6, &gs.ecs),
-            VirtualKeyCode::Key7 => return try_select_target(7, &gs.ecs),
-            VirtualKeyCode::Key8 => return try_select_target(8, &gs.ecs),
-            VirtualKeyCode::Key9 => return try_select_target(9, &gs.ecs),
+    // Num Keys
+    if let Some(selection) = player::target_number(key) {
+        player::select_target::<CombatStats>(ecs, selection);
+        return RunState::AwaitingInput;
+    }
 
-            // Skip
-            VirtualKeyCode::Numpad5 => return try_attack_menu(0, &gs.ecs),
-            VirtualKeyCode::Space => return try_attack_menu(0, &gs.ecs),
-            VirtualKeyCode::X => return try_attack_menu(0, &gs.ecs),
+    match key {
+        // Skip
+        VirtualKeyCode::Numpad5 => try_attack_menu(0, ecs),
+        VirtualKeyCode::Space => try_attack_menu(0, ecs),
+        VirtualKeyCode::X => try_attack_menu(0, ecs),
 
-            // Attack
-            VirtualKeyCode::J => return try_attack_menu(1, &gs.ecs),
-            VirtualKeyCode::K => return try_attack_menu(2, &gs.ecs),
-            VirtualKeyCode::L => return try_attack_menu(3, &gs.ecs),
+        // Attack
+        VirtualKeyCode::J => try_attack_menu(1, ecs),
+        VirtualKeyCode::K => try_attack_menu(2, ecs),
+        VirtualKeyCode::L => try_attack_menu(3, ecs),
 
-            VirtualKeyCode::N => return try_attack_menu(4, &gs.ecs),
-            VirtualKeyCode::M => return try_attack_menu(5, &gs.ecs),
+        VirtualKeyCode::N => try_attack_menu(4, ecs),
+        VirtualKeyCode::M => try_attack_menu(5, ecs),
 
-            // Descend
-            VirtualKeyCode::Period => return try_descend(&gs.ecs),
+        // Descend
+        VirtualKeyCode::Period => try_descend(ecs),
 
-            _ => { return RunState::AwaitingInput }
-        },
+        _ => RunState::AwaitingInput
     }
-    RunState::PlayerTurn
 }
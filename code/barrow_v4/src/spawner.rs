@@ -1,6 +1,7 @@
-use rltk::{ RGB, RandomNumberGenerator };
+use rltk::RandomNumberGenerator;
 use specs::prelude::*;
-use super::{CombatStats, AttackMove, CombatStance, Player, Renderable, Name, Position, Viewshed, Monster, BlocksTile, SmartMonster, SmartMonsterState };
+use barrow_core::spawner;
+use super::{CombatStats, AttackMove, CombatStance, SmartMonster, SmartMonsterState };
 use super::Command::*;
 use super::AttackMove::*;
 use super::WaitMove::*;
@@ -9,17 +10,7 @@ use super::CombatStance::*;
 /// Spawns the player and returns his/her entity object.
 pub fn player(ecs : &mut World, player_x : i32, player_y : i32, stats: Option<CombatStats>) -> Entity {
     let player_stats = stats.unwrap_or(CombatStats{ max_hp: 30, hp: 30, hp_regen: -10, max_ep: 40, ep: 40, ep_regen: -5, defense: 0, power: 4, attack_cost: 5, stance: Ready, current_target: None, visible_targets: vec![], last_command: None });
-    ecs
-        .create_entity()
-        .with(Position { x: player_x, y: player_y })
-        .with(Renderable {
-            glyph: rltk::to_cp437('@'),
-            fg: RGB::named(rltk::YELLOW),
-            bg: RGB::named(rltk::BLACK),
-        })
-        .with(Player{})
-        .with(Viewshed{ visible_tiles : Vec::new(), range: 8, dirty: true })
-        .with(Name{name: "Player".to_string() })
+    spawner::player(ecs, player_x, player_y)
         .with(player_stats)
         .build()
 }
@@ -44,17 +35,7 @@ pub fn goblin(ecs: &mut World, x: i32, y: i32) { monster(ecs, x, y, rltk::to_cp4
 pub fn hobgoblin(ecs: &mut World, x: i32, y: i32) { monster(ecs, x, y, rltk::to_cp437('h'), 25, 45, 15, 4, 1, "Hobgoblin", Guard, Bash, 0.5, 20, 0.3); }
 
 fn monster<S : ToString>(ecs: &mut World, x: i32, y: i32, glyph : rltk::FontCharType, hp:i32, ep:i32, cost:i32, pow:i32, def:i32, name : S, stance: CombatStance, attack: AttackMove, chase_chance: f32, ep_threshold: i32, recover_ep_chance: f32) {
-    ecs.create_entity()
-        .with(Position{ x, y })
-        .with(Renderable{
-            glyph,
-            fg: RGB::named(rltk::RED),
-            bg: RGB::named(rltk::BLACK),
-        })
-        .with(Viewshed{ visible_tiles : Vec::new(), range: 8, dirty: true })
-        .with(Monster{})
-        .with(Name{ name : name.to_string() })
-        .with(BlocksTile{})
+    spawner::monster(ecs, x, y, glyph, name)
         .with(CombatStats{ max_hp: hp, hp: hp, hp_regen:-5, max_ep: ep, ep: ep, ep_regen:-10, defense: def, power: pow, attack_cost: 5, stance: Ready, current_target: None, visible_targets: vec![], last_command: None })
         .with(SmartMonster{ 
             state: SmartMonsterState::Asleep,
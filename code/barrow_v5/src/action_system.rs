@@ -1,5 +1,7 @@
 use specs::prelude::*;
-use super::{CombatStats, Action, WaitMove, CombatStance, Container, Item, Name, Player, Position, gamelog::GameLog, RunState, Map, Viewshed};
+use std::collections::{HashMap, HashSet};
+use super::{CombatStats, SufferDamage, Boss, Inventory, Corpse, spawner, Command, Renderable, Action, WaitMove, CombatStance, Container, Item, Name, Player, Position, RunState, Map, Viewshed, EventQueue, GameEvent, PickedUpItem, SmartMonster, SmartMonsterState, TileType, TrapKind};
+use barrow_core::intent::damage_formula;
 use super::Containers::*;
 use super::Items::*;
 use super::Command::*;
@@ -15,7 +17,7 @@ impl<'a> System<'a> for ActionSystem {
     #[allow(clippy::type_complexity)]
     type SystemData = ( Entities<'a>,
                         ReadExpect<'a, Entity>,
-                        WriteExpect<'a, GameLog>,
+                        WriteExpect<'a, EventQueue>,
                         WriteStorage<'a, Action>,
                         ReadStorage<'a, Name>,
                         WriteStorage<'a, Player>,
@@ -25,11 +27,26 @@ impl<'a> System<'a> for ActionSystem {
                         WriteStorage<'a, Viewshed>,
                         WriteExpect<'a, rltk::RandomNumberGenerator>,
                         ReadStorage<'a, Item>,
-                        ReadStorage<'a, Container>
+                        ReadStorage<'a, Container>,
+                        WriteStorage<'a, SmartMonster>,
+                        WriteStorage<'a, SufferDamage>,
+                        WriteStorage<'a, Inventory>
                     );
 
     fn run(&mut self, data : Self::SystemData) {
-        let (entities, player_entity, mut log, mut actions, names, mut player, mut combat_stats, mut map, mut positions, mut viewsheds, mut rng, items, containers) = data;
+        let (entities, player_entity, mut events, mut actions, names, mut player, mut combat_stats, mut map, mut positions, mut viewsheds, mut rng, items, containers, mut smart_monsters, mut inflict_damage, mut inventories) = data;
+        let mut doors_changed = false;
+        let mut alarm : Option<Position> = None;
+        // items picked up this pass; tile_content is only rebuilt between passes, so a second
+        // entity stepping onto the same tile would otherwise find them still lying there
+        let mut taken : HashSet<Entity> = HashSet::new();
+
+        // attacks read the stance each fighter started the pass in, not one changed by an
+        // action resolved earlier in the loop
+        let mut started_in : HashMap<Entity, (CombatStance, Option<Command>)> = HashMap::new();
+        for (entity, stats) in (&entities, &combat_stats).join() {
+            started_in.insert(entity, (stats.stance, stats.last_command));
+        }
 
         for (entity, name, action) in (&entities, &names, &actions).join() {
             let eff_action: Action;
@@ -37,15 +54,14 @@ impl<'a> System<'a> for ActionSystem {
                 // Check here for any conditions that would override the selected action
                 let subject_stats = combat_stats.get_mut(entity).unwrap(); 
                 if subject_stats.stance == Stun {
-                    log.entries.push(format!("#[red]{} is stunned#[], recovering...", &name.name));
-                    log.entries.push(format!("(you cannot attack, move or wait to recover)"));
+                    events.emit(GameEvent::Stunned { entity: entity, name: name.name.clone() });
                     // TODO: use proper command/regen
                     let a = &Action { command: WaitCommand(Wait), cost: -10, stance_after: subject_stats.stance, target: None, position: None };
                     eff_action = *a;
                     // return
                 }
                 else if action.cost > subject_stats.ep {
-                    log.entries.push(format!("#[yellow]{}#[] has insufficient ep, recovering...", &name.name));    
+                    events.emit(GameEvent::Exhausted { entity: entity, name: name.name.clone() });
                     // TODO: user proper command/regen
                     let a = &Action { command: WaitCommand(Wait), cost: -10, stance_after: subject_stats.stance, target: None, position: None };
                     eff_action = *a;
@@ -59,8 +75,7 @@ impl<'a> System<'a> for ActionSystem {
                 Action{ command: AttackCommand(a), target: Some(target), cost: ep_cost, .. } => {
                     let subject_stats = combat_stats.get(entity).unwrap();
                     let target_stats = combat_stats.get(*target).unwrap();
-                    let target_stance = target_stats.stance;
-                    let target_last_command = target_stats.last_command;
+                    let (target_stance, target_last_command) = started_in[target];
                     let pow_adj = match (a,target_stance) {
                         (Melee, Guard) => -1,
                         (Melee, _ ) => 0,
@@ -94,13 +109,6 @@ impl<'a> System<'a> for ActionSystem {
                         (_, _) => 0
                     };
 
-                    let attack_verb_string = match a {
-                        Melee => "attacks",
-                        Smash => "smashes",
-                        Bash  => "shield bashes",
-                        _     => "attacks"
-                    };
-
                     let reaction_ep_damage = match (a, target_last_command) {
                         // TODO: fill out
                         (Bash, Some(WaitCommand(Block))) => -10,
@@ -111,45 +119,53 @@ impl<'a> System<'a> for ActionSystem {
 
                     let ep_damage = attack_ep_damage + reaction_ep_damage;
 
-                    if ep_damage != 0 {
-                        log.entries.push(format!("{} {} #[orange]{}#[] for #[orange]{} hp#[] ({} ep).", &name.name, attack_verb_string, &target_name.name, raw_damage, ep_damage));
-                    } else {
-                        log.entries.push(format!("{} {} #[orange]{}#[] for #[orange]{} hp#[].", &name.name, attack_verb_string, &target_name.name, raw_damage));
-                    }
+                    events.emit(GameEvent::Attacked {
+                        attacker: entity,
+                        attacker_name: name.name.clone(),
+                        target: *target,
+                        target_name: target_name.name.clone(),
+                        target_pos: *positions.get(*target).unwrap(),
+                        attack: *a,
+                        hp_damage: raw_damage,
+                        ep_damage: ep_damage
+                    });
 
                     match (a, target_stance, target_last_command) {
-                        (Bash, Guard, Some(WaitCommand(Block))) => log.entries.push(format!("{}'s block is super effective!", &target_name.name)),
-                        (Bash, Guard, _) => log.entries.push(format!("{}'s bash attack is super effective!", &name.name)),
-                        (Smash, _, Some(WaitCommand(Fend))) => log.entries.push(format!("{}'s fend is super effective!", &target_name.name)),
-                        (_, Stun, _) => log.entries.push(format!("{} is stunned, {}'s attack is super effective", &target_name.name, &name.name)),
+                        (Bash, Guard, Some(WaitCommand(Block))) => events.emit(GameEvent::SuperEffective { name: target_name.name.clone(), technique: String::from("block") }),
+                        (Bash, Guard, _) => events.emit(GameEvent::SuperEffective { name: name.name.clone(), technique: String::from("bash attack") }),
+                        (Smash, _, Some(WaitCommand(Fend))) => events.emit(GameEvent::SuperEffective { name: target_name.name.clone(), technique: String::from("fend") }),
+                        (_, Stun, _) => events.emit(GameEvent::SuperEffective { name: name.name.clone(), technique: String::from("attack on a stunned foe") }),
                         _ => {}
                     };
 
                     {
                         let subject_stats = combat_stats.get_mut(entity).unwrap();
-                        apply_ep_damage(subject_stats,*ep_cost);
+                        let stance_before = subject_stats.stance;
+                        SufferDamage::new_ep_damage(&mut inflict_damage, entity, *ep_cost);
                         subject_stats.stance = action.stance_after;
                         subject_stats.last_command = Some(AttackCommand(*a));
+                        if subject_stats.stance != stance_before {
+                            events.emit(GameEvent::StanceChanged { entity: entity, name: name.name.clone(), from: stance_before, to: subject_stats.stance });
+                        }
                     }
-                    {
-                        let target_stats = combat_stats.get_mut(*target).unwrap();
-                        apply_hp_damage(target_stats, raw_damage);
-                        apply_ep_damage(target_stats, ep_damage);
-                    }
+                    SufferDamage::new_hp_damage(&mut inflict_damage, *target, raw_damage);
+                    SufferDamage::new_ep_damage(&mut inflict_damage, *target, ep_damage);
                 }
 
                 Action{ command: WaitCommand(w), target: None, cost: ep_cost, .. } => {
-                    let mut subject_stats = combat_stats.get_mut(entity).unwrap(); 
+                    let subject_stats = combat_stats.get_mut(entity).unwrap(); 
                     let player_inv = player.get_mut(entity);
 
                     // TODO: wait move ep recovery
                     if *ep_cost != 0 && subject_stats.ep != subject_stats.max_ep {
-                        let ep_string = format!("{}", *ep_cost).replace("-","");
-                        log.entries.push(format!("{} recovers {} ep.", &name.name, ep_string));
+                        events.emit(GameEvent::Recovered { entity: entity, name: name.name.clone(), ep: ep_cost.abs() });
+                    }
+                    if rest_or_default(entity, subject_stats, *w, *ep_cost, player_inv, &mut inflict_damage) {
+                        events.emit(GameEvent::AteFood { entity: entity, name: name.name.clone() });
                     }
-                    rest_or_default(&mut subject_stats, *w, *ep_cost, player_inv);
+                    // a fighter too tired to make its move only rests, and keeps its stance
                     if subject_stats.stance != Stun {
-                        subject_stats.stance = action.stance_after;
+                        subject_stats.stance = eff_action.stance_after;
                     }
                     subject_stats.last_command = Some(WaitCommand(*w));
 
@@ -159,21 +175,30 @@ impl<'a> System<'a> for ActionSystem {
                     let mut viewshed = viewsheds.get_mut(entity).unwrap();
                     let p = player.get_mut(entity);
 
-                    let mut container_open = false;
+                    let mut interacted = false;
 
-                    {
+                    // bumping a closed door opens it instead of moving
+                    let door_idx = map.xy_idx(*x, *y);
+                    if map.tiles[door_idx] == TileType::DoorClosed {
+                        map.tiles[door_idx] = TileType::DoorOpen;
+                        events.emit(GameEvent::DoorOpened { entity: entity, name: name.name.clone() });
+                        doors_changed = true;
+                        interacted = true;
+                    }
+
+                    if !interacted {
                         let contents = &map.tile_content[map.xy_idx(*x,*y)];
                         for c in contents {
                             let container = containers.get(*c);
                             match container { 
                                 Some(Container { container: Barrel, .. } ) => {
-                                    container_open = true;
-                                    log.entries.push(format!("{} smashes the barrel.", &name.name));
+                                    interacted = true;
+                                    events.emit(GameEvent::ContainerOpened { name: name.name.clone(), container: Barrel });
                                     entities.delete(*c).expect("Unable to delete");
                                 }
                                 Some(Container { container: Treasure, .. } ) => {
-                                    container_open = true;
-                                    log.entries.push(format!("{} opens the treasure chest!", &name.name));
+                                    interacted = true;
+                                    events.emit(GameEvent::ContainerOpened { name: name.name.clone(), container: Treasure });
                                     entities.delete(*c).expect("Unable to delete");
                                 }
 
@@ -182,7 +207,7 @@ impl<'a> System<'a> for ActionSystem {
                         }
                     }
 
-                    if !container_open {
+                    if !interacted {
                         pos.x = *x;
                         pos.y = *y;
     
@@ -192,12 +217,24 @@ impl<'a> System<'a> for ActionSystem {
                         }
                         viewshed.dirty = true;
     
-                        let mut subject_stats = combat_stats.get_mut(entity).unwrap(); 
+                        let subject_stats = combat_stats.get_mut(entity).unwrap(); 
                         // TODO: move ep regen?
-                        move_regen(&mut subject_stats);
+                        move_regen(entity, subject_stats, &mut inflict_damage);
                         subject_stats.stance = action.stance_after;
                         subject_stats.last_command = Some(MoveCommand);
     
+                        // hidden traps only catch the player; monsters know their way around the barrow,
+                        // and a trap that has been found is stepped over
+                        if p.is_some() {
+                            if let TileType::Trap { kind, hidden: true } = map.tiles[idx] {
+                                map.tiles[idx] = TileType::Trap { kind: kind, hidden: false };
+                                let damage = if kind == TrapKind::Dart { rng.roll_dice(1, 4) + map.depth } else { 0 };
+                                SufferDamage::new_hp_damage(&mut inflict_damage, entity, damage);
+                                if kind == TrapKind::Alarm { alarm = Some(Position { x: *x, y: *y }); }
+                                events.emit(GameEvent::TrapSprung { entity: entity, kind: kind, damage: damage });
+                            }
+                        }
+
                         // check new tile contents
                         if p.is_some() {
                             let mut player_inv = p.unwrap();
@@ -205,39 +242,69 @@ impl<'a> System<'a> for ActionSystem {
                             // let contents = &map.tile_content[map.xy_idx(*x,*y)];
                             // console::log(format!("tile contents: {:?}", contents));
                             for c in contents {
+                                if taken.contains(c) { continue; }
                                 {
                                     let i = items.get(*c);
                                     match i { 
                                         Some(Item { item: Coin(i), .. } ) => { 
-                                            log.entries.push(format!("You pick up {} coins from the ground.", i));
+                                            events.emit(GameEvent::PickedUp { entity: entity, item: PickedUpItem::Coins(*i) });
                                             player_inv.coin = player_inv.coin + i;
+                                            taken.insert(*c);
                                             entities.delete(*c).expect("Unable to delete");
                                         }
                                         Some(Item { item: Amulet, ..}) => {
-                                            log.entries.push(format!("You take the Amulet of Yendor, and feel its dark power course through your body."));
-                                            log.entries.push(format!("Now you must escape the dungeon before the darkness consumes you!"));
+                                            events.emit(GameEvent::PickedUp { entity: entity, item: PickedUpItem::Amulet });
                                             player_inv.has_amulet = true;
                                             player_inv.atk_bonus = 6;
                                             player_inv.def_bonus = 6;
                                             subject_stats.hp = subject_stats.max_hp;
                                             subject_stats.power = 10;
                                             subject_stats.defense = 6;
+                                            taken.insert(*c);
                                             entities.delete(*c).expect("Unable to delete");
                                         }
                                         _ => {}
                                     }
                                 }
                             }
-                        }    
+                        } else if let Some(inventory) = inventories.get_mut(entity) {
+                            // looters pocket any coins on the tile they step onto
+                            if inventory.picks_up {
+                                let idx = map.xy_idx(*x, *y);
+                                for c in map.tile_content[idx].iter() {
+                                    if let Some(item @ Item { item: Coin(_), .. }) = items.get(*c) {
+                                        if !taken.insert(*c) { continue; }
+                                        inventory.items.push(item.clone());
+                                        entities.delete(*c).expect("Unable to delete");
+                                        if map.visible_tiles[idx] {
+                                            events.emit(GameEvent::MonsterPickedUp { name: name.name.clone(), item: names.get(*c).map_or(String::from("something"), |n| n.name.clone()) });
+                                        }
+                                    }
+                                }
+                            }
+                        }
                     }
 
                 }
                 _ => {
-                    log.entries.push(format!("Anomaly: {} has an incoherent intent", name.name));
+                    events.emit(GameEvent::Anomaly { name: name.name.clone() });
                 }
             }
         }
         actions.clear();
+
+        if doors_changed {
+            for viewshed in (&mut viewsheds).join() {
+                viewshed.dirty = true;
+            }
+        }
+        // an alarm sends every monster on the level toward the plate
+        if let Some(alarm_pos) = alarm {
+            for smart_monster in (&mut smart_monsters).join() {
+                smart_monster.state = SmartMonsterState::Attacking;
+                smart_monster.target_location = Some(alarm_pos);
+            }
+        }
     }
 }
 
@@ -268,41 +335,35 @@ pub fn apply_ep_damage( stats: &mut CombatStats, amount: i32) {
     }
 }
 
-pub fn damage_formula(rng: &mut rltk::RandomNumberGenerator, attacker_pow:i32, target_def:i32) -> i32 {
-    let eff_atk = i32::max(0, attacker_pow - target_def);
-    let random_atk_max = 2 + ((2_f32 * eff_atk as f32)/3_f32).ceil() as i32;
-    let random_atk = rng.range(0,random_atk_max);
-    let damage = eff_atk + random_atk;
-    return damage
-}
-
-pub fn move_regen(stats: &mut CombatStats) {
+pub fn move_regen(entity: Entity, stats: &CombatStats, inflict_damage: &mut WriteStorage<SufferDamage>) {
     if stats.stance == CombatStance::Guard { return; };
     if stats.current_target == None {
-        apply_ep_damage(stats, stats.ep_regen);
+        SufferDamage::new_ep_damage(inflict_damage, entity, stats.ep_regen);
     }  else {
-        apply_ep_damage(stats, stats.ep_regen / 2);
+        SufferDamage::new_ep_damage(inflict_damage, entity, stats.ep_regen / 2);
     }
 }
 
-pub fn rest_or_default(stats: &mut CombatStats, _wait_move: WaitMove, cost: i32, player: Option<&mut Player>) {
-    if stats.current_target == None && player.is_some() && stats.hp < stats.max_hp {
-        let p = player.unwrap();
-        if p.food > 0 {
-            apply_hp_damage(stats, stats.hp_regen);
-            apply_ep_damage(stats, cost);
-            // todo: checks, etc.
-            p.food = p.food - 1;    
-        } else {
-            apply_ep_damage(stats, cost);
+/// Returns true if food was eaten to recover hp
+pub fn rest_or_default(entity: Entity, stats: &CombatStats, _wait_move: WaitMove, cost: i32, player: Option<&mut Player>, inflict_damage: &mut WriteStorage<SufferDamage>) -> bool {
+    SufferDamage::new_ep_damage(inflict_damage, entity, cost);
+    if stats.current_target == None && stats.hp < stats.max_hp {
+        if let Some(p) = player {
+            if p.food > 0 {
+                SufferDamage::new_hp_damage(inflict_damage, entity, stats.hp_regen);
+                // todo: checks, etc.
+                p.food = p.food - 1;
+                return true;
+            }
         }
-    } else {
-        apply_ep_damage(stats, cost);
     }
+    false
 } 
 
 pub fn delete_the_dead(ecs : &mut World) {
     let mut dead : Vec<Entity> = Vec::new();
+    // what each slain monster leaves behind: its corpse and everything it carried
+    let mut remains : Vec<((i32, i32), String, Vec<Item>)> = Vec::new();
     // Using a scope to make the borrow checker happy
     {
         let combat_stats = ecs.read_storage::<CombatStats>();
@@ -310,24 +371,31 @@ pub fn delete_the_dead(ecs : &mut World) {
         let names = ecs.read_storage::<Name>();
         let entities = ecs.entities();
         let positions = ecs.read_storage::<Position>();
-        let _map = ecs.read_resource::<Map>();
-        let mut log = ecs.write_resource::<GameLog>();
-        for (entity, stats, _position) in (&entities, &combat_stats, &positions).join() {
+        let renderables = ecs.read_storage::<Renderable>();
+        let bosses = ecs.read_storage::<Boss>();
+        let inventories = ecs.read_storage::<Inventory>();
+        let mut events = ecs.write_resource::<EventQueue>();
+        for (entity, stats, position) in (&entities, &combat_stats, &positions).join() {
+            let glyph = renderables.get(entity).map_or(rltk::to_cp437('%'), |r| r.glyph);
             let player = players.get(entity);
             if stats.hp < 1 {
                 match player {
                     None => {
                         let victim_name = names.get(entity);
                         if let Some(victim_name) = victim_name {
-                            log.entries.push(format!("#[orange]{}#[] is dead", &victim_name.name));
+                            events.emit(GameEvent::Died { entity: entity, name: victim_name.name.clone(), is_player: false, pos: *position, glyph: glyph });
+                            let carried = inventories.get(entity).map_or(vec![], |i| i.items.clone());
+                            remains.push(((position.x, position.y), victim_name.name.clone(), carried));
+                        }
+                        if bosses.get(entity).map_or(false, |b| b.stairs_sealed) {
+                            events.emit(GameEvent::StairsUnsealed);
                         }
                         dead.push(entity)
                     }
                     Some(_) => {
                         let mut runstate = ecs.write_resource::<RunState>();
                         if *runstate != RunState::GameOver {
-                            log.entries.push(format!("#[red]You died! "));
-                            log.entries.push(format!("#[pink]Press ESCAPE to return to the menu."));
+                            events.emit(GameEvent::Died { entity: entity, name: String::from("Player"), is_player: true, pos: *position, glyph: glyph });
                             *runstate = RunState::GameOver;
                         }                         
                     }
@@ -339,4 +407,63 @@ pub fn delete_the_dead(ecs : &mut World) {
     for victim in dead {
         ecs.delete_entity(victim).expect("Unable to delete");
     }
+
+    for (loc, name, carried) in remains {
+        spawner::corpse(ecs, loc, &name);
+        for item in carried.iter() {
+            spawner::drop_item(ecs, loc, item);
+        }
+    }
+}
+
+/// Corpses rot a little every turn, and are gone once they have rotted away
+pub fn rot_corpses(ecs: &World) {
+    let entities = ecs.entities();
+    let mut corpses = ecs.write_storage::<Corpse>();
+    for (entity, corpse) in (&entities, &mut corpses).join() {
+        corpse.rot -= 1;
+        if corpse.rot <= 0 {
+            entities.delete(entity).expect("Unable to delete");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ruleset::tests::{arena, fighter};
+
+    #[test]
+    fn two_looters_stepping_onto_one_coin_get_it_once() {
+        let mut arena = arena(false);
+        let world = &mut arena.world;
+        let coin = world.create_entity()
+            .with(Position { x: 12, y: 11 })
+            .with(Name { name: String::from("Coins") })
+            .with(Item { item: Coin(5), tag: 7 })
+            .build();
+        let looter = |world: &mut World, x: i32| world.create_entity()
+            .with(Position { x: x, y: 11 })
+            .with(Name { name: String::from("Goblin") })
+            .with(Viewshed { visible_tiles: vec![], range: 8, dirty: false })
+            .with(Inventory { items: vec![], picks_up: true })
+            .with(fighter(None))
+            .with(Action { command: MoveCommand, cost: 0, stance_after: Ready, target: None, position: Some(Position { x: 12, y: 11 }) })
+            .build();
+        let looters = [looter(world, 11), looter(world, 13)];
+        {
+            let mut map = world.write_resource::<Map>();
+            let idx = map.xy_idx(12, 11);
+            map.tiles[idx] = TileType::Floor;
+            map.tile_content[idx].push(coin);
+        }
+
+        ActionSystem{}.run_now(world);
+        world.maintain();
+
+        let inventories = world.read_storage::<Inventory>();
+        let carried : usize = looters.iter().map(|l| inventories.get(*l).unwrap().items.len()).sum();
+        assert_eq!(carried, 1);
+        assert!(!world.is_alive(coin));
+    }
 }
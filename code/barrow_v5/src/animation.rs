@@ -0,0 +1,117 @@
+use specs::prelude::*;
+use rltk::{Rltk, RGB};
+use super::{Map, Position, CombatStance, camera::Camera};
+
+/// What an animation draws each frame
+#[derive(PartialEq, Clone, Debug)]
+pub enum AnimationKind {
+    /// Text that floats upward, like a damage number
+    Text(String),
+    /// A single glyph, such as a stance icon or a fading corpse
+    Glyph(rltk::FontCharType),
+    /// A background colour flash on the tile
+    Flash
+}
+
+/// A purely visual effect with a lifetime in frames.  Animations are never part of the game
+/// state: turns carry on while they play, and they are dropped when they expire.
+#[derive(Clone, Debug)]
+pub struct Animation {
+    pub kind: AnimationKind,
+    pub x: i32,
+    pub y: i32,
+    /// Tracks an entity while it is alive, so icons stay above a moving monster
+    pub follow: Option<Entity>,
+    pub offset_y: i32,
+    pub fg: RGB,
+    pub age: i32,
+    pub lifetime: i32,
+    /// Frames per row of upward drift; 0 keeps the animation in place
+    pub rise_every: i32
+}
+
+pub struct Animations {
+    pub active: Vec<Animation>
+}
+
+impl Animations {
+    pub fn new() -> Animations {
+        Animations { active: vec![] }
+    }
+
+    pub fn damage_number(&mut self, pos: Position, amount: i32) {
+        let (text, fg) = if amount > 0 { (format!("-{}", amount), RGB::named(rltk::ORANGE)) } else { (String::from("0"), RGB::named(rltk::GREY)) };
+        self.active.push(Animation { kind: AnimationKind::Text(text), x: pos.x, y: pos.y, follow: None, offset_y: -1, fg: fg, age: 0, lifetime: 45, rise_every: 15 });
+    }
+
+    pub fn hit_flash(&mut self, pos: Position) {
+        self.active.push(Animation { kind: AnimationKind::Flash, x: pos.x, y: pos.y, follow: None, offset_y: 0, fg: RGB::named(rltk::RED), age: 0, lifetime: 12, rise_every: 0 });
+    }
+
+    pub fn stance_icon(&mut self, entity: Entity, pos: Position, stance: CombatStance) {
+        let (glyph, fg, lifetime) = match stance {
+            CombatStance::Ready => (rltk::to_cp437('•'), RGB::named(rltk::YELLOW), 40),
+            CombatStance::Power => (rltk::to_cp437('↑'), RGB::named(rltk::CYAN), 40),
+            CombatStance::Guard => (rltk::to_cp437('♦'), RGB::named(rltk::GREEN), 40),
+            CombatStance::Stun => (rltk::to_cp437('*'), RGB::named(rltk::YELLOW), 90)
+        };
+        self.active.retain(|a| a.follow != Some(entity));
+        self.active.push(Animation { kind: AnimationKind::Glyph(glyph), x: pos.x, y: pos.y, follow: Some(entity), offset_y: -1, fg: fg, age: 0, lifetime: lifetime, rise_every: 0 });
+    }
+
+    pub fn death_fade(&mut self, pos: Position, glyph: rltk::FontCharType) {
+        self.active.push(Animation { kind: AnimationKind::Glyph(glyph), x: pos.x, y: pos.y, follow: None, offset_y: 0, fg: RGB::named(rltk::RED), age: 0, lifetime: 30, rise_every: 0 });
+    }
+
+    /// Advances every animation by one frame and drops the finished ones
+    pub fn update(&mut self) {
+        for animation in self.active.iter_mut() {
+            animation.age += 1;
+        }
+        self.active.retain(|a| a.age < a.lifetime);
+    }
+}
+
+impl Default for Animations {
+    fn default() -> Animations {
+        Animations::new()
+    }
+}
+
+/// Draws the active animations over the map and entities, then ages them by a frame
+pub fn draw_animations(ecs: &World, ctx: &mut Rltk) {
+    let camera = Camera::new(ecs);
+    let mut animations = ecs.write_resource::<Animations>();
+    let positions = ecs.read_storage::<Position>();
+    let map = ecs.fetch::<Map>();
+
+    for animation in animations.active.iter() {
+        let (x, y) = match animation.follow.and_then(|e| positions.get(e)) {
+            Some(pos) => (pos.x, pos.y),
+            None => (animation.x, animation.y)
+        };
+        // nothing is shown for things happening out of sight
+        if x < 0 || x >= map.width || y < 0 || y >= map.height || !map.visible_tiles[map.xy_idx(x, y)] { continue; }
+
+        let (x, y) = match camera.to_screen(x, y) { Some(screen) => screen, None => continue };
+        let rise = if animation.rise_every > 0 { animation.age / animation.rise_every } else { 0 };
+        let draw_y = y + animation.offset_y - rise;
+        if draw_y < 0 { continue; }
+        let fade = 1.0 - animation.age as f32 / animation.lifetime as f32;
+        let fg = animation.fg * fade;
+
+        match &animation.kind {
+            AnimationKind::Text(text) => {
+                for (i, c) in text.chars().enumerate() {
+                    let draw_x = x + i as i32;
+                    if draw_x <= camera.view.right() {
+                        ctx.set(draw_x, draw_y, fg, RGB::named(rltk::BLACK), rltk::to_cp437(c));
+                    }
+                }
+            }
+            AnimationKind::Glyph(glyph) => ctx.set(x, draw_y, fg, RGB::named(rltk::BLACK), *glyph),
+            AnimationKind::Flash => ctx.set_bg(x, draw_y, fg)
+        }
+    }
+    animations.update();
+}
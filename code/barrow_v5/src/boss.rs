@@ -0,0 +1,231 @@
+use specs::prelude::*;
+use rltk::Point;
+use super::{Boss, CombatStats, SmartMonster, Name, Position, Viewshed, Action, LightSource, Player, BlocksTile, Map, RunState, TileType, SufferDamage, EventQueue, GameEvent, AttackMove, CombatStance, spawner};
+use super::AttackMove::*;
+use super::CombatStance::*;
+
+/// The bosses of the barrow.  A boss is an ordinary monster with a Boss component attached,
+/// which plays out its kind's script of phases on top of the normal AI.  A boss for another
+/// depth needs a kind here, a script below and a spawner function that attaches the Boss.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum BossKind { BarrowLord, BarrowWraith }
+
+/// What happens to the arena as a boss enters a phase
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum ArenaChange {
+    Summon { name: &'static str, count: i32 },
+    /// The stairs on the level can't be used until the boss is dead
+    SealStairs,
+    /// Every light on the level but the player's torch goes out
+    DouseLights,
+    Empower { power: i32, defense: i32 }
+}
+
+/// A boss's signature attack, used in place of its normal action every few turns
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum SpecialMove {
+    /// Dark energy bursts out of the boss, hurting and exhausting the player if they are close
+    DarkPulse { radius: f32, hp: i32, ep: i32 },
+    /// A grasping touch that steals the player's life for the boss
+    LifeDrain { hp: i32 }
+}
+
+pub struct BossPhase {
+    /// The phase begins once the boss is down to this share of its max hp
+    pub hp_percent: i32,
+    pub title: &'static str,
+    pub announce: &'static str,
+    pub stance: CombatStance,
+    pub attack: AttackMove,
+    pub attack_cost: i32,
+    pub arena: &'static [ArenaChange],
+    pub special: Option<SpecialMove>,
+    pub special_every: i32
+}
+
+static BARROW_LORD : [BossPhase; 3] = [
+    BossPhase { hp_percent: 100, title: "Patient", announce: "",
+        stance: Power, attack: Smash, attack_cost: 15, arena: &[], special: None, special_every: 0 },
+    BossPhase { hp_percent: 66, title: "Wrathful", announce: "Yendor roars, and dark energies seal the stairs! The dead rise to his call.",
+        stance: Power, attack: Smash, attack_cost: 15,
+        arena: &[ArenaChange::SealStairs, ArenaChange::Summon { name: "Skeleton", count: 2 }],
+        special: Some(SpecialMove::DarkPulse { radius: 2.5, hp: 3, ep: 10 }), special_every: 5 },
+    BossPhase { hp_percent: 33, title: "Desperate", announce: "The Barrow-Lord draws the fire out of the braziers and into himself!",
+        stance: Ready, attack: Melee, attack_cost: 5,
+        arena: &[ArenaChange::DouseLights, ArenaChange::Empower { power: 2, defense: 0 }, ArenaChange::Summon { name: "Wight", count: 1 }],
+        special: Some(SpecialMove::LifeDrain { hp: 4 }), special_every: 4 }
+];
+
+static BARROW_WRAITH : [BossPhase; 2] = [
+    BossPhase { hp_percent: 100, title: "Vengeful", announce: "",
+        stance: Power, attack: Smash, attack_cost: 15, arena: &[],
+        special: Some(SpecialMove::DarkPulse { radius: 2.5, hp: 2, ep: 10 }), special_every: 6 },
+    BossPhase { hp_percent: 50, title: "Unravelling", announce: "The wraith tears at the dark, and shades pour out of it!",
+        stance: Power, attack: Smash, attack_cost: 15,
+        arena: &[ArenaChange::Summon { name: "Shade", count: 3 }],
+        special: Some(SpecialMove::LifeDrain { hp: 3 }), special_every: 5 }
+];
+
+impl BossKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BossKind::BarrowLord => "Barrow-Lord",
+            BossKind::BarrowWraith => "Barrow-Wraith"
+        }
+    }
+
+    pub fn phases(&self) -> &'static [BossPhase] {
+        match self {
+            BossKind::BarrowLord => &BARROW_LORD,
+            BossKind::BarrowWraith => &BARROW_WRAITH
+        }
+    }
+
+    /// The latest phase whose hp threshold the boss has fallen to
+    fn phase_for(&self, stats: &CombatStats) -> usize {
+        self.phases().iter().rposition(|p| stats.hp * 100 <= p.hp_percent * stats.max_hp).unwrap_or(0)
+    }
+}
+
+/// Runs between the monsters choosing their actions and those actions being resolved: moves
+/// bosses on to new phases as their hp drops, then lets them swap their chosen action for their
+/// special move when it is ready
+pub fn run_bosses(ecs: &mut World) {
+    if *ecs.fetch::<RunState>() != RunState::MonsterTurn { return; }
+
+    let mut entering : Vec<(Entity, BossKind, usize, usize, Position)> = vec![];
+    {
+        let entities = ecs.entities();
+        let mut bosses = ecs.write_storage::<Boss>();
+        let combat_stats = ecs.read_storage::<CombatStats>();
+        let positions = ecs.read_storage::<Position>();
+        for (entity, boss, stats, pos) in (&entities, &mut bosses, &combat_stats, &positions).join() {
+            if stats.hp <= 0 { continue; }
+            let phase = boss.kind.phase_for(stats);
+            if phase > boss.phase {
+                entering.push((entity, boss.kind, boss.phase, phase, *pos));
+                boss.phase = phase;
+                boss.cooldown = 0;
+            }
+        }
+    }
+    // a heavy blow can carry a boss through several phases at once; it still goes through
+    // every one of them, so no seal or summons is skipped
+    for (entity, kind, from, to, pos) in entering {
+        for phase in from + 1 ..= to {
+            enter_phase(ecs, entity, kind, phase, pos);
+        }
+    }
+
+    special_moves(ecs);
+}
+
+/// Whether a living boss on the level has sealed the stairs
+pub fn stairs_sealed(ecs: &World) -> bool {
+    let bosses = ecs.read_storage::<Boss>();
+    let combat_stats = ecs.read_storage::<CombatStats>();
+    (&bosses, &combat_stats).join().any(|(boss, stats)| boss.stairs_sealed && stats.hp > 0)
+}
+
+fn enter_phase(ecs: &mut World, entity: Entity, kind: BossKind, phase: usize, pos: Position) {
+    let script = &kind.phases()[phase];
+    ecs.write_resource::<EventQueue>().emit(GameEvent::BossPhase { name: kind.name().to_string(), title: script.title, announce: script.announce });
+
+    if let Some(smart) = ecs.write_storage::<SmartMonster>().get_mut(entity) {
+        smart.primary_stance = script.stance;
+        smart.primary_attack = script.attack;
+        smart.primary_attack_cost = script.attack_cost;
+    }
+
+    for change in script.arena.iter() {
+        match *change {
+            ArenaChange::Summon { name, count } => {
+                for spot in free_tiles_around(ecs, pos, count as usize) {
+                    let tag = ecs.write_resource::<rltk::RandomNumberGenerator>().next_u64();
+                    spawner::monster_by_name(ecs, name, spot, tag);
+                }
+            }
+            ArenaChange::SealStairs => {
+                if let Some(boss) = ecs.write_storage::<Boss>().get_mut(entity) { boss.stairs_sealed = true; }
+            }
+            ArenaChange::DouseLights => {
+                let doused : Vec<Entity> = {
+                    let entities = ecs.entities();
+                    let lights = ecs.read_storage::<LightSource>();
+                    let players = ecs.read_storage::<Player>();
+                    (&entities, &lights, !&players).join().map(|(e, _, _)| e).collect()
+                };
+                let mut lights = ecs.write_storage::<LightSource>();
+                for light in doused { lights.remove(light); }
+            }
+            ArenaChange::Empower { power, defense } => {
+                if let Some(stats) = ecs.write_storage::<CombatStats>().get_mut(entity) {
+                    stats.power += power;
+                    stats.defense += defense;
+                }
+            }
+        }
+    }
+}
+
+/// Up to `count` open floor tiles next to `pos`, for summoned minions to appear on
+fn free_tiles_around(ecs: &World, pos: Position, count: usize) -> Vec<(i32, i32)> {
+    let map = ecs.fetch::<Map>();
+    let positions = ecs.read_storage::<Position>();
+    let blockers = ecs.read_storage::<BlocksTile>();
+    let occupied : Vec<Position> = (&positions, &blockers).join().map(|(p, _)| *p).collect();
+    (-1..=1).flat_map(|dy| (-1..=1).map(move |dx| (pos.x + dx, pos.y + dy)))
+        .filter(|(x, y)| map.tiles[map.xy_idx(*x, *y)] == TileType::Floor && !occupied.contains(&Position { x: *x, y: *y }))
+        .take(count)
+        .collect()
+}
+
+fn special_moves(ecs: &mut World) {
+    let player_entity = *ecs.fetch::<Entity>();
+    let player_pos = match ecs.read_storage::<Position>().get(player_entity) {
+        Some(pos) => Point::new(pos.x, pos.y),
+        None => return
+    };
+    let entities = ecs.entities();
+    let mut bosses = ecs.write_storage::<Boss>();
+    let combat_stats = ecs.read_storage::<CombatStats>();
+    let positions = ecs.read_storage::<Position>();
+    let viewsheds = ecs.read_storage::<Viewshed>();
+    let names = ecs.read_storage::<Name>();
+    let mut actions = ecs.write_storage::<Action>();
+    let mut inflict_damage = ecs.write_storage::<SufferDamage>();
+    let mut events = ecs.write_resource::<EventQueue>();
+
+    for (entity, boss, stats, pos, viewshed, name) in (&entities, &mut bosses, &combat_stats, &positions, &viewsheds, &names).join() {
+        if stats.hp <= 0 || stats.stance == Stun { continue; }
+        let script = &boss.kind.phases()[boss.phase];
+        let special = match script.special {
+            Some(special) => special,
+            None => continue
+        };
+        if boss.cooldown > 0 {
+            boss.cooldown -= 1;
+            continue;
+        }
+        if !viewshed.visible_tiles.contains(&player_pos) { continue; }
+
+        let distance = rltk::DistanceAlg::Pythagoras.distance2d(Point::new(pos.x, pos.y), player_pos);
+        let hp_damage = match special {
+            SpecialMove::DarkPulse { radius, hp, ep } if distance <= radius => {
+                SufferDamage::new_hp_damage(&mut inflict_damage, player_entity, hp);
+                SufferDamage::new_ep_damage(&mut inflict_damage, player_entity, ep);
+                hp
+            }
+            SpecialMove::LifeDrain { hp } if distance < 1.5 => {
+                SufferDamage::new_hp_damage(&mut inflict_damage, player_entity, hp);
+                SufferDamage::new_hp_damage(&mut inflict_damage, entity, -hp);
+                hp
+            }
+            _ => continue
+        };
+        events.emit(GameEvent::BossSpecial { name: name.name.clone(), target: player_entity, special: special, hp_damage: hp_damage });
+        // the special move takes the place of whatever the boss meant to do this turn
+        actions.remove(entity);
+        boss.cooldown = script.special_every;
+    }
+}
@@ -0,0 +1,139 @@
+use specs::prelude::*;
+use super::{Map, Position, ui::{Region, ScreenLayout}};
+
+/// The cells of console 0 the map is drawn in.  The screen is laid out on the 8x16 UI console,
+/// so this is its map panel stretched over console 0's 8x8 cells; the rest of the screen
+/// belongs to the sidebar and log.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct Viewport(pub Region);
+
+impl Viewport {
+    pub fn new(layout: &ScreenLayout) -> Viewport {
+        Viewport(layout.map.scaled(1, 2))
+    }
+}
+
+/// The window of the level that is on screen.  It is centred on the player and clamped so
+/// that it never shows past the edge of the map; maps smaller than the view sit in the corner.
+#[derive(Clone, Copy, Debug)]
+pub struct Camera {
+    pub min_x: i32,
+    pub min_y: i32,
+    pub map_width: i32,
+    pub map_height: i32,
+    pub view: Region
+}
+
+impl Camera {
+    /// Camera for the current frame, following the player
+    pub fn new(ecs: &World) -> Camera {
+        let map = ecs.fetch::<Map>();
+        let player_entity = ecs.fetch::<Entity>();
+        let positions = ecs.read_storage::<Position>();
+        let (x, y) = match positions.get(*player_entity) {
+            Some(pos) => (pos.x, pos.y),
+            None => (map.width / 2, map.height / 2)
+        };
+        let Viewport(view) = *ecs.fetch::<Viewport>();
+        Camera::centred_on(x, y, map.width, map.height, view)
+    }
+
+    pub fn centred_on(x: i32, y: i32, map_width: i32, map_height: i32, view: Region) -> Camera {
+        Camera {
+            min_x: clamp_axis(x - view.w / 2, map_width, view.w),
+            min_y: clamp_axis(y - view.h / 2, map_height, view.h),
+            map_width: map_width,
+            map_height: map_height,
+            view: view
+        }
+    }
+
+    /// Screen position of a world tile, or None if it is off screen
+    pub fn to_screen(self, x: i32, y: i32) -> Option<(i32, i32)> {
+        let (sx, sy) = (x - self.min_x + self.view.x, y - self.min_y + self.view.y);
+        if !self.view.contains(sx, sy) { return None; }
+        Some((sx, sy))
+    }
+
+    /// World tile under a screen position, or None if it is outside the map area or the map
+    pub fn to_world(self, sx: i32, sy: i32) -> Option<(i32, i32)> {
+        if !self.view.contains(sx, sy) { return None; }
+        let (x, y) = (sx - self.view.x + self.min_x, sy - self.view.y + self.min_y);
+        if x >= self.map_width || y >= self.map_height { return None; }
+        Some((x, y))
+    }
+}
+
+fn clamp_axis(min: i32, map_size: i32, view_size: i32) -> i32 {
+    if map_size <= view_size { return 0; }
+    i32::min(i32::max(min, 0), map_size - view_size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The viewport of the game's own 80x30 UI console
+    fn view() -> Region {
+        Viewport::new(&ScreenLayout::new(80, 30)).0
+    }
+
+    #[test]
+    fn the_viewport_is_the_map_panel_on_the_map_console() {
+        let layout = ScreenLayout::new(80, 30);
+        let view = view();
+        assert_eq!((view.x, view.y, view.w), (layout.map.x, layout.map.y, layout.map.w));
+        assert_eq!(view.h, layout.map.h * 2);
+        // the last map row sits just above the log
+        assert_eq!((view.bottom() + 1) / 2, layout.log.y);
+    }
+
+    #[test]
+    fn small_maps_do_not_scroll() {
+        let view = view();
+        let camera = Camera::centred_on(40, 30, view.w, view.h, view);
+        assert_eq!((camera.min_x, camera.min_y), (0, 0));
+        assert_eq!(camera.to_screen(12, 7), Some((12, 7)));
+    }
+
+    #[test]
+    fn large_maps_centre_on_the_player_and_clamp_at_the_edges() {
+        let view = view();
+        let camera = Camera::centred_on(40, 30, 80, 60, view);
+        assert_eq!((camera.min_x, camera.min_y), (40 - view.w / 2, 30 - view.h / 2));
+        assert_eq!(camera.to_screen(40, 30), Some((view.w / 2, view.h / 2)));
+
+        let corner = Camera::centred_on(79, 59, 80, 60, view);
+        assert_eq!((corner.min_x, corner.min_y), (80 - view.w, 60 - view.h));
+        let origin = Camera::centred_on(0, 0, 80, 60, view);
+        assert_eq!((origin.min_x, origin.min_y), (0, 0));
+    }
+
+    #[test]
+    fn screen_and_world_round_trip() {
+        for view in [view(), Region::new(3, 2, 30, 20)].iter() {
+            for &(px, py) in [(0, 0), (40, 30), (79, 59), (10, 55)].iter() {
+                let camera = Camera::centred_on(px, py, 80, 60, *view);
+                for sy in view.y..=view.bottom() {
+                    for sx in view.x..=view.right() {
+                        let (x, y) = camera.to_world(sx, sy).unwrap();
+                        assert_eq!(camera.to_screen(x, y), Some((sx, sy)));
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn positions_off_the_view_or_map_have_no_mapping() {
+        let view = view();
+        let camera = Camera::centred_on(40, 30, 80, 60, view);
+        assert_eq!(camera.to_world(-1, 0), None);
+        assert_eq!(camera.to_world(view.w, 0), None);
+        assert_eq!(camera.to_world(0, view.h), None);
+        assert_eq!(camera.to_screen(camera.min_x - 1, camera.min_y), None);
+
+        let small = Camera::centred_on(5, 5, 20, 20, view);
+        assert_eq!(small.to_world(25, 5), None);
+    }
+}
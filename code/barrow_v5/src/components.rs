@@ -1,18 +1,16 @@
 use specs::prelude::*;
 use specs_derive::*;
+pub use barrow_core::{Position, Viewshed, BlocksTile, SufferDamage};
 use rltk::{RGB};
+use super::BossKind;
 
-#[derive(PartialEq, Component, Debug, Clone, Copy)]
-pub struct Position {
-    pub x: i32,
-    pub y: i32,
-}
-
-#[derive(Component)]
+#[derive(Component, Clone)]
 pub struct Renderable {
     pub glyph: rltk::FontCharType,
     pub fg: RGB,
     pub bg: RGB,
+    /// Drawing layer; entities with a higher order are drawn over those sharing their tile
+    pub render_order: i32,
 }
 
 #[derive(Component, PartialEq, Debug, Copy, Clone)]
@@ -24,22 +22,23 @@ pub struct Player {
     pub atk_bonus: i32,
     pub def_bonus: i32,
     pub deepest_level: i32,
-    pub has_amulet: bool
+    pub has_amulet: bool,
+    pub torch_fuel: i32
 }
 
-#[derive(Debug)]
+#[derive(PartialEq, Copy, Clone, Debug)]
 pub enum Containers {
     Treasure,
     Barrel
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Clone)]
 pub struct Container {
     pub container: Containers,
     pub tag: u64
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Items {
     Coin(i32),
     Food(i32),
@@ -47,36 +46,49 @@ pub enum Items {
     Amulet
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Clone)]
 pub struct Item {
     pub item: Items,
     pub tag: u64
 }
 
-#[derive(Component)]
-pub struct Viewshed {
-    pub visible_tiles : Vec<rltk::Point>,
-    pub range : i32,
-    pub dirty : bool
+/// What a monster is carrying, dropped where it dies.  Items keep their tags while carried,
+/// so a dropped item is the same one that was picked up.
+#[derive(Component, Debug, Clone)]
+pub struct Inventory {
+    pub items: Vec<Item>,
+    /// Whether the monster grabs loot it walks over
+    pub picks_up: bool
+}
+
+/// The remains of a slain monster, which rot away after a while
+#[derive(Component, Debug, Clone)]
+pub struct Corpse {
+    pub rot: i32
+}
+
+/// Anything that lights up its surroundings: the player's torch, braziers, the amulet
+#[derive(Component, Clone)]
+pub struct LightSource {
+    pub radius: i32,
+    pub color: RGB,
+    pub falloff: f32
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Clone)]
 pub struct Monster {
     pub tag: u64
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Clone)]
 pub struct Name {
     pub name : String
 }
 
-#[derive(Component, Debug)]
-pub struct BlocksTile {}
-
 #[derive(PartialEq, Copy, Clone, Debug)]
 pub enum CombatStance { Guard, Ready, Power, Stun }
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Clone)]
 pub struct CombatStats {
     pub max_hp : i32,
     pub hp : i32,
@@ -134,6 +146,31 @@ pub enum SmartMonsterState {
     Idle
 }
 
+/// A monster that fights to a boss script rather than like the rest of its kind
+#[derive(Component, Debug, Clone)]
+pub struct Boss {
+    pub kind: BossKind,
+    pub phase: usize,
+    /// Monster turns until the special move of the current phase can be used again
+    pub cooldown: i32,
+    pub stairs_sealed: bool
+}
+
+impl Boss {
+    pub fn new(kind: BossKind) -> Boss {
+        Boss { kind: kind, phase: 0, cooldown: 0, stairs_sealed: false }
+    }
+}
+
+/// How willing a monster is to keep fighting.  Morale starts at the archetype's courage; a
+/// monster whose morale runs out flees from the player until it rallies.
+#[derive(Component, Debug, Clone)]
+pub struct Morale {
+    pub courage: i32,
+    pub morale: i32,
+    pub fleeing: bool
+}
+
 #[derive(PartialEq, Component, Debug, Clone)]
 pub struct SmartMonster {
     pub state: SmartMonsterState,
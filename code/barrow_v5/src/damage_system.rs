@@ -0,0 +1,100 @@
+use specs::prelude::*;
+use super::{CombatStats, SufferDamage, Name, EventQueue, GameEvent, action_system::{apply_hp_damage, apply_ep_damage}};
+
+/// Applies the damage and recovery queued during a pass, after every action has been resolved.
+/// An entity's hp and ep changes are each summed and applied once, so the outcome does not
+/// depend on the order entities acted in: two fighters who strike each other both land their
+/// blows, and a monster killed this pass still gets the attack it had already committed to.
+pub struct DamageSystem {}
+
+impl<'a> System<'a> for DamageSystem {
+    type SystemData = ( Entities<'a>,
+                        WriteExpect<'a, EventQueue>,
+                        ReadStorage<'a, Name>,
+                        WriteStorage<'a, CombatStats>,
+                        WriteStorage<'a, SufferDamage> );
+
+    fn run(&mut self, data : Self::SystemData) {
+        let (entities, mut events, names, mut combat_stats, mut damage) = data;
+
+        for (entity, stats, damage) in (&entities, &mut combat_stats, &damage).join() {
+            let stance_before = stats.stance;
+            apply_hp_damage(stats, damage.hp_amount.iter().sum::<i32>());
+            apply_ep_damage(stats, damage.ep_amount.iter().sum::<i32>());
+            if stats.stance != stance_before {
+                if let Some(name) = names.get(entity) {
+                    events.emit(GameEvent::StanceChanged { entity: entity, name: name.name.clone(), from: stance_before, to: stats.stance });
+                }
+            }
+        }
+
+        damage.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Action, CombatStance, Command::*, AttackMove::*, CombatStance::*, action_system::ActionSystem, ruleset::tests::{arena, Arena}};
+
+    /// The player smashes while the monster bashes back, and each pass is resolved by the
+    /// ActionSystem then the DamageSystem
+    fn exchange(monster_first: bool, hp: i32, player_ep: i32, monster_ep: i32) -> (Arena, Vec<(i32, i32, CombatStance)>) {
+        let mut arena = arena(monster_first);
+        let (player, monster) = (arena.player, arena.monster);
+        arena.set_stats(player, |s| { s.hp = hp; s.ep = player_ep; });
+        arena.set_stats(monster, |s| { s.hp = hp; s.ep = monster_ep; });
+        {
+            let mut actions = arena.world.write_storage::<Action>();
+            actions.insert(player, Action { command: AttackCommand(Smash), cost: 15, stance_after: Power, target: Some(monster), position: None }).expect("Unable to insert action");
+            actions.insert(monster, Action { command: AttackCommand(Bash), cost: 10, stance_after: Guard, target: Some(player), position: None }).expect("Unable to insert action");
+        }
+        ActionSystem{}.run_now(&arena.world);
+        DamageSystem{}.run_now(&arena.world);
+        arena.world.maintain();
+
+        let results = [player, monster].iter().map(|e| {
+            let stats = arena.stats(*e);
+            (stats.hp, stats.ep, stats.stance)
+        }).collect();
+        (arena, results)
+    }
+
+    #[test]
+    fn blows_land_the_same_whoever_acts_first() {
+        let (first, player_first) = exchange(false, 30, 40, 40);
+        let (second, monster_first) = exchange(true, 30, 40, 40);
+
+        // hp only differs by the rolls, which are drawn in join order, so compare it with what
+        // the attacks reported
+        for (arena, results) in [(&first, &player_first), (&second, &monster_first)].iter() {
+            assert_eq!(results[0].0, 30 - arena.damage_to(arena.player));
+            assert_eq!(results[1].0, 30 - arena.damage_to(arena.monster));
+        }
+        // the smash costs 15 and the bash drains 10 more; the bash costs 10 and the smash drains 5
+        assert_eq!((player_first[0].1, player_first[0].2), (15, Power));
+        assert_eq!((player_first[1].1, player_first[1].2), (25, Guard));
+        assert_eq!(player_first.iter().map(|r| (r.1, r.2)).collect::<Vec<_>>(), monster_first.iter().map(|r| (r.1, r.2)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn a_fighter_drained_below_zero_is_stunned_whoever_acts_first() {
+        let (_, player_first) = exchange(false, 30, 40, 12);
+        let (_, monster_first) = exchange(true, 30, 40, 12);
+
+        // the bash leaves the monster 2 energy, which the smash drains below zero
+        assert_eq!((player_first[0].1, player_first[0].2), (15, Power));
+        assert_eq!((player_first[1].1, player_first[1].2), (-3, Stun));
+        assert_eq!(player_first.iter().map(|r| (r.1, r.2)).collect::<Vec<_>>(), monster_first.iter().map(|r| (r.1, r.2)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn both_fighters_can_die_in_the_same_pass() {
+        for monster_first in [false, true].iter() {
+            let (arena, results) = exchange(*monster_first, 1, 40, 40);
+            assert!(results.iter().all(|r| r.0 < 1), "monster first: {}", monster_first);
+            let attacks = arena.events().iter().filter(|e| matches!(e, GameEvent::Attacked { .. })).count();
+            assert_eq!(attacks, 2, "monster first: {}", monster_first);
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use super::{Player, RulesetKind, lighting_system::{TORCH_FUEL_START, TORCH_FUEL_MAX}};
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum Difficulty { Easy, Normal, Hard, Ironman, Debug }
+
+#[derive(PartialEq, Copy, Clone, Debug, Default)]
+pub struct RunModifiers {
+    pub no_town_shop: bool,
+    pub double_monsters: bool,
+    pub permadark: bool,
+    pub food_scarcity: bool
+}
+
+/// Difficulty preset and modifiers chosen on the main menu, inserted as a resource for the run
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct RunSettings {
+    pub difficulty: Difficulty,
+    pub modifiers: RunModifiers,
+    pub ruleset: RulesetKind
+}
+
+impl Difficulty {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard",
+            Difficulty::Ironman => "Ironman",
+            Difficulty::Debug => "Debug"
+        }
+    }
+
+    pub fn next(&self) -> Difficulty {
+        match self {
+            Difficulty::Easy => Difficulty::Normal,
+            Difficulty::Normal => Difficulty::Hard,
+            Difficulty::Hard => Difficulty::Ironman,
+            Difficulty::Ironman => Difficulty::Debug,
+            Difficulty::Debug => Difficulty::Easy
+        }
+    }
+
+    pub fn prev(&self) -> Difficulty {
+        match self {
+            Difficulty::Easy => Difficulty::Debug,
+            Difficulty::Normal => Difficulty::Easy,
+            Difficulty::Hard => Difficulty::Normal,
+            Difficulty::Ironman => Difficulty::Hard,
+            Difficulty::Debug => Difficulty::Ironman
+        }
+    }
+
+    pub fn player_max_hp(&self) -> i32 {
+        match self {
+            Difficulty::Easy => 40,
+            Difficulty::Hard | Difficulty::Ironman => 25,
+            _ => 30
+        }
+    }
+
+    /// Monster hp is scaled by this percentage at spawn time
+    pub fn monster_hp_percent(&self) -> i32 {
+        match self {
+            Difficulty::Easy => 75,
+            Difficulty::Hard | Difficulty::Ironman => 125,
+            _ => 100
+        }
+    }
+}
+
+impl RunSettings {
+    pub fn new() -> RunSettings {
+        RunSettings { difficulty: Difficulty::Normal, modifiers: RunModifiers::default(), ruleset: RulesetKind::Stance }
+    }
+
+    /// The Player state a fresh run begins with
+    pub fn starting_player(&self) -> Player {
+        let mut player = match self.difficulty {
+            Difficulty::Easy => Player { food: 15, max_food: 15, coin: 10, potions: 0, atk_bonus: 0, def_bonus: 0, deepest_level: 0, has_amulet: false, torch_fuel: TORCH_FUEL_MAX },
+            Difficulty::Debug => Player { food: 10, max_food: 10, coin: 600, potions: 0, atk_bonus: 0, def_bonus: 0, deepest_level: 5, has_amulet: false, torch_fuel: TORCH_FUEL_MAX },
+            _ => Player { food: 10, max_food: 10, coin: 0, potions: 0, atk_bonus: 0, def_bonus: 0, deepest_level: 0, has_amulet: false, torch_fuel: TORCH_FUEL_START }
+        };
+        if self.modifiers.food_scarcity {
+            player.food = player.food / 2;
+        }
+        player
+    }
+
+    /// How far the player could see if everything were lit; light decides what is actually seen
+    pub fn viewshed_range(&self) -> i32 {
+        16
+    }
+
+    /// Radius of a burning torch; permadark runs make do with a meaner flame
+    pub fn torch_radius(&self) -> i32 {
+        if self.modifiers.permadark { 4 } else { 6 }
+    }
+
+    /// Ironman runs never get to shop, regardless of modifiers
+    pub fn town_shop_open(&self) -> bool {
+        !self.modifiers.no_town_shop && self.difficulty != Difficulty::Ironman
+    }
+
+    pub fn food_cost_multiplier(&self) -> i32 {
+        if self.modifiers.food_scarcity { 2 } else { 1 }
+    }
+
+    pub fn describe(&self) -> String {
+        let mut mods : Vec<&str> = vec![];
+        if self.ruleset != RulesetKind::Stance { mods.push(self.ruleset.name()); }
+        if self.modifiers.no_town_shop { mods.push("no shop"); }
+        if self.modifiers.double_monsters { mods.push("double monsters"); }
+        if self.modifiers.permadark { mods.push("permadark"); }
+        if self.modifiers.food_scarcity { mods.push("food scarcity"); }
+        if mods.is_empty() {
+            self.difficulty.name().to_string()
+        } else {
+            format!("{} ({})", self.difficulty.name(), mods.join(", "))
+        }
+    }
+}
+
+impl Default for RunSettings {
+    fn default() -> RunSettings {
+        RunSettings::new()
+    }
+}
@@ -0,0 +1,75 @@
+use std::ops::RangeInclusive;
+use super::{StairLink, STAIRS_UP, STAIRS_DOWN, STAIRS_BRANCH};
+
+/// The barrow itself and the side branches that open off it
+#[derive(PartialEq, Eq, Hash, Copy, Clone, Debug)]
+pub enum Branch { Barrow, Crypt, FloodedTunnels }
+
+pub const ALL_BRANCHES : [Branch; 3] = [Branch::Barrow, Branch::Crypt, Branch::FloodedTunnels];
+
+/// One level of the dungeon.  Depth is counted from the surface in every branch, so a branch
+/// level is as dangerous as the barrow level at the same depth.
+#[derive(PartialEq, Eq, Hash, Copy, Clone, Debug)]
+pub struct LevelId {
+    pub branch: Branch,
+    pub depth: i32
+}
+
+impl Branch {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Branch::Barrow => "The Barrow",
+            Branch::Crypt => "The Crypt",
+            Branch::FloodedTunnels => "Flooded Tunnels"
+        }
+    }
+
+    /// The barrow depth whose level holds the stairs down into this branch
+    pub fn entrance_depth(&self) -> Option<i32> {
+        match self {
+            Branch::Barrow => None,
+            Branch::Crypt => Some(2),
+            Branch::FloodedTunnels => Some(4)
+        }
+    }
+
+    pub fn depths(&self) -> RangeInclusive<i32> {
+        match self {
+            Branch::Barrow => 1..=6,
+            Branch::Crypt => 3..=4,
+            Branch::FloodedTunnels => 5..=5
+        }
+    }
+}
+
+impl LevelId {
+    pub fn barrow(depth: i32) -> LevelId {
+        LevelId { branch: Branch::Barrow, depth: depth }
+    }
+
+    pub fn name(&self) -> String {
+        format!("{} {}", self.branch.name(), self.depth)
+    }
+
+    /// The branch whose entrance is on this level, if any
+    pub fn branch_entrance(&self) -> Option<Branch> {
+        if self.branch != Branch::Barrow { return None; }
+        ALL_BRANCHES.iter().copied().find(|b| b.entrance_depth() == Some(self.depth))
+    }
+
+    /// Where this level's up stairs lead: the level above, or the barrow level a branch opens off
+    pub fn up(&self) -> StairLink {
+        match self.branch.entrance_depth() {
+            Some(entrance) if self.depth == *self.branch.depths().start() => {
+                StairLink { level: LevelId::barrow(entrance), stairs: STAIRS_BRANCH }
+            }
+            _ => StairLink { level: LevelId { branch: self.branch, depth: self.depth - 1 }, stairs: STAIRS_DOWN }
+        }
+    }
+
+    /// Where this level's down stairs lead, or None at the bottom of a branch
+    pub fn down(&self) -> Option<StairLink> {
+        let next = LevelId { branch: self.branch, depth: self.depth + 1 };
+        if self.branch.depths().contains(&next.depth) { Some(StairLink { level: next, stairs: STAIRS_UP }) } else { None }
+    }
+}
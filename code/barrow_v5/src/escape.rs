@@ -0,0 +1,177 @@
+use specs::prelude::*;
+use super::{CombatStats, Name, Position, Map, SmartMonster, SmartMonsterState, SufferDamage, RunStats, EventQueue, GameEvent, TileType, spawner};
+
+/// Turns of doom between each deepening of the darkness, and how deep it can get
+const DOOM_STEP : i32 = 100;
+const MAX_DOOM_LEVEL : i32 = 4;
+
+/// Who is hunting the player: the Barrow-Lord himself, then his wraith once the lord is slain
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum Pursuer { Lord, Wraith, Banished }
+
+impl Pursuer {
+    pub fn name(&self) -> Option<&'static str> {
+        match self {
+            Pursuer::Lord => Some("Barrow-Lord"),
+            Pursuer::Wraith => Some("Barrow-Wraith"),
+            Pursuer::Banished => None
+        }
+    }
+}
+
+/// The endgame, from taking the Amulet to reaching the barrow's mouth.  Doom builds up every
+/// turn the Amulet is carried, and the pursuer follows the player from level to level.
+pub struct EscapeState {
+    pub active: bool,
+    pub started_turn: i32,
+    pub doom: i32,
+    pub damage_taken: i32,
+    pub pursuer: Pursuer,
+    /// The pursuer's hp while it is between levels, so wounds carry over
+    pub pursuer_hp: Option<i32>,
+    /// The turn the pursuer comes out of the stairs behind the player, if it is on its way
+    pub pursuer_due: Option<i32>,
+    /// Where the player came into the current level, which is where the pursuer follows them in
+    pub entry: Option<Position>
+}
+
+impl EscapeState {
+    pub fn new() -> EscapeState {
+        EscapeState { active: false, started_turn: 0, doom: 0, damage_taken: 0, pursuer: Pursuer::Lord, pursuer_hp: None, pursuer_due: None, entry: None }
+    }
+
+    pub fn begin(&mut self, turn: i32, lord_slain: bool) {
+        self.active = true;
+        self.started_turn = turn;
+        self.pursuer = if lord_slain { Pursuer::Wraith } else { Pursuer::Lord };
+    }
+
+    pub fn doom_level(&self) -> i32 {
+        i32::min(MAX_DOOM_LEVEL, self.doom / DOOM_STEP)
+    }
+
+    /// Turns between each point of hp the darkness drains, by doom level
+    fn drain_interval(&self) -> Option<i32> {
+        match self.doom_level() {
+            0 => None,
+            1 => Some(20),
+            2 => Some(10),
+            3 => Some(5),
+            _ => Some(2)
+        }
+    }
+
+    /// The pursuer keeps closer on the player's heels as the darkness deepens
+    pub fn pursuit_delay(&self) -> i32 {
+        i32::max(5, 30 - 6 * self.doom_level())
+    }
+
+    /// Called when the player leaves a level without the pursuer at their side
+    pub fn pursuer_left_behind(&mut self, turn: i32, hp: Option<i32>) {
+        if self.pursuer == Pursuer::Banished { return; }
+        if hp.is_some() { self.pursuer_hp = hp; }
+        self.pursuer_due = Some(turn + self.pursuit_delay());
+    }
+
+    /// Graded on how quickly the player got out and how much of a beating they took doing it
+    pub fn grade(&self, turn: i32) -> char {
+        let score = (turn - self.started_turn) + 2 * self.damage_taken;
+        match score {
+            s if s <= 250 => 'S',
+            s if s <= 400 => 'A',
+            s if s <= 600 => 'B',
+            s if s <= 900 => 'C',
+            _ => 'D'
+        }
+    }
+}
+
+impl Default for EscapeState {
+    fn default() -> EscapeState {
+        EscapeState::new()
+    }
+}
+
+/// Advances the doom counter by a turn, drains the player as the darkness deepens and brings
+/// the pursuer out of the stairs when it catches up
+pub fn tick(ecs: &mut World) {
+    let turn = ecs.fetch::<RunStats>().turns;
+    let arriving = {
+        let mut escape = ecs.write_resource::<EscapeState>();
+        if !escape.active { return; }
+        let level_before = escape.doom_level();
+        escape.doom += 1;
+        if escape.doom_level() > level_before {
+            ecs.write_resource::<EventQueue>().emit(GameEvent::DoomDeepens { level: escape.doom_level() });
+        }
+        if let Some(interval) = escape.drain_interval() {
+            if escape.doom % interval == 0 {
+                let player_entity = ecs.fetch::<Entity>();
+                SufferDamage::new_hp_damage(&mut ecs.write_storage::<SufferDamage>(), *player_entity, 1);
+                ecs.write_resource::<EventQueue>().emit(GameEvent::DoomDrain);
+            }
+        }
+        match (escape.pursuer_due, escape.pursuer.name()) {
+            (Some(due), Some(name)) if turn >= due => {
+                escape.pursuer_due = None;
+                Some((name, escape.pursuer_hp, escape.entry))
+            }
+            _ => None
+        }
+    };
+
+    if let Some((name, hp, Some(entry))) = arriving {
+        let spot = {
+            let map = ecs.fetch::<Map>();
+            let positions = ecs.read_storage::<Position>();
+            let taken = |x: i32, y: i32| positions.join().any(|p| p.x == x && p.y == y);
+            (-1..=1).flat_map(|dy| (-1..=1).map(move |dx| (entry.x + dx, entry.y + dy)))
+                .find(|(x, y)| { let t = map.tiles[map.xy_idx(*x, *y)]; (t == TileType::Floor || t == TileType::StairsUp || t == TileType::StairsDown) && !taken(*x, *y) })
+        };
+        let spot = match spot {
+            Some(spot) => spot,
+            // the stairs are crowded; it will push through next turn
+            None => { ecs.write_resource::<EscapeState>().pursuer_due = Some(turn + 1); return; }
+        };
+        let tag = ecs.write_resource::<rltk::RandomNumberGenerator>().next_u64();
+        spawner::monster_by_name(ecs, name, spot, tag);
+
+        let player_pos = *ecs.read_storage::<Position>().get(*ecs.fetch::<Entity>()).unwrap();
+        let entities = ecs.entities();
+        let names = ecs.read_storage::<Name>();
+        let mut combat_stats = ecs.write_storage::<CombatStats>();
+        let mut smart_monsters = ecs.write_storage::<SmartMonster>();
+        for (_entity, n, stats, smart) in (&entities, &names, &mut combat_stats, &mut smart_monsters).join() {
+            if n.name != name { continue; }
+            if let Some(hp) = hp { stats.hp = i32::min(hp, stats.max_hp); }
+            smart.state = SmartMonsterState::Attacking;
+            smart.target_location = Some(player_pos);
+        }
+        ecs.write_resource::<EventQueue>().emit(GameEvent::PursuerArrived { name: name.to_string() });
+    }
+}
+
+/// Follows the escape through the game's events: it starts with the Amulet, tallies the
+/// damage taken on the way out, and tracks the pursuer from lord to wraith to banishment
+pub fn record_escape(escape: &mut EscapeState, stats: &RunStats, event: &GameEvent, player_entity: Entity) {
+    match event {
+        GameEvent::PickedUp { item: super::PickedUpItem::Amulet, .. } => {
+            escape.begin(stats.turns, stats.kills.contains_key("Barrow-Lord"));
+        }
+        GameEvent::Attacked { target, hp_damage, .. } | GameEvent::BossSpecial { target, hp_damage, .. } if escape.active && *target == player_entity => {
+            escape.damage_taken += hp_damage;
+        }
+        GameEvent::Died { name, .. } if escape.active && Some(name.as_str()) == escape.pursuer.name() => {
+            escape.pursuer = if escape.pursuer == Pursuer::Lord { Pursuer::Wraith } else { Pursuer::Banished };
+            escape.pursuer_hp = None;
+            // the lord's spirit takes a little while to gather itself
+            escape.pursuer_due = if escape.pursuer == Pursuer::Wraith { Some(stats.turns + 15) } else { None };
+        }
+        _ => {}
+    }
+}
+
+/// Whether the player is carrying the Amulet out
+pub fn escaping(ecs: &World) -> bool {
+    ecs.fetch::<EscapeState>().active
+}
@@ -0,0 +1,353 @@
+use specs::prelude::*;
+use super::{AttackMove, CombatStance, Containers, Map, Position, RunStats, Animations, TravelPath, TrapKind, SarcophagusContents, Branch, LevelId, EscapeState, escape, boss::SpecialMove, gamelog::{GameLog, LogCategory}};
+
+/// Something that happened in the game world.  Systems emit these into the EventQueue,
+/// and the consumers below turn them into log lines, statistics, sounds and achievements.
+#[derive(PartialEq, Clone, Debug)]
+pub enum GameEvent {
+    /// Carries where the target stood, since a target killed by the blow is gone by the time
+    /// the event is processed
+    Attacked { attacker: Entity, attacker_name: String, target: Entity, target_name: String, target_pos: Position, attack: AttackMove, hp_damage: i32, ep_damage: i32 },
+    SuperEffective { name: String, technique: String },
+    StanceChanged { entity: Entity, name: String, from: CombatStance, to: CombatStance },
+    Stunned { entity: Entity, name: String },
+    Exhausted { entity: Entity, name: String },
+    Recovered { entity: Entity, name: String, ep: i32 },
+    AteFood { entity: Entity, name: String },
+    ContainerOpened { name: String, container: Containers },
+    PickedUp { entity: Entity, item: PickedUpItem },
+    Died { entity: Entity, name: String, is_player: bool, pos: Position, glyph: rltk::FontCharType },
+    DoorOpened { entity: Entity, name: String },
+    DoorClosed,
+    RubbleCleared,
+    TrapSprung { entity: Entity, kind: TrapKind, damage: i32 },
+    SarcophagusOpened { contents: SarcophagusContents },
+    TorchSputtering { fuel: i32 },
+    TorchOut,
+    Descended { level: LevelId },
+    Ascended { depth: i32, has_amulet: bool },
+    QuickAscended,
+    DoomDeepens { level: i32 },
+    DoomDrain,
+    PursuerArrived { name: String },
+    BossPhase { name: String, title: &'static str, announce: &'static str },
+    BossSpecial { name: String, target: Entity, special: SpecialMove, hp_damage: i32 },
+    Fled { entity: Entity, name: String },
+    MonsterPickedUp { name: String, item: String },
+    Dropped { name: String },
+    Rallied { entity: Entity, name: String },
+    StairsSealed,
+    StairsUnsealed,
+    Escaped { grade: char },
+    Anomaly { name: String }
+}
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum PickedUpItem { Coins(i32), Amulet }
+
+pub struct EventQueue {
+    pub events: Vec<GameEvent>
+}
+
+impl EventQueue {
+    pub fn new() -> EventQueue {
+        EventQueue { events: vec![] }
+    }
+
+    pub fn emit(&mut self, event: GameEvent) {
+        self.events.push(event);
+    }
+}
+
+impl Default for EventQueue {
+    fn default() -> EventQueue {
+        EventQueue::new()
+    }
+}
+
+/// Achievements unlocked during the current run
+pub struct Achievements {
+    pub unlocked: Vec<&'static str>,
+    pub super_effective_count: i32
+}
+
+impl Achievements {
+    pub fn new() -> Achievements {
+        Achievements { unlocked: vec![], super_effective_count: 0 }
+    }
+}
+
+impl Default for Achievements {
+    fn default() -> Achievements {
+        Achievements::new()
+    }
+}
+
+/// The sound cues raised by the latest batch of events.  There is no audio backend yet; one
+/// only has to drain this each frame to give the game sound.
+pub struct SoundCues {
+    pub pending: Vec<&'static str>
+}
+
+impl SoundCues {
+    pub fn new() -> SoundCues {
+        SoundCues { pending: vec![] }
+    }
+}
+
+impl Default for SoundCues {
+    fn default() -> SoundCues {
+        SoundCues::new()
+    }
+}
+
+/// Drains the queue and hands every event to each consumer in turn
+pub fn process_events(ecs: &mut World) {
+    let events : Vec<GameEvent> = ecs.write_resource::<EventQueue>().events.drain(..).collect();
+    if events.is_empty() { return; }
+
+    let player_entity = *ecs.fetch::<Entity>();
+    let depth = ecs.fetch::<Map>().depth;
+    let mut log = ecs.write_resource::<GameLog>();
+    let mut stats = ecs.write_resource::<RunStats>();
+    let mut achievements = ecs.write_resource::<Achievements>();
+    let mut travel = ecs.write_resource::<TravelPath>();
+    let mut animations = ecs.write_resource::<Animations>();
+    let mut escape_state = ecs.write_resource::<EscapeState>();
+    let mut sounds = ecs.write_resource::<SoundCues>();
+    sounds.pending.clear();
+    let positions = ecs.read_storage::<Position>();
+
+    for event in events.iter() {
+        log_event(&mut log, event, player_entity);
+        record_stats(&mut stats, event, player_entity, depth);
+        escape::record_escape(&mut escape_state, &stats, event, player_entity);
+        check_achievements(&mut achievements, &mut log, event);
+        interrupt_travel(&mut travel, event, player_entity);
+        animate_event(&mut animations, event, &positions);
+        play_sounds(&mut sounds, event, player_entity);
+    }
+}
+
+fn play_sounds(sounds: &mut SoundCues, event: &GameEvent, player_entity: Entity) {
+    let cue = match event {
+        GameEvent::Attacked { target, .. } if *target == player_entity => "player_hit",
+        GameEvent::Attacked { .. } => "hit",
+        GameEvent::SuperEffective { .. } => "super_effective",
+        GameEvent::Stunned { .. } => "stun",
+        GameEvent::Died { is_player: true, .. } => "player_death",
+        GameEvent::Died { .. } => "death",
+        GameEvent::PickedUp { item: PickedUpItem::Coins(_), .. } => "coins",
+        GameEvent::PickedUp { item: PickedUpItem::Amulet, .. } => "amulet",
+        GameEvent::DoorOpened { .. } | GameEvent::DoorClosed => "door",
+        GameEvent::TrapSprung { .. } => "trap",
+        GameEvent::Descended { .. } | GameEvent::Ascended { .. } | GameEvent::QuickAscended => "stairs",
+        GameEvent::BossPhase { .. } => "boss_roar",
+        GameEvent::Escaped { .. } => "victory",
+        _ => return
+    };
+    sounds.pending.push(cue);
+}
+
+pub fn event_category(event: &GameEvent) -> LogCategory {
+    match event {
+        GameEvent::ContainerOpened { .. } | GameEvent::PickedUp { .. } | GameEvent::SarcophagusOpened { .. } => LogCategory::Loot,
+        GameEvent::MonsterPickedUp { .. } | GameEvent::Dropped { .. } => LogCategory::Loot,
+        GameEvent::Descended { .. } | GameEvent::Ascended { .. } | GameEvent::QuickAscended => LogCategory::Movement,
+        GameEvent::DoorOpened { .. } | GameEvent::DoorClosed | GameEvent::RubbleCleared => LogCategory::Movement,
+        GameEvent::Died { is_player: true, .. } | GameEvent::Escaped { .. } | GameEvent::Anomaly { .. } => LogCategory::System,
+        GameEvent::TorchSputtering { .. } | GameEvent::TorchOut => LogCategory::System,
+        GameEvent::DoomDeepens { .. } | GameEvent::DoomDrain | GameEvent::PursuerArrived { .. } => LogCategory::System,
+        GameEvent::StairsSealed | GameEvent::StairsUnsealed => LogCategory::Movement,
+        _ => LogCategory::Combat
+    }
+}
+
+fn log_event(log: &mut GameLog, event: &GameEvent, player_entity: Entity) {
+    let category = event_category(event);
+    match event {
+        GameEvent::Attacked { attacker_name, target_name, attack, hp_damage, ep_damage, .. } => {
+            let attack_verb_string = match attack {
+                AttackMove::Smash => "smashes",
+                AttackMove::Bash  => "shield bashes",
+                _     => "attacks"
+            };
+            if *ep_damage != 0 {
+                log.push_category(category, format!("{} {} #[orange]{}#[] for #[orange]{} hp#[] ({} ep).", attacker_name, attack_verb_string, target_name, hp_damage, ep_damage));
+            } else {
+                log.push_category(category, format!("{} {} #[orange]{}#[] for #[orange]{} hp#[].", attacker_name, attack_verb_string, target_name, hp_damage));
+            }
+        }
+        GameEvent::SuperEffective { name, technique } => log.push_category(category, format!("{}'s {} is super effective!", name, technique)),
+        GameEvent::StanceChanged { name, to: CombatStance::Stun, .. } => log.push_category(category, format!("#[red]{} is stunned!#[]", name)),
+        GameEvent::StanceChanged { .. } => {}
+        GameEvent::Stunned { entity, .. } if *entity == player_entity => {
+            log.push_category(category, format!("#[red]You are stunned#[], recovering... (you cannot attack, move or wait to recover)"));
+        }
+        GameEvent::Stunned { name, .. } => log.push_category(category, format!("#[red]{} is stunned#[], recovering...", name)),
+        GameEvent::Exhausted { name, .. } => log.push_category(category, format!("#[yellow]{}#[] has insufficient ep, recovering...", name)),
+        GameEvent::Recovered { name, ep, .. } => log.push_category(category, format!("{} recovers {} ep.", name, ep)),
+        GameEvent::AteFood { .. } => {}
+        GameEvent::ContainerOpened { name, container: Containers::Treasure } => log.push_category(category, format!("{} opens the treasure chest!", name)),
+        GameEvent::ContainerOpened { name, container: Containers::Barrel } => log.push_category(category, format!("{} smashes the barrel.", name)),
+        GameEvent::PickedUp { item: PickedUpItem::Coins(amount), .. } => log.push_category(category, format!("You pick up {} coins from the ground.", amount)),
+        GameEvent::PickedUp { item: PickedUpItem::Amulet, .. } => {
+            log.push_category(category, format!("You take the Amulet of Yendor, and feel its dark power course through your body."));
+            log.push_category(category, format!("Now you must escape the dungeon before the darkness consumes you!"));
+        }
+        GameEvent::Died { is_player: true, .. } => {
+            log.push_category(category, format!("#[red]You died! "));
+            log.push_category(category, format!("#[pink]Press ESCAPE to return to the menu."));
+        }
+        GameEvent::Died { name, .. } => log.push_category(category, format!("#[orange]{}#[] is dead", name)),
+        GameEvent::DoorOpened { name, .. } => log.push_category(category, format!("{} opens the door.", name)),
+        GameEvent::DoorClosed => log.push_category(category, format!("You close the door.")),
+        GameEvent::RubbleCleared => log.push_category(category, format!("You clear away the rubble.")),
+        GameEvent::TrapSprung { kind: TrapKind::Dart, damage, .. } => log.push_category(category, format!("#[red]A dart shoots from the wall#[] for #[orange]{} hp#[]!", damage)),
+        GameEvent::TrapSprung { kind: TrapKind::Pit, .. } => log.push_category(category, format!("#[red]The floor gives way and you fall into the darkness below!#[]")),
+        GameEvent::TrapSprung { kind: TrapKind::Alarm, .. } => log.push_category(category, format!("#[red]A pressure plate clicks, and a bell tolls through the barrow!#[]")),
+        GameEvent::SarcophagusOpened { contents: SarcophagusContents::Undead(name) } => log.push_category(category, format!("You lift the lid, and a #[orange]{}#[] rises from the sarcophagus!", name)),
+        GameEvent::SarcophagusOpened { contents: SarcophagusContents::Coins(amount) } => log.push_category(category, format!("You lift the lid and find {} coins among the grave goods.", amount)),
+        GameEvent::TorchSputtering { fuel } => log.push_category(category, format!("#[yellow]Your torch sputters; it has {} turns of fuel left.#[]", fuel)),
+        GameEvent::TorchOut => log.push_category(category, format!("#[red]Your torch gutters out, leaving only embers.#[] Buy more oil in town.")),
+        GameEvent::SarcophagusOpened { contents: SarcophagusContents::Empty } => log.push_category(category, format!("You lift the lid, but the sarcophagus holds only dust.")),
+        GameEvent::Descended { level } => {
+            if level.branch != Branch::Barrow && level.depth == *level.branch.depths().start() {
+                log.push_category(category, format!("You leave the barrow's halls and descend into #[orange]{}#[]...", level.branch.name()));
+            } else {
+                log.push_category(category, format!("You descend deeper into the barrow..."));
+            }
+            log.push_category(category, format!("(loading level {})", level.depth));
+        }
+        GameEvent::Ascended { depth, has_amulet: true } => {
+            if *depth > 0 {
+                log.push_category(category, format!("You ascend toward town, but the amulet's darkness pervades your mind..."));
+                log.push_category(category, format!("(loading level {})", depth));
+            }
+        }
+        GameEvent::Ascended { depth, has_amulet: false } => {
+            if *depth > 0 {
+                log.push_category(category, format!("You retreat from the depths (loading level {})", depth));
+            } else {
+                log.push_category(category, format!("You ascend toward town, but the barrow beckons you to return..."));
+            }
+        }
+        GameEvent::QuickAscended => log.push_category(category, format!("You quickly ascend to town, but the barrow beckons you to return...")),
+        GameEvent::DoomDeepens { level } => {
+            let warning = match level {
+                1 => "The shadows lengthen behind you.",
+                2 => "Your torch gutters; the dark presses in on you.",
+                3 => "The darkness gnaws at you with every step.",
+                _ => "The Amulet's darkness is consuming you!"
+            };
+            log.push_category(category, format!("#[red]{}#[] (doom {})", warning, level));
+        }
+        GameEvent::DoomDrain => {}
+        GameEvent::PursuerArrived { name } => log.push_category(category, format!("#[red]{} comes up the stairs behind you!#[]", name)),
+        GameEvent::BossPhase { announce, .. } if announce.is_empty() => {}
+        GameEvent::BossPhase { announce, .. } => log.push_category(category, format!("#[red]{}#[]", announce)),
+        GameEvent::BossSpecial { name, special: SpecialMove::DarkPulse { ep, .. }, hp_damage, .. } => log.push_category(category, format!("{} unleashes a pulse of darkness for #[orange]{} hp#[] ({} ep)!", name, hp_damage, ep)),
+        GameEvent::BossSpecial { name, special: SpecialMove::LifeDrain { .. }, hp_damage, .. } => log.push_category(category, format!("{} drains #[orange]{} hp#[] of your life!", name, hp_damage)),
+        GameEvent::MonsterPickedUp { name, item } => log.push_category(category, format!("#[orange]{}#[] snatches up the {}.", name, item.to_lowercase())),
+        GameEvent::Dropped { name } => log.push_category(category, format!("#[orange]{}#[] drops what it was carrying.", name)),
+        GameEvent::Fled { name, .. } => log.push_category(category, format!("#[yellow]{}#[] loses its nerve and flees!", name)),
+        GameEvent::Rallied { name, .. } => log.push_category(category, format!("#[orange]{}#[] rallies and turns to fight.", name)),
+        GameEvent::StairsSealed => log.push_category(category, format!("#[red]Dark energies seal the stairs; there is no escape while the boss lives.#[]")),
+        GameEvent::StairsUnsealed => log.push_category(category, format!("The seal on the stairs breaks.")),
+        GameEvent::Escaped { grade } => {
+            log.push_category(category, format!("You return to town safely with the Amulet..."));
+            log.push_category(category, format!("But Yendor's darkness clings to your spirit."));
+            log.push_category(category, format!("#[yellow]Escape graded {}#[]", grade));
+            log.push_category(category, format!("#[red](You have won Barrow!)"));
+            log.push_category(category, format!("#[magenta](Press ESCAPE to return to the main menu!)"));
+        }
+        GameEvent::Anomaly { name } => log.push_category(category, format!("Anomaly: {} has an incoherent intent", name))
+    }
+}
+
+fn record_stats(stats: &mut RunStats, event: &GameEvent, player_entity: Entity, depth: i32) {
+    match event {
+        GameEvent::Attacked { attacker_name, target, .. } if *target == player_entity => {
+            stats.last_hit_by = Some(attacker_name.clone());
+        }
+        GameEvent::BossSpecial { name, target, .. } if *target == player_entity => {
+            stats.last_hit_by = Some(name.clone());
+        }
+        GameEvent::AteFood { entity, .. } if *entity == player_entity => stats.food_eaten += 1,
+        GameEvent::PickedUp { item: PickedUpItem::Coins(amount), .. } => stats.coins_earned += amount,
+        GameEvent::SarcophagusOpened { contents: SarcophagusContents::Coins(amount) } => stats.coins_earned += amount,
+        GameEvent::TrapSprung { kind: TrapKind::Dart, .. } => stats.last_hit_by = Some(String::from("a dart trap")),
+        GameEvent::Died { is_player: true, .. } => {
+            let killer = stats.last_hit_by.clone().unwrap_or(String::from("the darkness"));
+            stats.cause_of_death = Some(format!("Slain by {} on depth {}", killer, depth));
+        }
+        GameEvent::Died { name, .. } => stats.record_kill(name),
+        GameEvent::DoomDrain => stats.last_hit_by = Some(String::from("the Amulet's darkness")),
+        GameEvent::Escaped { grade } => {
+            stats.victory = true;
+            stats.cause_of_death = Some(format!("Escaped the barrow with the Amulet, graded {}", grade));
+        }
+        _ => {}
+    }
+}
+
+/// Auto-walking stops whenever something worth the player's attention happens to them
+fn interrupt_travel(travel: &mut TravelPath, event: &GameEvent, player_entity: Entity) {
+    match event {
+        GameEvent::Attacked { target, .. } | GameEvent::BossSpecial { target, .. } if *target == player_entity => travel.clear(),
+        GameEvent::BossPhase { .. } => travel.clear(),
+        GameEvent::PickedUp { .. } | GameEvent::ContainerOpened { .. } | GameEvent::TrapSprung { .. } => travel.clear(),
+        _ => {}
+    }
+}
+
+/// Combat feedback on the map: damage numbers, hit flashes, stance icons and death fades
+fn animate_event(animations: &mut Animations, event: &GameEvent, positions: &ReadStorage<Position>) {
+    match event {
+        GameEvent::Attacked { target_pos, hp_damage, .. } => {
+            animations.damage_number(*target_pos, *hp_damage);
+            if *hp_damage > 0 { animations.hit_flash(*target_pos); }
+        }
+        GameEvent::BossSpecial { target, hp_damage, .. } => {
+            if let Some(pos) = positions.get(*target) {
+                animations.damage_number(*pos, *hp_damage);
+                animations.hit_flash(*pos);
+            }
+        }
+        GameEvent::StanceChanged { entity, to, .. } => {
+            if let Some(pos) = positions.get(*entity) { animations.stance_icon(*entity, *pos, *to); }
+        }
+        GameEvent::Stunned { entity, .. } => {
+            if let Some(pos) = positions.get(*entity) { animations.stance_icon(*entity, *pos, CombatStance::Stun); }
+        }
+        GameEvent::Died { pos, glyph, .. } => animations.death_fade(*pos, *glyph),
+        _ => {}
+    }
+}
+
+fn check_achievements(achievements: &mut Achievements, log: &mut GameLog, event: &GameEvent) {
+    let mut unlocked : Vec<&'static str> = vec![];
+    match event {
+        GameEvent::Died { name, is_player: false, .. } => {
+            unlocked.push("First Blood");
+            if name == "Troll" { unlocked.push("Troll Slayer"); }
+            if name == "Barrow-Lord" { unlocked.push("Kingslayer"); }
+        }
+        GameEvent::SuperEffective { .. } => {
+            achievements.super_effective_count += 1;
+            if achievements.super_effective_count >= 10 { unlocked.push("Tactician"); }
+        }
+        GameEvent::Descended { level } if level.depth >= 6 => unlocked.push("Into the Deep"),
+        GameEvent::Escaped { grade } => {
+            unlocked.push("Escape Artist");
+            if *grade == 'S' { unlocked.push("Into the Light"); }
+        }
+        _ => {}
+    };
+    for name in unlocked {
+        if !achievements.unlocked.contains(&name) {
+            achievements.unlocked.push(name);
+            log.push(format!("#[yellow]Achievement unlocked: {}#[]", name));
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use specs::prelude::*;
+use super::{Map, Player, Position, Viewshed, RunState, TileType, EventQueue, GameEvent, GameLog, gamelog::LogCategory, spawner, Branch, LevelId};
+
+/// What was found inside an opened sarcophagus
+#[derive(PartialEq, Clone, Debug)]
+pub enum SarcophagusContents { Undead(String), Coins(i32), Empty }
+
+/// Rubble and sarcophagi block movement, so bumping into them is how the player interacts.
+/// Returns true if the bump was used up by a dungeon feature.
+pub fn try_interact(delta_x: i32, delta_y: i32, ecs: &mut World) -> bool {
+    let (idx, tile) = {
+        let player_entity = ecs.fetch::<Entity>();
+        let positions = ecs.read_storage::<Position>();
+        let map = ecs.fetch::<Map>();
+        let pos = positions.get(*player_entity).unwrap();
+        let (x, y) = (pos.x + delta_x, pos.y + delta_y);
+        if x < 1 || x > map.width-1 || y < 1 || y > map.height-1 { return false; }
+        let idx = map.xy_idx(x, y);
+        (idx, map.tiles[idx])
+    };
+
+    match tile {
+        TileType::Rubble => {
+            ecs.write_resource::<Map>().tiles[idx] = TileType::Floor;
+            ecs.write_resource::<EventQueue>().emit(GameEvent::RubbleCleared);
+            return true;
+        }
+        TileType::Sarcophagus => {
+            open_sarcophagus(idx, ecs);
+            return true;
+        }
+        _ => return false
+    }
+}
+
+/// Lifts the lid of a sarcophagus: the dead inside may rise, or there may be grave goods
+fn open_sarcophagus(idx: usize, ecs: &mut World) {
+    let (depth, width) = {
+        let mut map = ecs.write_resource::<Map>();
+        map.tiles[idx] = TileType::SarcophagusOpen;
+        (map.depth, map.width)
+    };
+    let (roll, tag, coins, wight) = {
+        let mut rng = ecs.write_resource::<rltk::RandomNumberGenerator>();
+        (rng.roll_dice(1, 6), rng.next_u64(), rng.roll_dice(2, 10) + depth * 5, rng.range(0, 6) < depth)
+    };
+
+    let contents = if roll <= 3 {
+        let name = if wight { "Wight" } else { "Skeleton" };
+        match free_tile_near(idx, ecs) {
+            Some(spawn_idx) => {
+                spawner::monster_by_name(ecs, name, (spawn_idx as i32 % width, spawn_idx as i32 / width), tag);
+                SarcophagusContents::Undead(String::from(name))
+            }
+            None => SarcophagusContents::Empty
+        }
+    } else if roll <= 5 {
+        let player_entity = *ecs.fetch::<Entity>();
+        if let Some(player) = ecs.write_storage::<Player>().get_mut(player_entity) {
+            player.coin += coins;
+        }
+        SarcophagusContents::Coins(coins)
+    } else {
+        SarcophagusContents::Empty
+    };
+    ecs.write_resource::<EventQueue>().emit(GameEvent::SarcophagusOpened { contents: contents });
+}
+
+fn free_tile_near(idx: usize, ecs: &World) -> Option<usize> {
+    let map = ecs.fetch::<Map>();
+    let x = idx as i32 % map.width;
+    let y = idx as i32 / map.width;
+    let neighbours = [(x-1, y), (x+1, y), (x, y-1), (x, y+1), (x-1, y-1), (x+1, y-1), (x-1, y+1), (x+1, y+1)];
+    neighbours.iter()
+        .map(|(nx, ny)| map.xy_idx(*nx, *ny))
+        .find(|n| map.tiles[*n] == TileType::Floor && !map.blocked[*n] && map.tile_content[*n].is_empty())
+}
+
+/// Closes an open door next to the player, as long as nothing is standing in the doorway
+pub fn try_close_door(ecs: &mut World) -> RunState {
+    let door = {
+        let player_entity = ecs.fetch::<Entity>();
+        let positions = ecs.read_storage::<Position>();
+        let map = ecs.fetch::<Map>();
+        let pos = positions.get(*player_entity).unwrap();
+        let mut door = None;
+        for dy in -1 ..= 1 {
+            for dx in -1 ..= 1 {
+                let idx = map.xy_idx(pos.x + dx, pos.y + dy);
+                if map.tiles[idx] == TileType::DoorOpen && map.tile_content[idx].is_empty() {
+                    door = Some(idx);
+                }
+            }
+        }
+        door
+    };
+
+    match door {
+        Some(idx) => {
+            ecs.write_resource::<Map>().tiles[idx] = TileType::DoorClosed;
+            for viewshed in (&mut ecs.write_storage::<Viewshed>()).join() {
+                viewshed.dirty = true;
+            }
+            ecs.write_resource::<EventQueue>().emit(GameEvent::DoorClosed);
+            return RunState::PlayerTurn
+        }
+        None => {
+            ecs.write_resource::<GameLog>().push_category(LogCategory::Movement, "There is no open door to close.");
+            return RunState::AwaitingInput
+        }
+    }
+}
+
+/// A sprung pit drops the player to the next level down at the end of their turn
+pub fn fell_through_pit(ecs: &World) -> Option<LevelId> {
+    let player_entity = ecs.fetch::<Entity>();
+    let positions = ecs.read_storage::<Position>();
+    let mut players = ecs.write_storage::<Player>();
+    let map = ecs.fetch::<Map>();
+
+    let pos = positions.get(*player_entity)?;
+    match map.tiles[map.xy_idx(pos.x, pos.y)] {
+        TileType::Trap { kind: super::TrapKind::Pit, hidden: false } => {
+            // nothing lies below the bottom of a branch
+            let next_level = map.level_id().down()?.level;
+            if let Some(player) = players.get_mut(*player_entity) {
+                if next_level.branch == Branch::Barrow {
+                    player.deepest_level = i32::max(player.deepest_level, next_level.depth);
+                }
+            }
+            Some(next_level)
+        }
+        _ => None
+    }
+}
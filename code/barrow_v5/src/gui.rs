@@ -1,17 +1,20 @@
-use rltk::{ RGB, RGBA, Rltk, Point, VirtualKeyCode };
+use rltk::{ RGB, Rltk, Point, VirtualKeyCode, FastNoise };
 use rltk::console;
 use specs::prelude::*;
-use super::player::get_available_moves;
-use super::{CombatStats, Player, Monster, gamelog::GameLog, Map, Name, Position, RunState, State, Command, MenuCommand};
+use super::{CombatStats, Player, Monster, gamelog::{GameLog, LogView, LOG_CATEGORIES, wrap_markup}, Map, Name, Position, RunState, State, Command, MenuCommand, RunSettings, Difficulty, RunStats, EscapeState, Boss, LevelId, CombatStance, morale, dungeon::ALL_BRANCHES};
 use super::Command::*;
 use super::AttackMove::*;
 use super::WaitMove::*;
 use super::CombatStance::*;
 use ShoppingResult::*;
-use bracket_terminal::prelude::TextAlign;
+use super::keymap::{Keymap, InputAction, ALL_ACTIONS, is_bindable};
+use super::lighting_system::{TORCH_FUEL_LOW, TORCH_FUEL_MAX};
+use super::camera::Camera;
+use super::{Renderable, TileType, tile_glyph};
+use super::ui::{self, Region, ScreenLayout, VStack, Bar, Panel, List, Modal, Focus, list_focus};
 
 #[derive(PartialEq, Copy, Clone)]
-pub enum MainMenuSelection { NewGame, Quit, CheatMode }
+pub enum MainMenuSelection { NewGame, Difficulty, Ruleset, NoTownShop, DoubleMonsters, Permadark, FoodScarcity, HallOfTheFallen, KeyBindings, Quit }
 
 #[derive(PartialEq, Copy, Clone)]
 pub enum MainMenuResult { NoSelection{ selected : MainMenuSelection }, Selected{ selected: MainMenuSelection } }
@@ -29,12 +32,55 @@ pub struct ShoppingMenuItem {
 #[derive(PartialEq, Copy, Clone)]
 pub enum GameOverResult { NoSelection, QuitToMenu }
 
-// pub fn draw_stat_bar(text, current_stat, max_stat, x, y, w, text_color,ctx: &mut Rltk)
+#[derive(PartialEq, Copy, Clone)]
+pub enum HallResult { Selected{ selected: i32 }, Back }
 
-pub fn draw_ui(ecs: &World, ctx : &mut Rltk) {
-    // ctx.draw_box(0, 43, 49, 16, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK));
-    // ctx.draw_box(50, 0, 29, 42, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK));
+#[derive(PartialEq, Copy, Clone)]
+pub enum LogHistoryResult { Scrolled{ offset: i32 }, Close }
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum OverviewResult { Open, Close }
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum KeyBindingsResult { Selected{ selection: i32, capturing: bool }, Back }
+
+/// Rows of the sidebar that respond to clicks, recorded each frame by draw_ui
+pub struct PanelLayout {
+    /// None until the sidebar has been drawn and the console's size is known
+    pub sidebar: Option<Region>,
+    pub targets: Vec<(i32, usize)>,
+    pub commands: Vec<(i32, usize)>
+}
+
+impl PanelLayout {
+    pub fn new() -> PanelLayout {
+        PanelLayout { sidebar: None, targets: vec![], commands: vec![] }
+    }
+}
+
+impl Default for PanelLayout {
+    fn default() -> PanelLayout {
+        PanelLayout::new()
+    }
+}
+
+fn stance_colour(stance: CombatStance) -> RGB {
+    match stance {
+        Ready => RGB::named(rltk::YELLOW),
+        Power => RGB::named(rltk::CYAN),
+        Guard => RGB::named(rltk::GREEN),
+        Stun => RGB::named(rltk::RED)
+    }
+}
 
+/// A labelled row of the sidebar with an hp or ep bar filling its right-hand side
+fn stat_row(ctx: &mut Rltk, at: Region, text_colour: RGB, text: String, bar: Bar) {
+    let (text_region, bar_region) = at.split_columns(14);
+    ui::label(ctx, text_region, text_colour, text);
+    bar.draw(ctx, bar_region);
+}
+
+pub fn draw_ui(ecs: &World, ctx : &mut Rltk) {
     let combat_stats = ecs.read_storage::<CombatStats>();
     let players = ecs.read_storage::<Player>();
 
@@ -43,125 +89,123 @@ pub fn draw_ui(ecs: &World, ctx : &mut Rltk) {
     let positions = ecs.read_storage::<Position>();
     let entities = ecs.entities();
     let map = ecs.fetch::<Map>();
+    let keymap = ecs.fetch::<Keymap>();
+    let mut layout = ecs.write_resource::<PanelLayout>();
+    layout.targets.clear();
+    layout.commands.clear();
 
     let mut target_offset = 1;
-    let mut gui_offset = 2;
 
     let mouse_pos = ctx.mouse_pos();
 
     ctx.set_active_console(1);
     ctx.cls();
+    let screen = ScreenLayout::for_console(ctx);
+    layout.sidebar = Some(screen.sidebar);
     let menu_mouse_pos = ctx.mouse_pos();
     let menu_y = menu_mouse_pos.1;
     let mut info_popup : Option<String> = None;
 
     for (player, stats) in (&players, &combat_stats).join() {
-        let health = format!("HP:{}/{} ", stats.hp, stats.max_hp);
-        let name = format!("Player");
-        ctx.print_color(51, 1, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), &name);
-        ctx.draw_bar_horizontal(65, 1, 16, stats.hp, stats.max_hp, RGB::named(rltk::RED), RGB::named(rltk::BLACK));
-        ctx.print_color(68, 1, RGB::named(rltk::WHITE), RGBA::from_f32(0.0,0.0,0.0,0.0), &health);
-        let stance = format!("Stance: {:?}", stats.stance);
-        // console::log(format!("current stance: {:?}", stats.stance));
-        match stats.stance {
-            Ready => ctx.print_color(51, 2, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), &stance),
-            Power => ctx.print_color(51, 2, RGB::named(rltk::CYAN), RGB::named(rltk::BLACK), &stance),
-            Guard => ctx.print_color(51, 2, RGB::named(rltk::GREEN), RGB::named(rltk::BLACK), &stance),
-            Stun => ctx.print_color(51, 2, RGB::named(rltk::RED), RGB::named(rltk::BLACK), &stance),
-            // _ => ctx.print_color(51, 2, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), &stance)
-        };
-
-        let energy = format!("EP:{}/{} ", stats.ep, stats.max_ep);
-        ctx.draw_bar_horizontal(65, 2, 16, stats.ep, stats.max_ep, RGB::named(rltk::BLUE), RGB::named(rltk::BLACK));
-        ctx.print_color(68, 2, RGB::named(rltk::WHITE), RGBA::from_f32(0.0,0.0,0.0,0.0), &energy);
+        let mut sidebar = VStack::new(screen.sidebar);
+        sidebar.gap(1);
 
-        let items = format!("Food:   {}     Coin: {}", &player.food, &player.coin); 
-        ctx.print_color(51, 3, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), &items );
+        let name = format!("Player");
+        let player_rows = sidebar.take(3);
+        let mut rows = VStack::new(player_rows);
+        stat_row(ctx, rows.row(), RGB::named(rltk::WHITE), name.clone(),
+            Bar { value: stats.hp, max: stats.max_hp, colour: RGB::named(rltk::RED), text: format!("HP:{}/{} ", stats.hp, stats.max_hp) });
+        stat_row(ctx, rows.row(), stance_colour(stats.stance), format!("Stance: {:?}", stats.stance),
+            Bar { value: stats.ep, max: stats.max_ep, colour: RGB::named(rltk::BLUE), text: format!("EP:{}/{} ", stats.ep, stats.max_ep) });
+        ui::label(ctx, rows.row(), RGB::named(rltk::WHITE), format!("Food:   {}     Coin: {}", &player.food, &player.coin));
+
+        let torch_color = if player.torch_fuel <= 0 { RGB::named(rltk::RED) } else if player.torch_fuel <= TORCH_FUEL_LOW { RGB::named(rltk::YELLOW) } else { RGB::named(rltk::WHITE) };
+        let (torch, doom) = sidebar.row().split_columns(14);
+        ui::label(ctx, torch, torch_color, format!("Torch:  {}", player.torch_fuel));
+        let escape = ecs.fetch::<EscapeState>();
+        if escape.active {
+            ui::label(ctx, doom, RGB::named(rltk::RED), format!("Doom: {} ({})", escape.doom_level(), escape.doom));
+        }
 
-        if menu_y == 1 || menu_y == 2 || menu_y == 3 {
-            info_popup = Some(monster_tooltip(&name, &stats));
+        if player_rows.contains(menu_mouse_pos.0, menu_y) {
+            info_popup = Some(monster_tooltip(&name, &stats, false));
         }
 
         for (entity, _monster, monster_stats, name, position) in (&entities, &monsters, &combat_stats, &names, &positions).join() {
             let idx = map.xy_idx(position.x, position.y);
             if map.visible_tiles[idx] == true {
                 if stats.visible_targets.contains(&entity) {
-                    if stats.current_target == Some(entity) {
-                        let target_string = format!("{}){}", target_offset, name.name);
-                        ctx.print_color(51, 3 + gui_offset, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), target_string);
-                        ctx.draw_bar_horizontal(65, 3 + gui_offset, 16, monster_stats.hp, monster_stats.max_hp, RGB::named(rltk::RED), RGB::named(rltk::BLACK));
-                        let health = format!("HP:{}/{}", monster_stats.hp, monster_stats.max_hp);
-                        ctx.print_color(68, 3 + gui_offset, RGB::named(rltk::WHITE), RGBA::from_f32(0.0,0.0,0.0,0.0), &health);
-                        let monster_stance = format!("Stance: {:?}", monster_stats.stance);
-                        match monster_stats.stance {
-                            Ready => ctx.print_color(51, 4 + gui_offset, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), monster_stance),
-                            Power => ctx.print_color(51, 4 + gui_offset, RGB::named(rltk::CYAN), RGB::named(rltk::BLACK), monster_stance),
-                            Guard => ctx.print_color(51, 4 + gui_offset, RGB::named(rltk::GREEN), RGB::named(rltk::BLACK), monster_stance),
-                            Stun => ctx.print_color(51, 4 + gui_offset, RGB::named(rltk::RED), RGB::named(rltk::BLACK), monster_stance),
-                            // _ => ctx.print_color(51, 4 + gui_offset, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), monster_stance)
-                
-                        }
-                        ctx.draw_bar_horizontal(65, 4 + gui_offset, 16, monster_stats.ep, monster_stats.max_ep, RGB::named(rltk::BLUE), RGB::named(rltk::BLACK));
-                        let energy = format!("EP:{}/{} ", monster_stats.ep, monster_stats.max_ep);
-                        ctx.print_color(68, 4 + gui_offset, RGB::named(rltk::WHITE), RGBA::from_f32(0.0,0.0,0.0,0.0), &energy);
-
-                        if menu_y == 3 + gui_offset || menu_y == 4 + gui_offset {
-                            info_popup = Some(monster_tooltip(&name.name, &monster_stats));
-                        }
-                        target_offset += 1;
-                        gui_offset += 2;
+                    let fleeing = morale::is_fleeing(ecs, entity);
+                    let shown_name = if fleeing { format!("{} (fleeing)", name.name) } else { name.name.clone() };
+                    let target_rows = if stats.current_target == Some(entity) {
+                        let target_rows = sidebar.take(2);
+                        let mut rows = VStack::new(target_rows);
+                        stat_row(ctx, rows.row(), RGB::named(rltk::YELLOW), format!("{}){}", keymap.primary_label(InputAction::Target(target_offset)), shown_name),
+                            Bar { value: monster_stats.hp, max: monster_stats.max_hp, colour: RGB::named(rltk::RED), text: format!("HP:{}/{}", monster_stats.hp, monster_stats.max_hp) });
+                        stat_row(ctx, rows.row(), stance_colour(monster_stats.stance), format!("Stance: {:?}", monster_stats.stance),
+                            Bar { value: monster_stats.ep, max: monster_stats.max_ep, colour: RGB::named(rltk::BLUE), text: format!("EP:{}/{} ", monster_stats.ep, monster_stats.max_ep) });
+                        target_rows
                     } else {
-                        let target_string = format!("{} {}", target_offset, name.name);
-                        ctx.print(51, 3 + gui_offset, target_string);
-                        if menu_y == 3 + gui_offset {
-                            info_popup = Some(monster_tooltip(&name.name, &monster_stats));
-                        }
-                        target_offset += 1;
-                        gui_offset += 1;
+                        let target_rows = sidebar.row();
+                        ui::label(ctx, target_rows, RGB::named(rltk::WHITE), format!("{} {}", keymap.primary_label(InputAction::Target(target_offset)), shown_name));
+                        target_rows
+                    };
+                    if target_rows.contains(menu_mouse_pos.0, menu_y) {
+                        info_popup = Some(monster_tooltip(&name.name, &monster_stats, fleeing));
+                    }
+                    for y in target_rows.y ..= target_rows.bottom() {
+                        layout.targets.push((y, target_offset));
                     }
+                    target_offset += 1;
                 }
             }
         }
-        gui_offset += 4;
-        ctx.print(51, 0 + gui_offset, format!("                            "));
-        ctx.print(51, 1 + gui_offset, format!("Commands             EP Cost"));
-        ctx.print(51, 2 + gui_offset, format!("--------             -------"));
-        ctx.print(51, 3 + gui_offset, format!("(ASDW) Move                 "));
-        ctx.print(51, 4 + gui_offset, format!("(QEZC) Diag. Move           "));
-        ctx.print(51, 5 + gui_offset, format!("(.)    Descend              "));
-        ctx.print(51, 6 + gui_offset, format!("(,)    Ascend               "));
-        ctx.print(51, 7 + gui_offset, format!("(T)    Return to Town       "));
-
-        gui_offset += 9;
-
-        let moves : Vec<MenuCommand> = get_available_moves(&stats);
-        let mut move_offset = 0;
-        let move_keys : Vec<&str> = vec!["(X/Sp)","(J)","(K)","(L)","(N)","(M)"];
-        for (i,m) in moves.iter().enumerate() {
-            if m.enabled == true {
-                ctx.printer(51, gui_offset + move_offset, format!("#[white]{:6} {}       ", move_keys[i], print_command(&m)), TextAlign::Left,Some(RGBA::named(rltk::BLACK)));
-            } else {
-                // console::log(format!("{:?}",m));
-                ctx.printer(51, gui_offset + move_offset, format!("#[grey]{:6} {}       ", move_keys[i], print_command(&m)), TextAlign::Left,Some(RGBA::named(rltk::BLACK)));
 
-            }
-            if menu_y == gui_offset + move_offset {
+        sidebar.gap(3);
+        ui::label(ctx, sidebar.row(), RGB::named(rltk::WHITE), "Commands             EP Cost");
+        ui::label(ctx, sidebar.row(), RGB::named(rltk::WHITE), "--------             -------");
+        let move_label = format!("({}{}{}{})", keymap.primary_label(InputAction::MoveWest), keymap.primary_label(InputAction::MoveSouth), keymap.primary_label(InputAction::MoveEast), keymap.primary_label(InputAction::MoveNorth));
+        let diag_label = format!("({}{}{}{})", keymap.primary_label(InputAction::MoveNorthWest), keymap.primary_label(InputAction::MoveNorthEast), keymap.primary_label(InputAction::MoveSouthWest), keymap.primary_label(InputAction::MoveSouthEast));
+        let travel_label = format!("({}{})", keymap.primary_label(InputAction::TravelUpStairs), keymap.primary_label(InputAction::TravelDownStairs));
+        let commands : Vec<(String, RGB)> = vec![
+            (move_label, "Move"),
+            (diag_label, "Diag. Move"),
+            (format!("({})", keymap.label(InputAction::Descend)), "Descend"),
+            (format!("({})", keymap.label(InputAction::Ascend)), "Ascend"),
+            (format!("({})", keymap.label(InputAction::QuickAscend)), "Return to Town"),
+            (format!("({})", keymap.label(InputAction::MessageHistory)), "Message History"),
+            (format!("({})", keymap.label(InputAction::AutoExplore)), "Auto-explore"),
+            (travel_label, "Travel to < / >"),
+            (format!("({})", keymap.label(InputAction::CloseDoor)), "Close Door")
+        ].into_iter().map(|(keys, text)| (format!("{:6} {:21}", keys, text), RGB::named(rltk::WHITE))).collect();
+        List::new(&commands, None).draw(ctx, sidebar.take(commands.len() as i32));
+        sidebar.gap(1);
+
+        let moves : Vec<MenuCommand> = ecs.fetch::<RunSettings>().ruleset.rules().available_moves(&stats);
+        for (i,m) in moves.iter().enumerate() {
+            let row = sidebar.row();
+            if row.h == 0 { break; }
+            let colour = if m.enabled { "white" } else { "grey" };
+            ui::markup(ctx, row, format!("#[{}]{:6} {}       ", colour, format!("({})", keymap.label(InputAction::AttackMenu(i))), print_command(&m)));
+            if menu_y == row.y {
                 info_popup = Some(command_tooltip(&m));
             }
-            move_offset += 1;           
+            layout.commands.push((row.y, i));
         }
     }
-    // ctx.target(0);
+
+    draw_boss_bar(ecs, ctx, Region::new(screen.map.x + 1, screen.map.y, screen.map.w - 1, 1));
 
     let log = ecs.fetch::<GameLog>();
-    // ctx.set_active_console(1);
-    // ctx.cls();
-    let mut y = 29;
-    for s in log.entries.iter().rev() {
-        if y >= 21 { 
-            ctx.printer(1, y, format!("#[white]{}",s), TextAlign::Left,Some(RGBA::named(rltk::BLACK))); 
+    let mut y = screen.log.bottom();
+    for entry in log.entries.iter().rev() {
+        for line in wrap_markup(&entry.display(), screen.log.w as usize).iter().rev() {
+            if y >= screen.log.y {
+                ui::markup(ctx, Region::new(screen.log.x, y, screen.log.w, 1), format!("#[white]{}",line));
+            }
+            y -= 1;
         }
-        y -= 1;
+        if y < screen.log.y { break; }
     }
     ctx.set_active_console(0);
 
@@ -172,19 +216,35 @@ pub fn draw_ui(ecs: &World, ctx : &mut Rltk) {
     ctx.set_active_console(1);
 
     // Draw mouse cursor
-    if menu_mouse_pos.0 > 50 && info_popup != None {
-        ctx.set_bg(menu_mouse_pos.0, menu_mouse_pos.1, RGB::named(rltk::MAGENTA));
-        ctx.draw_box(8,1,34,18,rltk::WHITE,rltk::BLACK);
-        let popup_text = info_popup.unwrap();
-        let popup_lines: Vec<&str> = popup_text.split('\n').collect();
-        for (i,line) in popup_lines.iter().enumerate() {
-            ctx.print_color(9,2 + i, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), line);
+    if screen.sidebar.contains(menu_mouse_pos.0, menu_mouse_pos.1) {
+        if let Some(popup_text) = info_popup {
+            ctx.set_bg(menu_mouse_pos.0, menu_mouse_pos.1, RGB::named(rltk::MAGENTA));
+            let inner = Panel { title: None }.draw(ctx, screen.popup);
+            ui::text_block(ctx, inner, RGB::named(rltk::WHITE), &popup_text);
         }
     }
 }
 
-fn monster_tooltip(name: &String, stats: &CombatStats) -> String {
-    return match name.as_str() {
+/// A bar across the top of the map for any boss the player can see, with its current phase
+fn draw_boss_bar(ecs: &World, ctx : &mut Rltk, at: Region) {
+    let bosses = ecs.read_storage::<Boss>();
+    let combat_stats = ecs.read_storage::<CombatStats>();
+    let positions = ecs.read_storage::<Position>();
+    let map = ecs.fetch::<Map>();
+
+    for (boss, stats, position) in (&bosses, &combat_stats, &positions).join() {
+        if !map.visible_tiles[map.xy_idx(position.x, position.y)] { continue; }
+        let phase = &boss.kind.phases()[boss.phase];
+        let (title, bar) = at.split_columns(at.w - 23);
+        ui::label(ctx, title, RGB::named(rltk::ORANGE), format!("{} ({})", boss.kind.name(), phase.title));
+        Bar { value: stats.hp, max: stats.max_hp, colour: RGB::named(rltk::DARK_RED), text: format!("HP:{}/{}", stats.hp, stats.max_hp) }
+            .draw(ctx, Region::new(bar.x, bar.y, bar.w - 1, 1));
+        break;
+    }
+}
+
+fn monster_tooltip(name: &String, stats: &CombatStats, fleeing: bool) -> String {
+    let tooltip = match name.as_str() {
         "Player" => format!("This is you.\nDrawn by legendary riches, \narmed with sword and shield\n{} attack\n{} defense", stats.power, stats.defense),
         "Goblin" => String::from("Goblin\nWeak and cowardly, but numerous\nThoroughly disagreeable\nBlocking is very effective"),
         "Orc" => String::from("Orc\nAttacks fiercely, easily tired.\nFerocious, not to be underestimated\nFend is very effective."),
@@ -193,8 +253,14 @@ fn monster_tooltip(name: &String, stats: &CombatStats) -> String {
         "Hobgoblin" => String::from("Hobgoblin\nCunning and well-armed\nStrong defense\nVulnerable to shield bashes \nwhen stamina is low"),
         "Troll" => String::from("Troll\nBrutish, deadly, albeit dim\nPowerful attacks, low stamina\nFend off its smash attacks, \nretaliate when stamina is low"),
         "Barrow-Lord" => String::from("Aye, Yendor, lord of the Barrow\nHe lives, or something like it\nA profoundly dangerous opponent, \nanimated by dark energies\nPatient and methodical.\nwait for him to expose himself - \nthen strike!"),
+        "Barrow-Wraith" => String::from("Barrow-Wraith\nYendor's spirit, unbound by death\nIt will not rest while you \ncarry the Amulet\nRelentless, strikes hard and often"),
+        "Shade" => String::from("Shade\nA sliver of the Amulet's darkness\nFrail, but there are always more"),
         _ => name.clone()
+    };
+    if fleeing {
+        return format!("{}\n\nIts nerve has broken, and it is fleeing!\nIt may rally if it heals or finds \nits pack", tooltip);
     }
+    tooltip
 }
 
 fn command_tooltip(command: &MenuCommand) -> String {
@@ -232,11 +298,15 @@ fn draw_tooltips(ecs: &World, ctx : &mut Rltk) {
     let positions = ecs.read_storage::<Position>();
 
     let mouse_pos = ctx.mouse_pos();
-    if mouse_pos.0 >= map.width || mouse_pos.1 >= map.height { return; }
+    let camera = Camera::new(ecs);
+    let (world_x, world_y) = match camera.to_world(mouse_pos.0, mouse_pos.1) {
+        Some(world) => world,
+        None => return
+    };
     let mut tooltip : Vec<String> = Vec::new();
     for (name, position) in (&names, &positions).join() {
         let idx = map.xy_idx(position.x, position.y);
-        if position.x == mouse_pos.0 && position.y == mouse_pos.1 && map.visible_tiles[idx] {
+        if position.x == world_x && position.y == world_y && map.visible_tiles[idx] {
             tooltip.push(name.name.to_string());
         }
     }
@@ -248,7 +318,7 @@ fn draw_tooltips(ecs: &World, ctx : &mut Rltk) {
         }
         width += 3;
 
-        if mouse_pos.0 > 40 {
+        if mouse_pos.0 > camera.view.x + camera.view.w - 10 {
             let arrow_pos = Point::new(mouse_pos.0 - 2, mouse_pos.1);
             let left_x = mouse_pos.0 - width;
             let mut y = mouse_pos.1;
@@ -282,61 +352,61 @@ pub fn main_menu(gs : &mut State, ctx : &mut Rltk) -> MainMenuResult {
     let runstate = gs.ecs.fetch::<RunState>();
 
     ctx.set_active_console(1);
-    ctx.print_color_centered(8, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), "Barrow");
+    let screen = ScreenLayout::for_console(ctx);
+    let mut rows = VStack::new(screen.screen);
+    rows.gap(screen.screen.h / 2 - 7);
+    ui::centered(ctx, rows.row(), RGB::named(rltk::YELLOW), "Barrow");
+    rows.gap(2);
 
     if let RunState::MainMenu{ menu_selection : selection } = *runstate {
-        if selection == MainMenuSelection::NewGame {
-            ctx.print_color_centered(12, RGB::named(rltk::MAGENTA), RGB::named(rltk::BLACK), "Begin New Game (press Enter)");
-        } else {
-            ctx.print_color_centered(12, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), "Begin New Game");
-        }
-
-        if selection == MainMenuSelection::Quit {
-            ctx.print_color_centered(13, RGB::named(rltk::MAGENTA), RGB::named(rltk::BLACK), "Quit (press Enter)");
-        } else {
-            ctx.print_color_centered(13, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), "Quit");
-        }
-
-        if gs.cheat_mode == true {
-            ctx.print_color_centered(14, RGB::named(rltk::RED), RGB::named(rltk::BLACK), "CHEAT MODE ENABLED");
-        }
-        else if selection == MainMenuSelection::CheatMode {
-            ctx.print_color_centered(14, RGB::named(rltk::MAGENTA), RGB::named(rltk::BLACK), "Enable Cheat Mode (press Enter)");
-        } else {
-            ctx.print_color_centered(14, RGB::from_u8(30,30,30), RGB::named(rltk::BLACK), "Enable Cheat Mode");
-
+        let settings = gs.settings;
+        let check = |enabled: bool| if enabled { "[x]" } else { "[ ]" };
+        let entries : Vec<(MainMenuSelection, String)> = vec![
+            (MainMenuSelection::NewGame, format!("Begin New Game")),
+            (MainMenuSelection::Difficulty, format!("Difficulty: < {} >", settings.difficulty.name())),
+            (MainMenuSelection::Ruleset, format!("Combat rules: < {} >", settings.ruleset.name())),
+            (MainMenuSelection::NoTownShop, format!("{} No town shop", check(settings.modifiers.no_town_shop))),
+            (MainMenuSelection::DoubleMonsters, format!("{} Double monsters", check(settings.modifiers.double_monsters))),
+            (MainMenuSelection::Permadark, format!("{} Permadark", check(settings.modifiers.permadark))),
+            (MainMenuSelection::FoodScarcity, format!("{} Food scarcity", check(settings.modifiers.food_scarcity))),
+            (MainMenuSelection::HallOfTheFallen, format!("Hall of the Fallen")),
+            (MainMenuSelection::KeyBindings, format!("Key Bindings")),
+            (MainMenuSelection::Quit, format!("Quit"))
+        ];
+
+        let current = entries.iter().position(|(entry, _)| *entry == selection).unwrap_or(0);
+        let shown : Vec<(String, RGB)> = entries.iter().enumerate().map(|(i, (entry, text))| {
+            if i == current {
+                (format!("{} (press Enter)", text), RGB::named(rltk::MAGENTA))
+            } else if *entry == MainMenuSelection::Difficulty && settings.difficulty == Difficulty::Debug {
+                (text.clone(), RGB::named(rltk::RED))
+            } else {
+                (text.clone(), RGB::named(rltk::WHITE))
+            }
+        }).collect();
+        List { entries: &shown, selected: Some(current), highlight: RGB::named(rltk::MAGENTA), gaps_after: &[0, 6], centered: true }
+            .draw(ctx, rows.rest());
+
+        match list_focus(ctx.key, current, entries.len()) {
+            Focus::Moved(i) => return MainMenuResult::NoSelection{ selected: entries[i].0 },
+            Focus::Activated(_) => return MainMenuResult::Selected{ selected : selection },
+            Focus::Cancelled | Focus::Unchanged => {}
         }
-
-
         match ctx.key {
-            None => return MainMenuResult::NoSelection{ selected: selection },
-            Some(key) => {
-                match key {
-                    VirtualKeyCode::Space => { return MainMenuResult::Selected{ selected: MainMenuSelection::NewGame } }
-                    VirtualKeyCode::Up => {
-                        let newselection;
-                        match selection {
-                            MainMenuSelection::NewGame => newselection = MainMenuSelection::CheatMode,
-                            MainMenuSelection::Quit => newselection = MainMenuSelection::NewGame,
-                            MainMenuSelection::CheatMode => newselection = MainMenuSelection::Quit
-
-                        }
-                        return MainMenuResult::NoSelection{ selected: newselection }
-                    }
-                    VirtualKeyCode::Down => {
-                        let newselection;
-                        match selection {
-                            MainMenuSelection::NewGame => newselection = MainMenuSelection::Quit,
-                            MainMenuSelection::Quit => newselection = MainMenuSelection::CheatMode,
-                            MainMenuSelection::CheatMode => newselection = MainMenuSelection::NewGame
-
-                        }
-                        return MainMenuResult::NoSelection{ selected: newselection }
-                    }
-                    VirtualKeyCode::Return => return MainMenuResult::Selected{ selected : selection },
-                    _ => return MainMenuResult::NoSelection{ selected: selection }
-                }
+            Some(VirtualKeyCode::Space) => { return MainMenuResult::Selected{ selected: MainMenuSelection::NewGame } }
+            Some(VirtualKeyCode::Left) if selection == MainMenuSelection::Difficulty => {
+                gs.settings.difficulty = settings.difficulty.prev();
+                return MainMenuResult::NoSelection{ selected: selection }
+            }
+            Some(VirtualKeyCode::Right) if selection == MainMenuSelection::Difficulty => {
+                gs.settings.difficulty = settings.difficulty.next();
+                return MainMenuResult::NoSelection{ selected: selection }
             }
+            Some(VirtualKeyCode::Left) | Some(VirtualKeyCode::Right) if selection == MainMenuSelection::Ruleset => {
+                gs.settings.ruleset = settings.ruleset.next();
+                return MainMenuResult::NoSelection{ selected: selection }
+            }
+            _ => return MainMenuResult::NoSelection{ selected: selection }
         }
     }
 
@@ -348,118 +418,129 @@ pub fn shopping(gs: &mut State, ctx: &mut Rltk) -> ShoppingResult {
     let player_entity = gs.ecs.fetch::<Entity>();
     let mut players = gs.ecs.write_storage::<Player>();
     let mut log = gs.ecs.write_resource::<GameLog>();
+    let settings = *gs.ecs.fetch::<RunSettings>();
 
     let player_inv = players.get_mut(*player_entity).unwrap();
 
     if let RunState::Shopping { menu_selection: selection } = *runstate {
-        let mut new_selection = selection;
-        
         let mut shopping_menu_items: Vec<ShoppingMenuItem> = vec![];
 
-        let food_to_buy = player_inv.max_food - player_inv.food;
+        if settings.town_shop_open() {
+            let food_to_buy = player_inv.max_food - player_inv.food;
 
-        if food_to_buy > 0 {
-            let food_cost = food_to_buy / 5;
-            let mut new_state = player_inv.clone();
-            new_state.food = new_state.max_food;
+            if food_to_buy > 0 {
+                let food_cost = settings.food_cost_multiplier() * food_to_buy / 5;
+                let mut new_state = player_inv.clone();
+                new_state.food = new_state.max_food;
 
-            shopping_menu_items.push(ShoppingMenuItem { 
-                    description: "buy more food", 
-                    cost: food_cost, 
+                shopping_menu_items.push(ShoppingMenuItem { 
+                        description: "buy more food", 
+                        cost: food_cost, 
+                        result: Purchase { new_state: new_state }
+                });
+            }
+
+            if player_inv.max_food < 25 {
+                let pack_upgrade_cost = 15;
+                let mut new_state = player_inv.clone();
+                new_state.max_food = 25;
+                new_state.food = new_state.max_food;            
+                shopping_menu_items.push(ShoppingMenuItem {
+                    description: "buy pack upgrade (25)",
+                    cost: pack_upgrade_cost,
                     result: Purchase { new_state: new_state }
-            });
-        }
+                })
+            } else if player_inv.max_food < 35 {
+                let pack_upgrade_cost = 45;
+                let mut new_state = player_inv.clone();
+                new_state.max_food = 35;
+                new_state.food = new_state.max_food;            
+                shopping_menu_items.push(ShoppingMenuItem {
+                    description: "buy pack upgrade (35)",
+                    cost: pack_upgrade_cost,
+                    result: Purchase { new_state: new_state }
+                })
+            } else if player_inv.max_food < 40 {
+                let pack_upgrade_cost = 65;
+                let mut new_state = player_inv.clone();
+                new_state.max_food = 40;
+                new_state.food = new_state.max_food;            
+                shopping_menu_items.push(ShoppingMenuItem {
+                    description: "buy pack upgrade (40)",
+                    cost: pack_upgrade_cost,
+                    result: Purchase { new_state: new_state }
+                })
+            }
 
-        if player_inv.max_food < 25 {
-            let pack_upgrade_cost = 15;
-            let mut new_state = player_inv.clone();
-            new_state.max_food = 25;
-            new_state.food = new_state.max_food;            
-            shopping_menu_items.push(ShoppingMenuItem {
-                description: "buy pack upgrade (25)",
-                cost: pack_upgrade_cost,
-                result: Purchase { new_state: new_state }
-            })
-        } else if player_inv.max_food < 35 {
-            let pack_upgrade_cost = 45;
-            let mut new_state = player_inv.clone();
-            new_state.max_food = 35;
-            new_state.food = new_state.max_food;            
-            shopping_menu_items.push(ShoppingMenuItem {
-                description: "buy pack upgrade (35)",
-                cost: pack_upgrade_cost,
-                result: Purchase { new_state: new_state }
-            })
-        } else if player_inv.max_food < 40 {
-            let pack_upgrade_cost = 65;
-            let mut new_state = player_inv.clone();
-            new_state.max_food = 40;
-            new_state.food = new_state.max_food;            
-            shopping_menu_items.push(ShoppingMenuItem {
-                description: "buy pack upgrade (40)",
-                cost: pack_upgrade_cost,
-                result: Purchase { new_state: new_state }
-            })
-        }
+            if player_inv.torch_fuel < TORCH_FUEL_MAX {
+                let mut new_state = player_inv.clone();
+                new_state.torch_fuel = i32::min(TORCH_FUEL_MAX, new_state.torch_fuel + 400);
+                shopping_menu_items.push(ShoppingMenuItem {
+                    description: "buy torch oil (+400)",
+                    cost: 10,
+                    result: Purchase { new_state: new_state }
+                })
+            }
 
-        if player_inv.atk_bonus == 0 {
-            let mut new_state = player_inv.clone();
-            new_state.atk_bonus = 1;
-            shopping_menu_items.push(ShoppingMenuItem {
-                description: "buy +1 weapon upgrade",
-                cost: 20,
-                result: Purchase { new_state: new_state }
-            })
-        } else if player_inv.atk_bonus == 1 {
-            let mut new_state = player_inv.clone();
-            new_state.atk_bonus = 2;
-            shopping_menu_items.push(ShoppingMenuItem {
-                description: "buy +2 weapon upgrade",
-                cost: 40,
-                result: Purchase { new_state: new_state }
-            })
-        } else if player_inv.atk_bonus == 2 {
-            let mut new_state = player_inv.clone();
-            new_state.atk_bonus = 3;
-            shopping_menu_items.push(ShoppingMenuItem {
-                description: "buy +3 weapon upgrade",
-                cost: 75,
-                result: Purchase { new_state: new_state }
-            })
-        }
+            if player_inv.atk_bonus == 0 {
+                let mut new_state = player_inv.clone();
+                new_state.atk_bonus = 1;
+                shopping_menu_items.push(ShoppingMenuItem {
+                    description: "buy +1 weapon upgrade",
+                    cost: 20,
+                    result: Purchase { new_state: new_state }
+                })
+            } else if player_inv.atk_bonus == 1 {
+                let mut new_state = player_inv.clone();
+                new_state.atk_bonus = 2;
+                shopping_menu_items.push(ShoppingMenuItem {
+                    description: "buy +2 weapon upgrade",
+                    cost: 40,
+                    result: Purchase { new_state: new_state }
+                })
+            } else if player_inv.atk_bonus == 2 {
+                let mut new_state = player_inv.clone();
+                new_state.atk_bonus = 3;
+                shopping_menu_items.push(ShoppingMenuItem {
+                    description: "buy +3 weapon upgrade",
+                    cost: 75,
+                    result: Purchase { new_state: new_state }
+                })
+            }
 
-        if player_inv.def_bonus == 0 {
-            let mut new_state = player_inv.clone();
-            new_state.def_bonus = 1;
-            shopping_menu_items.push(ShoppingMenuItem {
-                description: "buy +1 armor upgrade",
-                cost: 25,
-                result: Purchase { new_state: new_state }
-            })
-        } else if player_inv.def_bonus == 1 {
-            let mut new_state = player_inv.clone();
-            new_state.def_bonus = 2;
-            shopping_menu_items.push(ShoppingMenuItem {
-                description: "buy +2 armor upgrade",
-                cost: 50,
-                result: Purchase { new_state: new_state }
-            })
-        } else if player_inv.def_bonus == 2 {
-            let mut new_state = player_inv.clone();
-            new_state.def_bonus = 3;
-            shopping_menu_items.push(ShoppingMenuItem {
-                description: "buy +3 armor upgrade",
-                cost: 80,
-                result: Purchase { new_state: new_state }
-            })
-        } else if player_inv.def_bonus == 3 {
-            let mut new_state = player_inv.clone();
-            new_state.def_bonus = 4;
-            shopping_menu_items.push(ShoppingMenuItem {
-                description: "buy +4 armor upgrade",
-                cost: 120,
-                result: Purchase { new_state: new_state }
-            })
+            if player_inv.def_bonus == 0 {
+                let mut new_state = player_inv.clone();
+                new_state.def_bonus = 1;
+                shopping_menu_items.push(ShoppingMenuItem {
+                    description: "buy +1 armor upgrade",
+                    cost: 25,
+                    result: Purchase { new_state: new_state }
+                })
+            } else if player_inv.def_bonus == 1 {
+                let mut new_state = player_inv.clone();
+                new_state.def_bonus = 2;
+                shopping_menu_items.push(ShoppingMenuItem {
+                    description: "buy +2 armor upgrade",
+                    cost: 50,
+                    result: Purchase { new_state: new_state }
+                })
+            } else if player_inv.def_bonus == 2 {
+                let mut new_state = player_inv.clone();
+                new_state.def_bonus = 3;
+                shopping_menu_items.push(ShoppingMenuItem {
+                    description: "buy +3 armor upgrade",
+                    cost: 80,
+                    result: Purchase { new_state: new_state }
+                })
+            } else if player_inv.def_bonus == 3 {
+                let mut new_state = player_inv.clone();
+                new_state.def_bonus = 4;
+                shopping_menu_items.push(ShoppingMenuItem {
+                    description: "buy +4 armor upgrade",
+                    cost: 120,
+                    result: Purchase { new_state: new_state }
+                })
+            }
         }
 
         shopping_menu_items.push(ShoppingMenuItem {
@@ -482,43 +563,30 @@ pub fn shopping(gs: &mut State, ctx: &mut Rltk) -> ShoppingResult {
             });
         }
 
-        let mut execute_selection = false;
-
-        match ctx.key { 
-            Some(VirtualKeyCode::Up) => {
-                new_selection = selection - 1;
-            }
-            Some(VirtualKeyCode::Down) => {
-                new_selection = selection + 1;
-            }
-            Some(VirtualKeyCode::Return) => {
-                // console::log("executing?");
-                execute_selection = true;
-            }
-            _ => {}
-        }
-
-        if new_selection < 0 { new_selection = shopping_menu_items.len() as i32 - 1 }
-        else if new_selection >= shopping_menu_items.len() as i32 { new_selection = 0 }
+        let len = shopping_menu_items.len();
+        let focused = if selection >= 0 && (selection as usize) < len { selection as usize } else { 0 };
+        let (new_selection, execute_selection) = match list_focus(ctx.key, focused, len) {
+            Focus::Moved(i) => (i as i32, false),
+            Focus::Activated(i) => (i as i32, true),
+            Focus::Cancelled | Focus::Unchanged => (focused as i32, false)
+        };
 
         ctx.set_active_console(1);
-        ctx.draw_box(2,1,46,19,rltk::WHITE,rltk::BLACK);
-        
-        let menu_base = 2;
-
-        for (i,menu_item) in shopping_menu_items.iter().enumerate() {
-            let text_color = if i == new_selection as usize { 
-                RGB::named(rltk::YELLOW) 
-            } else { 
-                RGB::named(rltk::WHITE) 
-            };
-            let bg_color = RGB::named(rltk::BLACK);
-            let menu_text = if menu_item.cost > 0  { 
-                format!("${} - {}", menu_item.cost, menu_item.description) 
+        let screen = ScreenLayout::for_console(ctx);
+        let hint = if settings.town_shop_open() { None } else { Some("(the town's shops are shuttered this run)") };
+        let inner = Modal { title: Some("Town"), hint: hint }.draw(ctx, screen.map.centered(47, 20));
+        let shown : Vec<(String, RGB)> = shopping_menu_items.iter().map(|menu_item| {
+            let menu_text = if menu_item.cost > 0  {
+                format!("${} - {}", menu_item.cost, menu_item.description)
             } else {
                 menu_item.description.to_string()
             };
-            ctx.print_color(4, menu_base + i, text_color, bg_color, menu_text);
+            (menu_text, RGB::named(rltk::WHITE))
+        }).collect();
+        List { entries: &shown, selected: Some(new_selection as usize), highlight: RGB::named(rltk::YELLOW), gaps_after: &[], centered: false }
+            .draw(ctx, Region::new(inner.x + 1, inner.y, inner.w - 1, inner.h));
+
+        for (i,menu_item) in shopping_menu_items.iter().enumerate() {
             if execute_selection && i == new_selection as usize {
                 match menu_item.result {
                     Return => {
@@ -530,13 +598,13 @@ pub fn shopping(gs: &mut State, ctx: &mut Rltk) -> ShoppingResult {
                         // console::log("checking purchase");
                         if new_inv.coin >= menu_item.cost {
                             console::log("purchasing");
-                            log.entries.push(format!("You hand over your hard-earned coins"));
+                            log.push(format!("You hand over your hard-earned coins"));
                             new_inv.coin -= menu_item.cost;
                             *player_inv = new_inv;
                             return menu_item.result
                         } else {
                             console::log("not enough coin");
-                            log.entries.push(format!("You can't afford that."));
+                            log.push(format!("You can't afford that."));
                             // return RunState::Shopping { menu_selection: new_selection }
                             return Selected { selected: 0 }
                         }
@@ -556,10 +624,383 @@ pub fn shopping(gs: &mut State, ctx: &mut Rltk) -> ShoppingResult {
 
 }
 
-pub fn game_over(ctx : &mut Rltk) -> GameOverResult {
+pub fn game_over(gs : &mut State, ctx : &mut Rltk) -> GameOverResult {
+    let settings = gs.ecs.fetch::<RunSettings>();
+    let stats = gs.ecs.fetch::<RunStats>();
+
+    ctx.set_active_console(1);
+    let screen = ScreenLayout::for_console(ctx);
+    let inner = Modal { title: None, hint: Some("Press ESCAPE to return to the menu") }.draw(ctx, screen.map.centered(43, 19));
+    let mut rows = VStack::new(Region::new(inner.x + 1, inner.y, inner.w - 1, inner.h));
+    if stats.victory {
+        ui::label(ctx, rows.row(), RGB::named(rltk::YELLOW), "You escaped with the Amulet!");
+    } else {
+        ui::label(ctx, rows.row(), RGB::named(rltk::RED), "You died!");
+    }
+    let cause = rows.row();
+    if let Some(text) = &stats.cause_of_death {
+        ui::label(ctx, cause, RGB::named(rltk::WHITE), text);
+    }
+    rows.gap(1);
+    ui::label(ctx, rows.row(), RGB::named(rltk::WHITE), format!("Difficulty:    {}", settings.describe()));
+    ui::label(ctx, rows.row(), RGB::named(rltk::WHITE), format!("Seed:          {}", stats.seed));
+    ui::label(ctx, rows.row(), RGB::named(rltk::WHITE), format!("Depth reached: {}", stats.deepest_depth));
+    ui::label(ctx, rows.row(), RGB::named(rltk::WHITE), format!("Turns taken:   {}", stats.turns));
+    ui::label(ctx, rows.row(), RGB::named(rltk::WHITE), format!("Coins earned:  {}", stats.coins_earned));
+    ui::label(ctx, rows.row(), RGB::named(rltk::WHITE), format!("Food eaten:    {}", stats.food_eaten));
+    let escape_row = rows.row();
+    if stats.victory {
+        let escape = gs.ecs.fetch::<EscapeState>();
+        ui::label(ctx, escape_row, RGB::named(rltk::YELLOW), format!("Escape:        {} turns, {} hp lost", stats.turns - escape.started_turn, escape.damage_taken));
+    }
+    ui::label(ctx, rows.row(), RGB::named(rltk::WHITE), format!("Kills ({}):", stats.total_kills()));
+    let kills : Vec<(String, RGB)> = stats.kill_list().iter().take(5).map(|(name, count)| (format!("{} x{}", name, count), RGB::named(rltk::ORANGE))).collect();
+    let kill_rows = rows.rest();
+    List::new(&kills, None).draw(ctx, Region::new(kill_rows.x + 2, kill_rows.y, kill_rows.w - 2, kill_rows.h));
+
     match ctx.key {
         None => GameOverResult::NoSelection,
         Some(VirtualKeyCode::Escape) => { return GameOverResult::QuitToMenu }
         Some(_) => GameOverResult::NoSelection
     }
-}
\ No newline at end of file
+}
+
+pub fn hall_of_the_fallen(gs : &mut State, ctx : &mut Rltk) -> HallResult {
+    let runstate = gs.ecs.fetch::<RunState>();
+    let records = &gs.hall_of_the_fallen;
+
+    let selection = match *runstate {
+        RunState::HallOfTheFallen{ menu_selection } => menu_selection,
+        _ => 0
+    };
+
+    ctx.set_active_console(1);
+    let screen = ScreenLayout::for_console(ctx).screen;
+    let mut rows = VStack::new(screen);
+    rows.gap(2);
+    ui::centered(ctx, rows.row(), RGB::named(rltk::YELLOW), "Hall of the Fallen");
+    rows.gap(1);
+
+    let details = Region::new(screen.x + 4, screen.bottom() - 9, screen.w - 9, 8);
+    let list = rows.take(details.y - 1 - rows.rest().y);
+    if records.is_empty() {
+        ui::centered(ctx, Region::new(screen.x, list.y + 1, screen.w, 1), RGB::named(rltk::GREY), "No runs have been recorded yet.");
+    }
+    let entries : Vec<(String, RGB)> = records.iter().enumerate().map(|(i, record)| {
+        let outcome = if record.victory { "Escaped" } else { "Fell" };
+        (format!("{:2}. {:5} {:7} depth {} {:5} turns  {}", i + 1, record.score(), outcome, record.depth, record.turns, record.difficulty), RGB::named(rltk::WHITE))
+    }).collect();
+    List::new(&entries, Some(selection as usize)).draw(ctx, Region::new(list.x + 6, list.y, list.w - 6, list.h));
+
+    if let Some(record) = records.get(selection as usize) {
+        let inner = Panel { title: None }.draw(ctx, details);
+        let mut rows = VStack::new(Region::new(inner.x + 1, inner.y, inner.w - 1, inner.h));
+        ui::label(ctx, rows.row(), RGB::named(rltk::WHITE), &record.cause);
+        ui::label(ctx, rows.row(), RGB::named(rltk::WHITE), format!("Coins earned: {}   Food eaten: {}   Seed: {}", record.coins, record.food, record.seed));
+        ui::label(ctx, rows.row(), RGB::named(rltk::WHITE), format!("Kills ({}):", record.kills));
+        ui::label(ctx, rows.row(), RGB::named(rltk::ORANGE), &record.kill_summary);
+    }
+    ui::centered(ctx, Region::new(screen.x, screen.bottom() - 1, screen.w, 1), RGB::named(rltk::GREY), "(Up/Down to browse, ESCAPE to return)");
+
+    match list_focus(ctx.key, selection as usize, records.len()) {
+        Focus::Cancelled => HallResult::Back,
+        Focus::Moved(i) => HallResult::Selected{ selected: i as i32 },
+        Focus::Activated(_) | Focus::Unchanged => HallResult::Selected{ selected: selection }
+    }
+}
+
+/// Full-screen view of the log history, newest entries at the bottom.
+/// Categories can be toggled with 1-4 and '/' starts a text search.
+pub fn log_history(gs : &mut State, ctx : &mut Rltk) -> LogHistoryResult {
+    let runstate = *gs.ecs.fetch::<RunState>();
+    let log = gs.ecs.fetch::<GameLog>();
+    let keymap = gs.ecs.fetch::<Keymap>();
+    let mut view = gs.ecs.write_resource::<LogView>();
+
+    let offset = match runstate {
+        RunState::LogHistory{ offset } => offset,
+        _ => 0
+    };
+
+    ctx.set_active_console(1);
+    let screen = ScreenLayout::for_console(ctx).screen;
+    let inner = Modal { title: None, hint: Some("(Up/Down/PgUp/PgDn scroll, 1-4 filter, / search, ESCAPE close)") }.draw(ctx, screen);
+    let mut rows = VStack::new(Region::new(inner.x + 1, inner.y, inner.w - 2, inner.h));
+    rows.gap(1);
+    let filters = rows.row();
+    rows.gap(1);
+    let text = rows.take(inner.h - 4);
+
+    let mut lines : Vec<String> = vec![];
+    for entry in log.entries.iter().filter(|e| view.matches(e)) {
+        lines.extend(wrap_markup(&entry.display(), text.w as usize));
+    }
+
+    let page = text.h;
+    let max_offset = i32::max(0, lines.len() as i32 - page);
+    let offset = i32::min(offset, max_offset);
+    ctx.print_color(screen.x + 2, screen.y, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), format!(" Message history ({} lines) ", lines.len()));
+
+    let mut x = filters.x;
+    for (i, category) in LOG_CATEGORIES.iter().enumerate() {
+        let label = format!("({}) {:?}", i + 1, category);
+        let color = if view.shown.contains(category) { RGB::named(rltk::WHITE) } else { RGB::from_u8(60,60,60) };
+        ctx.print_color(x, filters.y, color, RGB::named(rltk::BLACK), &label);
+        x += label.len() as i32 + 2;
+    }
+    let search = Region::new(x + 2, filters.y, filters.right() + 1 - (x + 2), 1);
+    if view.searching {
+        ui::label(ctx, search, RGB::named(rltk::MAGENTA), format!("Search: {}_", view.search));
+    } else if !view.search.is_empty() {
+        ui::label(ctx, search, RGB::named(rltk::YELLOW), format!("Search: {}", view.search));
+    }
+
+    let mut y = text.bottom();
+    for line in lines.iter().rev().skip(offset as usize).take(page as usize) {
+        ui::markup(ctx, Region::new(text.x, y, text.w, 1), format!("#[white]{}",line));
+        y -= 1;
+    }
+
+    if view.searching {
+        match ctx.key {
+            Some(VirtualKeyCode::Return) => view.searching = false,
+            Some(VirtualKeyCode::Escape) => {
+                view.searching = false;
+                view.search.clear();
+            }
+            Some(VirtualKeyCode::Back) => { view.search.pop(); }
+            Some(key) => {
+                if let Some(c) = key_to_char(key) { view.search.push(c); }
+            }
+            None => {}
+        }
+        return LogHistoryResult::Scrolled{ offset: 0 };
+    }
+
+    match ctx.key {
+        Some(VirtualKeyCode::Escape) => LogHistoryResult::Close,
+        Some(key) if keymap.action_for(key) == Some(InputAction::MessageHistory) => LogHistoryResult::Close,
+        Some(VirtualKeyCode::Slash) => {
+            view.searching = true;
+            LogHistoryResult::Scrolled{ offset: 0 }
+        }
+        Some(VirtualKeyCode::Key1) => { view.toggle(LOG_CATEGORIES[0]); LogHistoryResult::Scrolled{ offset: 0 } }
+        Some(VirtualKeyCode::Key2) => { view.toggle(LOG_CATEGORIES[1]); LogHistoryResult::Scrolled{ offset: 0 } }
+        Some(VirtualKeyCode::Key3) => { view.toggle(LOG_CATEGORIES[2]); LogHistoryResult::Scrolled{ offset: 0 } }
+        Some(VirtualKeyCode::Key4) => { view.toggle(LOG_CATEGORIES[3]); LogHistoryResult::Scrolled{ offset: 0 } }
+        Some(VirtualKeyCode::Up) => LogHistoryResult::Scrolled{ offset: i32::min(max_offset, offset + 1) },
+        Some(VirtualKeyCode::Down) => LogHistoryResult::Scrolled{ offset: i32::max(0, offset - 1) },
+        Some(VirtualKeyCode::PageUp) => LogHistoryResult::Scrolled{ offset: i32::min(max_offset, offset + page) },
+        Some(VirtualKeyCode::PageDown) => LogHistoryResult::Scrolled{ offset: i32::max(0, offset - page) },
+        _ => LogHistoryResult::Scrolled{ offset: offset }
+    }
+}
+
+/// The map area of the screen in the cells of the map console, which are shorter than the UI
+/// console's.  Leaves the map console active.
+fn map_console_area(ctx: &mut Rltk) -> Region {
+    ctx.set_active_console(1);
+    let screen = ScreenLayout::for_console(ctx);
+    let ui_rows = ctx.get_char_size().1 as i32;
+    ctx.set_active_console(0);
+    let map_rows = ctx.get_char_size().1 as i32;
+    screen.map.scaled(1, i32::max(1, map_rows / i32::max(1, ui_rows)))
+}
+
+/// The whole level at reduced scale, one glyph per block of tiles, showing everything the
+/// player has explored along with the stairs, remembered items and monsters, and the player.
+/// Drawn on the map console, under a chart of the dungeon's branches.
+pub fn level_overview(gs : &mut State, ctx : &mut Rltk) -> OverviewResult {
+    let map = gs.ecs.fetch::<Map>();
+    let keymap = gs.ecs.fetch::<Keymap>();
+    let player_entity = gs.ecs.fetch::<Entity>();
+    let positions = gs.ecs.read_storage::<Position>();
+    let renderables = gs.ecs.read_storage::<Renderable>();
+    let mut noise = FastNoise::new();
+    noise.set_seed(map.seed);
+
+    let mut rows = VStack::new(map_console_area(ctx).inset(1));
+    draw_dungeon_chart(&gs.ecs, ctx, map.level_id(), rows.take(ALL_BRANCHES.len() as i32));
+    rows.gap(1);
+    let below = rows.rest();
+
+    // shrink the level until it fits in the box, leaving room for the border and the hint
+    let blocks = |scale: i32| ((map.width + scale - 1) / scale, (map.height + scale - 1) / scale);
+    let mut scale = 2;
+    while (blocks(scale).0 > below.w - 2 || blocks(scale).1 > below.h - 3) && scale < i32::max(map.width, map.height) { scale += 1; }
+    let (cols, rows) = blocks(scale);
+
+    let title = map.level_id().name();
+    let hint = format!("({} or ESCAPE to close)", keymap.label(InputAction::LevelOverview));
+    let inner = Modal { title: Some(&title), hint: Some(&hint) }.draw(ctx, below.centered(cols + 2, rows + 3));
+
+    // each block shows its most interesting revealed tile, drawn as if in plain light
+    for row in 0..rows {
+        for col in 0..cols {
+            let mut shown : Option<usize> = None;
+            for y in row * scale .. i32::min(map.height, (row + 1) * scale) {
+                for x in col * scale .. i32::min(map.width, (col + 1) * scale) {
+                    let idx = map.xy_idx(x, y);
+                    if !map.revealed_tiles[idx] { continue; }
+                    if shown.map_or(true, |s| overview_priority(map.tiles[idx]) > overview_priority(map.tiles[s])) {
+                        shown = Some(idx);
+                    }
+                }
+            }
+            if let Some(idx) = shown {
+                let (glyph, fg, bg) = tile_glyph(&map, idx, &noise, true, RGB::from_f32(0.8, 0.8, 0.8));
+                ctx.set(inner.x + col, inner.y + row, fg, bg, glyph);
+            }
+        }
+    }
+
+    for (idx, memory) in map.remembered.iter().enumerate() {
+        if let Some(memory) = memory {
+            let (x, y) = (idx as i32 % map.width, idx as i32 / map.width);
+            ctx.set(inner.x + x / scale, inner.y + y / scale, memory.fg, RGB::named(rltk::BLACK), memory.glyph);
+        }
+    }
+    if let (Some(pos), Some(render)) = (positions.get(*player_entity), renderables.get(*player_entity)) {
+        ctx.set(inner.x + pos.x / scale, inner.y + pos.y / scale, render.fg, RGB::named(rltk::BLACK), render.glyph);
+    }
+
+    match ctx.key {
+        Some(VirtualKeyCode::Escape) => OverviewResult::Close,
+        Some(key) if keymap.action_for(key) == Some(InputAction::LevelOverview) => OverviewResult::Close,
+        _ => OverviewResult::Open
+    }
+}
+
+/// The branches of the dungeon, one row each: levels visited this run in white, the current
+/// one in yellow, and branches not yet entered greyed out
+fn draw_dungeon_chart(ecs: &World, ctx: &mut Rltk, current: LevelId, at: Region) {
+    let stats = ecs.fetch::<RunStats>();
+    let mut rows = VStack::new(at);
+    for branch in ALL_BRANCHES.iter() {
+        let (name, levels) = rows.row().split_columns(18);
+        let entered = branch.depths().any(|depth| stats.visited.contains(&LevelId { branch: *branch, depth: depth }));
+        let name_colour = if entered { RGB::named(rltk::WHITE) } else { RGB::named(rltk::GREY) };
+        ui::label(ctx, name, name_colour, branch.name());
+
+        let mut chart = String::new();
+        for depth in branch.depths() {
+            let level = LevelId { branch: *branch, depth: depth };
+            let colour = if level == current { "yellow" } else if stats.visited.contains(&level) { "white" } else { "grey" };
+            let label = if stats.visited.contains(&level) || level == current { format!("{}", depth) } else { String::from("?") };
+            chart.push_str(&format!("#[{}]{} ", colour, label));
+        }
+        if let Some(entrance) = branch.entrance_depth() {
+            chart.push_str(&format!(" #[grey](off depth {})", entrance));
+        }
+        ui::markup(ctx, levels, chart);
+    }
+}
+
+/// Which tile stands for a block in the overview: landmarks first, then open ground, then walls
+fn overview_priority(tile: TileType) -> i32 {
+    match tile {
+        TileType::StairsUp | TileType::StairsDown => 5,
+        TileType::DoorClosed | TileType::DoorOpen => 4,
+        TileType::Trap { hidden: false, .. } => 3,
+        TileType::Sarcophagus | TileType::SarcophagusOpen | TileType::Rubble => 2,
+        TileType::Wall => 0,
+        _ => 1
+    }
+}
+
+fn key_to_char(key: VirtualKeyCode) -> Option<char> {
+    let c = match key {
+        VirtualKeyCode::A => 'a', VirtualKeyCode::B => 'b', VirtualKeyCode::C => 'c', VirtualKeyCode::D => 'd',
+        VirtualKeyCode::E => 'e', VirtualKeyCode::F => 'f', VirtualKeyCode::G => 'g', VirtualKeyCode::H => 'h',
+        VirtualKeyCode::I => 'i', VirtualKeyCode::J => 'j', VirtualKeyCode::K => 'k', VirtualKeyCode::L => 'l',
+        VirtualKeyCode::M => 'm', VirtualKeyCode::N => 'n', VirtualKeyCode::O => 'o', VirtualKeyCode::P => 'p',
+        VirtualKeyCode::Q => 'q', VirtualKeyCode::R => 'r', VirtualKeyCode::S => 's', VirtualKeyCode::T => 't',
+        VirtualKeyCode::U => 'u', VirtualKeyCode::V => 'v', VirtualKeyCode::W => 'w', VirtualKeyCode::X => 'x',
+        VirtualKeyCode::Y => 'y', VirtualKeyCode::Z => 'z',
+        VirtualKeyCode::Key0 => '0', VirtualKeyCode::Key1 => '1', VirtualKeyCode::Key2 => '2', VirtualKeyCode::Key3 => '3',
+        VirtualKeyCode::Key4 => '4', VirtualKeyCode::Key5 => '5', VirtualKeyCode::Key6 => '6', VirtualKeyCode::Key7 => '7',
+        VirtualKeyCode::Key8 => '8', VirtualKeyCode::Key9 => '9',
+        VirtualKeyCode::Space => ' ', VirtualKeyCode::Minus => '-', VirtualKeyCode::Apostrophe => '\'',
+        _ => return None
+    };
+    Some(c)
+}
+
+/// Rebinding screen: Left/Right switch presets under the player's changes, Enter captures a new primary key for the selected action
+pub fn key_bindings(gs : &mut State, ctx : &mut Rltk) -> KeyBindingsResult {
+    let runstate = *gs.ecs.fetch::<RunState>();
+    let mut keymap = gs.ecs.write_resource::<Keymap>();
+
+    let (selection, capturing) = match runstate {
+        RunState::KeyBindings{ selection, capturing } => (selection, capturing),
+        _ => (0, false)
+    };
+
+    ctx.set_active_console(1);
+    let screen = ScreenLayout::for_console(ctx).screen;
+    let mut rows = VStack::new(screen);
+    rows.gap(2);
+    ui::centered(ctx, rows.row(), RGB::named(rltk::YELLOW), "Key Bindings");
+    rows.gap(1);
+    let preset = match keymap.overrides.len() {
+        0 => format!("Preset: < {} >", keymap.preset.name()),
+        1 => format!("Preset: < {} > with 1 change", keymap.preset.name()),
+        n => format!("Preset: < {} > with {} changes", keymap.preset.name(), n)
+    };
+    ui::centered(ctx, rows.row(), RGB::named(rltk::WHITE), preset);
+    rows.gap(1);
+
+    let column_length = 16;
+    let entries : Vec<(String, RGB)> = ALL_ACTIONS.iter().enumerate().map(|(i, action)| {
+        let keys : Vec<String> = keymap.keys_for(*action).iter().map(|k| super::keymap::key_label(*k)).collect();
+        let keys_text = if capturing && i as i32 == selection { String::from("press a key...") } else { keys.join(", ") };
+        (format!("{:16} {}", action.description(), keys_text), RGB::named(rltk::WHITE))
+    }).collect();
+    let (left, right) = rows.take(column_length as i32).split_columns(screen.w / 2);
+    let (left_entries, right_entries) = entries.split_at(usize::min(column_length, entries.len()));
+    let selected = selection as usize;
+    List::new(left_entries, if selected < column_length { Some(selected) } else { None })
+        .draw(ctx, Region::new(left.x + 2, left.y, left.w - 2, left.h));
+    List::new(right_entries, if selected >= column_length { Some(selected - column_length) } else { None })
+        .draw(ctx, Region::new(right.x + 2, right.y, right.w - 2, right.h));
+    ui::centered(ctx, rows.row(), RGB::named(rltk::GREY), "(Enter to rebind, Backspace to clear, Left/Right to change preset)");
+    ui::centered(ctx, rows.row(), RGB::named(rltk::GREY), "(ESCAPE to save and return)");
+
+    let count = ALL_ACTIONS.len() as i32;
+    let action = ALL_ACTIONS[selection as usize];
+    if capturing {
+        return match ctx.key {
+            Some(VirtualKeyCode::Escape) => KeyBindingsResult::Selected{ selection, capturing: false },
+            Some(key) if is_bindable(key) => {
+                keymap.rebind(action, key);
+                KeyBindingsResult::Selected{ selection, capturing: false }
+            }
+            _ => KeyBindingsResult::Selected{ selection, capturing: true }
+        }
+    }
+
+    match ctx.key {
+        Some(VirtualKeyCode::Escape) => {
+            keymap.save();
+            KeyBindingsResult::Back
+        }
+        Some(VirtualKeyCode::Up) => KeyBindingsResult::Selected{ selection: (selection + count - 1) % count, capturing: false },
+        Some(VirtualKeyCode::Down) => KeyBindingsResult::Selected{ selection: (selection + 1) % count, capturing: false },
+        Some(VirtualKeyCode::Left) => {
+            let preset = keymap.preset.prev();
+            keymap.set_preset(preset);
+            KeyBindingsResult::Selected{ selection, capturing: false }
+        }
+        Some(VirtualKeyCode::Right) => {
+            let preset = keymap.preset.next();
+            keymap.set_preset(preset);
+            KeyBindingsResult::Selected{ selection, capturing: false }
+        }
+        Some(VirtualKeyCode::Back) | Some(VirtualKeyCode::Delete) => {
+            keymap.unbind(action);
+            KeyBindingsResult::Selected{ selection, capturing: false }
+        }
+        Some(VirtualKeyCode::Return) => KeyBindingsResult::Selected{ selection, capturing: true },
+        _ => KeyBindingsResult::Selected{ selection, capturing: false }
+    }
+}
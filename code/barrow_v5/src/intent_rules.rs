@@ -0,0 +1,200 @@
+use specs::prelude::*;
+use rltk::Point;
+use barrow_core::intent::{Intent, IntentAction, IntentEvent, IntentModel, IntentRules, IntentStats, STRONG_ATTACK_COST};
+use super::{CombatStats, MenuCommand, Action, Name, Monster, Viewshed, Position, Map, Morale, morale, RunState, EventQueue, GameEvent, ruleset::Ruleset, action_system::ActionSystem};
+use super::Command::*;
+use super::AttackMove::*;
+use super::WaitMove::*;
+use super::CombatStance::*;
+
+/// The untitled prototypes' intent model played with barrow's fighters: a Smash is the strong
+/// attack, Block raises or lowers the guard and a stun stops any attack
+pub struct Intents;
+
+impl Ruleset for IntentRules<Intents> {
+    fn available_moves(&self, stats: &CombatStats) -> Vec<MenuCommand> {
+        let guard_after = if stats.stance == Guard { Ready } else { Guard };
+        let can_act = stats.stance != Stun;
+        vec![
+            MenuCommand { command: WaitCommand(Wait), cost: stats.ep_regen, stance_after: stats.stance, enabled: true },
+            MenuCommand { command: AttackCommand(Melee), cost: stats.attack_cost, stance_after: stats.stance, enabled: can_act },
+            MenuCommand { command: AttackCommand(Smash), cost: stats.attack_cost + STRONG_ATTACK_COST, stance_after: stats.stance, enabled: can_act },
+            MenuCommand { command: WaitCommand(Block), cost: 0, stance_after: guard_after, enabled: can_act }
+        ]
+    }
+}
+
+impl IntentModel for Intents {
+    type Stats = CombatStats;
+    type Action = Action;
+
+    fn run_ai(&self, ecs: &World) {
+        let mut mob = IntentAI{};
+        mob.run_now(ecs);
+    }
+
+    fn report(&self, ecs: &World, intent_events: &[IntentEvent]) {
+        let mut events = ecs.write_resource::<EventQueue>();
+        let names = ecs.read_storage::<Name>();
+        let positions = ecs.read_storage::<Position>();
+        let actions = ecs.read_storage::<Action>();
+        let mut combat_stats = ecs.write_storage::<CombatStats>();
+        let name = |entity: Entity| names.get(entity).map_or(String::new(), |n| n.name.clone());
+
+        for event in intent_events {
+            match *event {
+                IntentEvent::Hit { attacker, target, damage, .. } => {
+                    let attack = match actions.get(attacker) {
+                        Some(Action { command: AttackCommand(a), .. }) => *a,
+                        _ => continue
+                    };
+                    events.emit(GameEvent::Attacked {
+                        attacker: attacker,
+                        attacker_name: name(attacker),
+                        target: target,
+                        target_name: name(target),
+                        target_pos: *positions.get(target).unwrap(),
+                        attack: attack,
+                        hp_damage: damage,
+                        ep_damage: 0
+                    });
+                    if let Some(stats) = combat_stats.get_mut(attacker) {
+                        stats.last_command = Some(AttackCommand(attack));
+                    }
+                }
+                IntentEvent::Exhausted { entity } => events.emit(GameEvent::Exhausted { entity: entity, name: name(entity) }),
+                IntentEvent::Rested { entity } => {
+                    if let Some(stats) = combat_stats.get(entity) {
+                        if stats.ep != stats.max_ep {
+                            events.emit(GameEvent::Recovered { entity: entity, name: name(entity), ep: stats.ep_regen.abs() });
+                        }
+                    }
+                }
+                IntentEvent::GuardChanged { entity, up } => {
+                    let (from, to) = if up { (Ready, Guard) } else { (Guard, Ready) };
+                    events.emit(GameEvent::StanceChanged { entity: entity, name: name(entity), from: from, to: to });
+                }
+                IntentEvent::Attempted { .. } => {}
+            }
+        }
+    }
+
+    fn settle(&self, ecs: &World, resolved: &[Entity]) {
+        {
+            let mut actions = ecs.write_storage::<Action>();
+            let mut combat_stats = ecs.write_storage::<CombatStats>();
+            for entity in resolved {
+                if let Some(Action { command: WaitCommand(w), .. }) = actions.remove(*entity) {
+                    if let Some(stats) = combat_stats.get_mut(*entity) {
+                        stats.last_command = Some(WaitCommand(w));
+                    }
+                }
+            }
+        }
+        // whatever is left is movement, which both models share
+        let mut actions = ActionSystem{};
+        actions.run_now(ecs);
+    }
+}
+
+impl IntentStats for CombatStats {
+    fn hp(&self) -> i32 { self.hp }
+    fn ep(&self) -> i32 { self.ep }
+    fn power(&self) -> i32 { self.power }
+    fn defense(&self) -> i32 { self.defense }
+    fn attack_cost(&self) -> i32 { self.attack_cost }
+    fn hp_regen(&self) -> i32 { self.hp_regen }
+    fn ep_regen(&self) -> i32 { self.ep_regen }
+    fn in_combat(&self) -> bool { self.current_target.is_some() }
+    fn guarding(&self) -> bool { self.stance == Guard }
+    fn stunned(&self) -> bool { self.stance == Stun }
+    fn set_guard(&mut self, up: bool) { self.stance = if up { Guard } else { Ready }; }
+}
+
+impl IntentAction for Action {
+    fn intent(&self) -> Option<Intent> {
+        match *self {
+            Action { command: AttackCommand(a), target: Some(target), .. } => Some(Intent::Attack { target: target, strong: a != Melee }),
+            Action { command: WaitCommand(Block), stance_after, .. } => Some(Intent::SetGuard(stance_after == Guard)),
+            Action { command: WaitCommand(_), .. } => Some(Intent::Rest),
+            // moves are left to the ActionSystem
+            _ => None
+        }
+    }
+}
+
+/// Monsters attack when adjacent, close in when they can see the player and otherwise rest
+pub struct IntentAI {}
+
+impl<'a> System<'a> for IntentAI {
+    #[allow(clippy::type_complexity)]
+    type SystemData = ( WriteExpect<'a, Map>,
+                        ReadExpect<'a, Entity>,
+                        ReadExpect<'a, RunState>,
+                        Entities<'a>,
+                        ReadStorage<'a, Viewshed>,
+                        ReadStorage<'a, Monster>,
+                        ReadStorage<'a, Position>,
+                        WriteStorage<'a, Action>,
+                        WriteStorage<'a, CombatStats>,
+                        ReadStorage<'a, Morale>
+                    );
+
+    fn run(&mut self, data : Self::SystemData) {
+        let (mut map, player_entity, runstate, entities, viewsheds, monster, position, mut actions, mut combat_stats, morales) = data;
+
+        if *runstate != RunState::MonsterTurn { return; }
+
+        let player_ent_pos = position.get(*player_entity).unwrap();
+        let player_pos = Point::new(player_ent_pos.x, player_ent_pos.y);
+        let mut flee_map = None;
+
+        for (entity, viewshed, _monster, pos, stats) in (&entities, &viewsheds, &monster, &position, &mut combat_stats).join() {
+            stats.visible_targets.clear();
+            let can_see = viewshed.visible_tiles.contains(&player_pos);
+            if can_see {
+                stats.current_target = Some(*player_entity);
+                stats.visible_targets.push(*player_entity);
+            } else {
+                stats.current_target = None;
+            }
+
+            let wait = Action { command: WaitCommand(Wait), cost: stats.ep_regen, stance_after: stats.stance, target: None, position: None };
+            let distance = rltk::DistanceAlg::Pythagoras.distance2d(Point::new(pos.x, pos.y), player_pos);
+            let fleeing = morales.get(entity).map_or(false, |m| m.fleeing);
+            let action = if fleeing {
+                let flee = flee_map.get_or_insert_with(|| morale::flee_map(&*map, player_pos));
+                match morale::flee_step(flee, &*map, pos) {
+                    Some(step) => {
+                        let idx = map.xy_idx(pos.x, pos.y);
+                        map.blocked[idx] = false;
+                        Action { command: MoveCommand, cost: 0, stance_after: stats.stance, target: None, position: Some(step) }
+                    }
+                    None if distance < 1.5 => Action { command: AttackCommand(Melee), cost: stats.attack_cost, stance_after: stats.stance, target: Some(*player_entity), position: None },
+                    None => wait
+                }
+            } else if distance < 1.5 {
+                Action { command: AttackCommand(Melee), cost: stats.attack_cost, stance_after: stats.stance, target: Some(*player_entity), position: None }
+            } else if can_see {
+                let path = rltk::a_star_search(
+                    map.xy_idx(pos.x, pos.y),
+                    map.xy_idx(player_pos.x, player_pos.y),
+                    &map.grid
+                );
+                if path.success && path.steps.len()>1 {
+                    let idx = map.xy_idx(pos.x, pos.y);
+                    map.blocked[idx] = false;
+                    let new_x = path.steps[1] as i32 % map.width;
+                    let new_y = path.steps[1] as i32 / map.width;
+                    Action { command: MoveCommand, cost: 0, stance_after: stats.stance, target: None, position: Some(Position { x: new_x, y: new_y }) }
+                } else {
+                    wait
+                }
+            } else {
+                wait
+            };
+            actions.insert(entity, action).expect("Unable to insert action");
+        }
+    }
+}
+
@@ -0,0 +1,427 @@
+use rltk::VirtualKeyCode;
+use rltk::VirtualKeyCode::*;
+
+/// Key bindings are saved next to the executable; the web build always starts from the default preset
+const KEYMAP_FILE : &str = "barrow_keys.cfg";
+
+/// Everything the player can do from the map screen
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+pub enum InputAction {
+    MoveWest, MoveEast, MoveNorth, MoveSouth,
+    MoveNorthWest, MoveNorthEast, MoveSouthWest, MoveSouthEast,
+    Target(usize),
+    AttackMenu(usize),
+    Ascend, Descend, QuickAscend,
+    MessageHistory,
+    AutoExplore, TravelUpStairs, TravelDownStairs,
+    CloseDoor,
+    LevelOverview
+}
+
+pub const ALL_ACTIONS : [InputAction; 32] = [
+    InputAction::MoveWest, InputAction::MoveEast, InputAction::MoveNorth, InputAction::MoveSouth,
+    InputAction::MoveNorthWest, InputAction::MoveNorthEast, InputAction::MoveSouthWest, InputAction::MoveSouthEast,
+    InputAction::AttackMenu(0), InputAction::AttackMenu(1), InputAction::AttackMenu(2),
+    InputAction::AttackMenu(3), InputAction::AttackMenu(4), InputAction::AttackMenu(5),
+    InputAction::Descend, InputAction::Ascend, InputAction::QuickAscend, InputAction::MessageHistory,
+    InputAction::AutoExplore, InputAction::TravelUpStairs, InputAction::TravelDownStairs, InputAction::CloseDoor, InputAction::LevelOverview,
+    InputAction::Target(1), InputAction::Target(2), InputAction::Target(3),
+    InputAction::Target(4), InputAction::Target(5), InputAction::Target(6),
+    InputAction::Target(7), InputAction::Target(8), InputAction::Target(9)
+];
+
+/// Keys that may be bound; anything else is ignored by the rebinding screen
+const BINDABLE_KEYS : [VirtualKeyCode; 69] = [
+    Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9, Key0,
+    A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z,
+    Left, Up, Right, Down, Space, Home, End, PageUp, PageDown, Insert, Delete,
+    Numpad0, Numpad1, Numpad2, Numpad3, Numpad4, Numpad5, Numpad6, Numpad7, Numpad8, Numpad9,
+    NumpadAdd, NumpadSubtract, NumpadMultiply, NumpadDivide, NumpadDecimal, NumpadEnter,
+    Comma, Period, Slash, Semicolon, Apostrophe, Tab
+];
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum KeymapPreset { Wasd, ViKeys, NumpadOnly }
+
+impl KeymapPreset {
+    pub fn name(&self) -> &'static str {
+        match self {
+            KeymapPreset::Wasd => "WASD",
+            KeymapPreset::ViKeys => "vi-keys",
+            KeymapPreset::NumpadOnly => "numpad-only"
+        }
+    }
+
+    pub fn next(&self) -> KeymapPreset {
+        match self {
+            KeymapPreset::Wasd => KeymapPreset::ViKeys,
+            KeymapPreset::ViKeys => KeymapPreset::NumpadOnly,
+            KeymapPreset::NumpadOnly => KeymapPreset::Wasd
+        }
+    }
+
+    pub fn prev(&self) -> KeymapPreset {
+        match self {
+            KeymapPreset::Wasd => KeymapPreset::NumpadOnly,
+            KeymapPreset::ViKeys => KeymapPreset::Wasd,
+            KeymapPreset::NumpadOnly => KeymapPreset::ViKeys
+        }
+    }
+
+    fn parse(name: &str) -> Option<KeymapPreset> {
+        [KeymapPreset::Wasd, KeymapPreset::ViKeys, KeymapPreset::NumpadOnly].iter().find(|p| p.name() == name).copied()
+    }
+}
+
+impl InputAction {
+    pub fn description(&self) -> String {
+        match self {
+            InputAction::MoveWest => String::from("Move west"),
+            InputAction::MoveEast => String::from("Move east"),
+            InputAction::MoveNorth => String::from("Move north"),
+            InputAction::MoveSouth => String::from("Move south"),
+            InputAction::MoveNorthWest => String::from("Move northwest"),
+            InputAction::MoveNorthEast => String::from("Move northeast"),
+            InputAction::MoveSouthWest => String::from("Move southwest"),
+            InputAction::MoveSouthEast => String::from("Move southeast"),
+            InputAction::Target(n) => format!("Select target {}", n),
+            InputAction::AttackMenu(n) => format!("Command {}", n + 1),
+            InputAction::Ascend => String::from("Ascend"),
+            InputAction::Descend => String::from("Descend"),
+            InputAction::QuickAscend => String::from("Return to town"),
+            InputAction::MessageHistory => String::from("Message history"),
+            InputAction::AutoExplore => String::from("Auto-explore"),
+            InputAction::TravelUpStairs => String::from("Travel to <"),
+            InputAction::TravelDownStairs => String::from("Travel to >"),
+            InputAction::CloseDoor => String::from("Close door"),
+            InputAction::LevelOverview => String::from("Level overview")
+        }
+    }
+
+    fn config_name(&self) -> String {
+        format!("{:?}", self)
+    }
+}
+
+/// One change the player made on top of a preset
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum KeyOverride {
+    /// Makes the key the action's primary key, taking it from whatever had it
+    Bind(InputAction, VirtualKeyCode),
+    /// Leaves the action with no keys
+    Clear(InputAction)
+}
+
+/// Maps keys to named actions.  The first key bound to an action is its primary key,
+/// which is the one shown in the command panel.  The bindings are always the preset with
+/// the player's overrides played over it in order, so switching presets keeps them.
+pub struct Keymap {
+    pub preset: KeymapPreset,
+    pub overrides: Vec<KeyOverride>,
+    pub bindings: Vec<(InputAction, Vec<VirtualKeyCode>)>
+}
+
+impl Keymap {
+    pub fn preset(preset: KeymapPreset) -> Keymap {
+        let movement : Vec<(InputAction, Vec<VirtualKeyCode>)> = match preset {
+            KeymapPreset::Wasd => vec![
+                (InputAction::MoveWest, vec![A, Left, Numpad4]),
+                (InputAction::MoveEast, vec![D, Right, Numpad6]),
+                (InputAction::MoveNorth, vec![W, Up, Numpad8]),
+                (InputAction::MoveSouth, vec![S, Down, Numpad2]),
+                (InputAction::MoveNorthWest, vec![Q, Numpad7]),
+                (InputAction::MoveNorthEast, vec![E, Numpad9]),
+                (InputAction::MoveSouthWest, vec![Z, Numpad1]),
+                (InputAction::MoveSouthEast, vec![C, Numpad3]),
+                (InputAction::AttackMenu(0), vec![X, Space, Numpad5]),
+                (InputAction::AttackMenu(1), vec![J]),
+                (InputAction::AttackMenu(2), vec![K]),
+                (InputAction::AttackMenu(3), vec![L]),
+                (InputAction::AttackMenu(4), vec![N]),
+                (InputAction::AttackMenu(5), vec![M])
+            ],
+            KeymapPreset::ViKeys => vec![
+                (InputAction::MoveWest, vec![H, Left]),
+                (InputAction::MoveEast, vec![L, Right]),
+                (InputAction::MoveNorth, vec![K, Up]),
+                (InputAction::MoveSouth, vec![J, Down]),
+                (InputAction::MoveNorthWest, vec![Y]),
+                (InputAction::MoveNorthEast, vec![U]),
+                (InputAction::MoveSouthWest, vec![B]),
+                (InputAction::MoveSouthEast, vec![N]),
+                (InputAction::AttackMenu(0), vec![Space, X]),
+                (InputAction::AttackMenu(1), vec![A]),
+                (InputAction::AttackMenu(2), vec![S]),
+                (InputAction::AttackMenu(3), vec![D]),
+                (InputAction::AttackMenu(4), vec![F]),
+                (InputAction::AttackMenu(5), vec![G])
+            ],
+            KeymapPreset::NumpadOnly => vec![
+                (InputAction::MoveWest, vec![Numpad4]),
+                (InputAction::MoveEast, vec![Numpad6]),
+                (InputAction::MoveNorth, vec![Numpad8]),
+                (InputAction::MoveSouth, vec![Numpad2]),
+                (InputAction::MoveNorthWest, vec![Numpad7]),
+                (InputAction::MoveNorthEast, vec![Numpad9]),
+                (InputAction::MoveSouthWest, vec![Numpad1]),
+                (InputAction::MoveSouthEast, vec![Numpad3]),
+                (InputAction::AttackMenu(0), vec![Numpad5]),
+                (InputAction::AttackMenu(1), vec![Numpad0]),
+                (InputAction::AttackMenu(2), vec![NumpadEnter]),
+                (InputAction::AttackMenu(3), vec![NumpadAdd]),
+                (InputAction::AttackMenu(4), vec![NumpadSubtract]),
+                (InputAction::AttackMenu(5), vec![NumpadMultiply])
+            ]
+        };
+
+        let mut bindings = movement;
+        bindings.push((InputAction::Descend, vec![Period]));
+        bindings.push((InputAction::Ascend, vec![Comma]));
+        bindings.push((InputAction::QuickAscend, vec![T]));
+        bindings.push((InputAction::MessageHistory, vec![V]));
+        bindings.push((InputAction::AutoExplore, vec![O]));
+        bindings.push((InputAction::TravelUpStairs, vec![I]));
+        bindings.push((InputAction::TravelDownStairs, vec![P]));
+        bindings.push((InputAction::CloseDoor, vec![R]));
+        bindings.push((InputAction::LevelOverview, vec![Tab]));
+        let number_keys = [Key1, Key2, Key3, Key4, Key5, Key6, Key7, Key8, Key9];
+        for (i, key) in number_keys.iter().enumerate() {
+            bindings.push((InputAction::Target(i + 1), vec![*key]));
+        }
+
+        Keymap { preset: preset, overrides: Vec::new(), bindings: bindings }
+    }
+
+    /// Switches to another preset, keeping the player's overrides on top of it
+    pub fn set_preset(&mut self, preset: KeymapPreset) {
+        let overrides = std::mem::take(&mut self.overrides);
+        *self = Keymap::preset(preset);
+        for o in overrides {
+            self.apply(o);
+        }
+    }
+
+    fn apply(&mut self, o: KeyOverride) {
+        match o {
+            KeyOverride::Bind(action, key) => {
+                for (_, keys) in self.bindings.iter_mut() {
+                    keys.retain(|k| *k != key);
+                }
+                match self.bindings.iter_mut().find(|(a, _)| *a == action) {
+                    Some((_, keys)) => keys.insert(0, key),
+                    None => self.bindings.push((action, vec![key]))
+                }
+            }
+            KeyOverride::Clear(action) => {
+                for (a, keys) in self.bindings.iter_mut() {
+                    if *a == action { keys.clear(); }
+                }
+            }
+        }
+        self.overrides.push(o);
+    }
+
+    pub fn action_for(&self, key: VirtualKeyCode) -> Option<InputAction> {
+        self.bindings.iter().find(|(_, keys)| keys.contains(&key)).map(|(action, _)| *action)
+    }
+
+    pub fn keys_for(&self, action: InputAction) -> Vec<VirtualKeyCode> {
+        match self.bindings.iter().find(|(a, _)| *a == action) {
+            Some((_, keys)) => keys.clone(),
+            None => vec![]
+        }
+    }
+
+    /// Short label for the primary key of an action, or "-" if unbound
+    pub fn primary_label(&self, action: InputAction) -> String {
+        match self.keys_for(action).first() {
+            Some(key) => key_label(*key),
+            None => String::from("-")
+        }
+    }
+
+    /// Label with up to two keys, e.g. "X/Sp"
+    pub fn label(&self, action: InputAction) -> String {
+        let keys = self.keys_for(action);
+        if keys.is_empty() { return String::from("-"); }
+        keys.iter().take(2).map(|k| key_label(*k)).collect::<Vec<String>>().join("/")
+    }
+
+    /// Makes `key` the primary key for `action`, taking it away from any other action
+    pub fn rebind(&mut self, action: InputAction, key: VirtualKeyCode) {
+        self.overrides.retain(|o| *o != KeyOverride::Bind(action, key));
+        self.apply(KeyOverride::Bind(action, key));
+    }
+
+    /// Leaves `action` with no keys.  Earlier overrides for it are dropped, which gives back
+    /// any keys they took from other actions.
+    pub fn unbind(&mut self, action: InputAction) {
+        let mut overrides = std::mem::take(&mut self.overrides);
+        overrides.retain(|o| match o {
+            KeyOverride::Bind(a, _) | KeyOverride::Clear(a) => *a != action
+        });
+        overrides.push(KeyOverride::Clear(action));
+        self.overrides = overrides;
+        self.set_preset(self.preset);
+    }
+
+    fn to_config(&self) -> String {
+        let mut config = format!("preset = {}\n", self.preset.name());
+        for o in self.overrides.iter() {
+            match o {
+                KeyOverride::Bind(action, key) => config.push_str(&format!("{} += {:?}\n", action.config_name(), key)),
+                KeyOverride::Clear(action) => config.push_str(&format!("{} =\n", action.config_name()))
+            }
+        }
+        config
+    }
+
+    /// Reads a config file: a preset line, then the overrides in order.  `Action += Key` binds
+    /// one more key, and `Action = Key Key` replaces all of the action's keys.
+    fn from_config(config: &str) -> Keymap {
+        let mut keymap = Keymap::preset(KeymapPreset::Wasd);
+        for line in config.lines() {
+            let parts : Vec<&str> = line.splitn(2, '=').map(|p| p.trim()).collect();
+            if parts.len() != 2 || parts[0].starts_with('#') { continue; }
+            if parts[0] == "preset" {
+                if let Some(preset) = KeymapPreset::parse(parts[1]) {
+                    keymap.set_preset(preset);
+                }
+                continue;
+            }
+            let (name, adding) = match parts[0].strip_suffix('+') {
+                Some(name) => (name.trim(), true),
+                None => (parts[0], false)
+            };
+            if let Some(action) = ALL_ACTIONS.iter().find(|a| a.config_name() == name) {
+                let keys : Vec<VirtualKeyCode> = parts[1].split_whitespace().filter_map(parse_key).collect();
+                if !adding {
+                    keymap.unbind(*action);
+                }
+                for key in keys.iter().rev() {
+                    keymap.rebind(*action, *key);
+                }
+            }
+        }
+        keymap
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load() -> Keymap {
+        match std::fs::read_to_string(KEYMAP_FILE) {
+            Ok(config) => Keymap::from_config(&config),
+            Err(_) => Keymap::preset(KeymapPreset::Wasd)
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn load() -> Keymap {
+        match super::web_storage::read(KEYMAP_FILE) {
+            Some(config) => Keymap::from_config(&config),
+            None => Keymap::preset(KeymapPreset::Wasd)
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self) {
+        if std::fs::write(KEYMAP_FILE, self.to_config()).is_err() {
+            rltk::console::log("unable to write key bindings");
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn save(&self) {
+        if !super::web_storage::write(KEYMAP_FILE, &self.to_config()) {
+            rltk::console::log("unable to save key bindings to local storage");
+        }
+    }
+}
+
+pub fn is_bindable(key: VirtualKeyCode) -> bool {
+    BINDABLE_KEYS.contains(&key)
+}
+
+fn parse_key(name: &str) -> Option<VirtualKeyCode> {
+    BINDABLE_KEYS.iter().find(|k| format!("{:?}", k) == name).copied()
+}
+
+pub fn key_label(key: VirtualKeyCode) -> String {
+    match key {
+        Space => String::from("Sp"),
+        Comma => String::from(","),
+        Period => String::from("."),
+        Slash => String::from("/"),
+        Semicolon => String::from(";"),
+        Apostrophe => String::from("'"),
+        Left => String::from("Lt"),
+        Right => String::from("Rt"),
+        Up => String::from("Up"),
+        Down => String::from("Dn"),
+        Key0 | Key1 | Key2 | Key3 | Key4 | Key5 | Key6 | Key7 | Key8 | Key9 => format!("{:?}", key).replace("Key", ""),
+        Numpad0 | Numpad1 | Numpad2 | Numpad3 | Numpad4 | Numpad5 | Numpad6 | Numpad7 | Numpad8 | Numpad9 => format!("{:?}", key).replace("Numpad", "N"),
+        NumpadAdd => String::from("N+"),
+        NumpadSubtract => String::from("N-"),
+        NumpadMultiply => String::from("N*"),
+        NumpadDivide => String::from("N/"),
+        NumpadDecimal => String::from("N."),
+        NumpadEnter => String::from("NEnt"),
+        _ => format!("{:?}", key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn switching_presets_keeps_the_players_changes() {
+        let mut keymap = Keymap::preset(KeymapPreset::Wasd);
+        keymap.rebind(InputAction::AutoExplore, G);
+        keymap.unbind(InputAction::CloseDoor);
+
+        keymap.set_preset(KeymapPreset::ViKeys);
+        assert_eq!(keymap.preset, KeymapPreset::ViKeys);
+        assert_eq!(keymap.action_for(G), Some(InputAction::AutoExplore));
+        assert_eq!(keymap.keys_for(InputAction::CloseDoor), vec![]);
+        assert_eq!(keymap.action_for(H), Some(InputAction::MoveWest));
+    }
+
+    #[test]
+    fn a_preset_key_taken_by_a_change_stays_taken() {
+        let mut keymap = Keymap::preset(KeymapPreset::Wasd);
+        keymap.rebind(InputAction::AutoExplore, H);
+        keymap.set_preset(KeymapPreset::ViKeys);
+        assert_eq!(keymap.action_for(H), Some(InputAction::AutoExplore));
+        assert_eq!(keymap.keys_for(InputAction::MoveWest), vec![Left]);
+    }
+
+    #[test]
+    fn clearing_an_action_gives_back_the_keys_it_took() {
+        let mut keymap = Keymap::preset(KeymapPreset::Wasd);
+        keymap.rebind(InputAction::AutoExplore, W);
+        assert_eq!(keymap.keys_for(InputAction::MoveNorth), vec![Up, Numpad8]);
+        keymap.unbind(InputAction::AutoExplore);
+        assert_eq!(keymap.keys_for(InputAction::MoveNorth), vec![W, Up, Numpad8]);
+        assert_eq!(keymap.overrides, vec![KeyOverride::Clear(InputAction::AutoExplore)]);
+    }
+
+    #[test]
+    fn config_round_trips_the_preset_and_the_changes() {
+        let mut keymap = Keymap::preset(KeymapPreset::NumpadOnly);
+        keymap.rebind(InputAction::MessageHistory, H);
+        keymap.rebind(InputAction::MessageHistory, G);
+        keymap.unbind(InputAction::Ascend);
+
+        let loaded = Keymap::from_config(&keymap.to_config());
+        assert_eq!(loaded.preset, KeymapPreset::NumpadOnly);
+        assert_eq!(loaded.overrides, keymap.overrides);
+        assert_eq!(loaded.bindings, keymap.bindings);
+    }
+
+    #[test]
+    fn full_key_lists_from_older_configs_still_load() {
+        let keymap = Keymap::from_config("preset = vi-keys\nAutoExplore = G H\n");
+        assert_eq!(keymap.keys_for(InputAction::AutoExplore), vec![G, H]);
+        assert_eq!(keymap.action_for(H), Some(InputAction::AutoExplore));
+    }
+}
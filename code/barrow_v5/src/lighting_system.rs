@@ -0,0 +1,96 @@
+use specs::prelude::*;
+use super::{LightSource, Map, Player, Position, Viewshed, RunSettings, EscapeState};
+use rltk::{field_of_view, Point, RGB};
+
+/// Tiles dimmer than this are too dark to make anything out
+pub const LIGHT_THRESHOLD : f32 = 0.15;
+
+/// Turns of torchlight in a fresh torch, the most the player can carry, and when it starts to sputter
+pub const TORCH_FUEL_START : i32 = 600;
+pub const TORCH_FUEL_MAX : i32 = 1000;
+pub const TORCH_FUEL_LOW : i32 = 100;
+
+/// Radius of a torch that has burnt down to embers
+const EMBER_RADIUS : i32 = 2;
+
+/// Ambient light that seeps into the barrow; it dies away entirely from depth 4 down
+pub fn ambient_light(depth: i32, permadark: bool) -> f32 {
+    if permadark { return 0.0; }
+    match depth {
+        1 => 0.08,
+        2 => 0.05,
+        3 => 0.03,
+        _ => 0.0
+    }
+}
+
+/// Builds the light map from every LightSource, then limits the player's sight to the tiles
+/// that are both in view and lit.  Runs every frame so moving lights update straight away.
+pub struct LightingSystem {}
+
+impl<'a> System<'a> for LightingSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = ( WriteExpect<'a, Map>,
+                        ReadExpect<'a, Entity>,
+                        ReadExpect<'a, RunSettings>,
+                        ReadExpect<'a, EscapeState>,
+                        ReadStorage<'a, Position>,
+                        ReadStorage<'a, LightSource>,
+                        ReadStorage<'a, Player>,
+                        ReadStorage<'a, Viewshed>);
+
+    fn run(&mut self, data : Self::SystemData) {
+        let (mut map, player_entity, settings, escape, positions, lights, players, viewsheds) = data;
+
+        let ambient = ambient_light(map.depth, settings.modifiers.permadark);
+        let mut light_map = vec![RGB::from_f32(ambient, ambient, ambient); map.tiles.len()];
+
+        let mut sources : Vec<(Point, i32, RGB, f32)> = vec![];
+        for (pos, light, player) in (&positions, &lights, (&players).maybe()).join() {
+            let radius = match player {
+                Some(p) if p.torch_fuel <= 0 => EMBER_RADIUS,
+                // the torch gutters lower as the Amulet's darkness deepens
+                Some(_) => i32::max(EMBER_RADIUS, light.radius - escape.doom_level()),
+                _ => light.radius
+            };
+            sources.push((Point::new(pos.x, pos.y), radius, light.color, light.falloff));
+            if player.map_or(false, |p| p.has_amulet) {
+                sources.push((Point::new(pos.x, pos.y), 3, RGB::from_f32(0.9, 0.1, 0.0), 1.0));
+            }
+        }
+
+        for (center, radius, color, falloff) in sources.iter() {
+            for tile in field_of_view(*center, *radius, &map.grid).iter() {
+                if tile.x < 0 || tile.x >= map.width || tile.y < 0 || tile.y >= map.height { continue; }
+                let distance = rltk::DistanceAlg::Pythagoras.distance2d(*center, *tile);
+                let intensity = (1.0 - distance / (*radius as f32 + 1.0)).max(0.0).powf(*falloff);
+                let idx = map.xy_idx(tile.x, tile.y);
+                let lit = light_map[idx];
+                light_map[idx] = RGB::from_f32(
+                    f32::min(1.0, lit.r + color.r * intensity),
+                    f32::min(1.0, lit.g + color.g * intensity),
+                    f32::min(1.0, lit.b + color.b * intensity));
+            }
+        }
+        map.light = light_map;
+
+        // the player only sees what is in view and lit, though they can always feel their way
+        // around the tiles right next to them
+        let player_pos = match positions.get(*player_entity) { Some(p) => Point::new(p.x, p.y), None => return };
+        if let Some(viewshed) = viewsheds.get(*player_entity) {
+            for t in map.visible_tiles.iter_mut() { *t = false };
+            for vis in viewshed.visible_tiles.iter() {
+                let idx = map.xy_idx(vis.x, vis.y);
+                let adjacent = rltk::DistanceAlg::Pythagoras.distance2d(player_pos, *vis) < 1.5;
+                if adjacent || brightness(map.light[idx]) >= LIGHT_THRESHOLD {
+                    map.revealed_tiles[idx] = true;
+                    map.visible_tiles[idx] = true;
+                }
+            }
+        }
+    }
+}
+
+pub fn brightness(light: RGB) -> f32 {
+    f32::max(light.r, f32::max(light.g, light.b))
+}
@@ -9,75 +9,140 @@ mod map;
 pub use map::*;
 mod player;
 use player::*;
-mod rect;
-pub use rect::Rect;
-mod visibility_system;
-use visibility_system::VisibilitySystem;
+pub use barrow_core::Rect;
+use barrow_core::VisibilitySystem;
+mod lighting_system;
+use lighting_system::LightingSystem;
+mod memory_system;
+use memory_system::MemorySystem;
 mod monster_ai_system;
-use monster_ai_system::MonsterAI;
-mod map_indexing_system;
-use map_indexing_system::MapIndexingSystem;
+use barrow_core::MapIndexingSystem;
 mod action_system;
-use action_system::ActionSystem;
+mod damage_system;
+use damage_system::DamageSystem;
+mod ruleset;
+pub use ruleset::{Ruleset, RulesetKind};
+mod intent_rules;
 mod gui;
+mod ui;
 use gui::ShoppingResult::*;
-mod gamelog;
+pub use barrow_core::gamelog;
 use gamelog::GameLog;
+mod events;
+pub use events::*;
 mod spawner;
+mod features;
+mod persistence;
+mod animation;
+mod renderer;
+mod camera;
+pub use animation::Animations;
+pub use features::SarcophagusContents;
+mod dungeon;
+pub use dungeon::{Branch, LevelId};
+mod escape;
+mod boss;
+mod morale;
+pub use boss::BossKind;
+pub use escape::EscapeState;
+mod difficulty;
+pub use difficulty::*;
+mod stats;
+pub use stats::RunStats;
+mod morgue;
+#[cfg(target_arch = "wasm32")]
+mod web_storage;
+use morgue::RunRecord;
+mod keymap;
+pub use keymap::{Keymap, InputAction};
 
 #[derive(PartialEq, Copy, Clone)]
 pub enum RunState { 
     AwaitingInput, 
     PreRun, 
-    Ascend { depth: i32},
-    Descend { depth: i32 },
+    Ascend { level: LevelId, stairs: Option<usize> },
+    Descend { level: LevelId, stairs: Option<usize> },
     PlayerTurn, 
     MonsterTurn,
     Shopping { menu_selection : i32},
     MainMenu { menu_selection : gui::MainMenuSelection },
+    HallOfTheFallen { menu_selection : i32 },
+    LogHistory { offset : i32 },
+    LevelOverview,
+    KeyBindings { selection : i32, capturing : bool },
     GameOver
 }
 
 pub struct LevelState {
     pub seed: u64,
-    pub live_tags: HashMap<u64, bool>,
     pub revealed_tiles: Vec<bool>,
-    pub player_pos: Option<Position>
+    pub remembered: Vec<Option<memory_system::RememberedGlyph>>,
+    pub tiles: Vec<TileType>,
+    pub entities: Vec<persistence::EntitySnapshot>,
+    /// Waves of darkness that have risen here since the Amulet was taken
+    pub darkness: i32
 }
 
 pub struct LevelHistory {
-    pub levels: HashMap<i32, LevelState>
+    pub levels: HashMap<LevelId, LevelState>
 }
 
 pub struct State {
     pub ecs: World,
     pub history: LevelHistory,
-    pub cheat_mode: bool
+    pub settings: RunSettings,
+    pub hall_of_the_fallen: Vec<RunRecord>
 }
 
 impl State {
     fn run_systems(&mut self) {
-        let mut vis = VisibilitySystem{};
+        let mut vis = VisibilitySystem::<Map, Player>::new();
         vis.run_now(&self.ecs);
-        let mut mob = MonsterAI{};
-        mob.run_now(&self.ecs);
-        let mut melee = ActionSystem{};
-        melee.run_now(&self.ecs);
-        let mut mapindex = MapIndexingSystem{};
+        let mut lighting = LightingSystem{};
+        lighting.run_now(&self.ecs);
+        let mut memory = MemorySystem{};
+        memory.run_now(&self.ecs);
+        let rules = self.ecs.fetch::<RunSettings>().ruleset.rules();
+        rules.run_ai(&self.ecs);
+        boss::run_bosses(&mut self.ecs);
+        rules.resolve(&self.ecs);
+        let mut damage = DamageSystem{};
+        damage.run_now(&self.ecs);
+        morale::update_morale(&mut self.ecs);
+        let mut mapindex = MapIndexingSystem::<Map>::new();
         mapindex.run_now(&self.ecs);
         self.ecs.maintain();
     }
 
-    fn load_level(&mut self, depth : i32, player_inv: Option<&Player>, clear: bool, ascend: bool) {
+    /// Writes the finished run to the morgue file, once per run
+    fn record_run(&mut self) {
+        let record;
+        {
+            let mut stats = self.ecs.write_resource::<RunStats>();
+            if stats.recorded { return; }
+            stats.recorded = true;
+            let settings = self.ecs.fetch::<RunSettings>();
+            record = RunRecord::from_run(&stats, &settings);
+        }
+        morgue::save_record(&record);
+        self.hall_of_the_fallen.push(record);
+        morgue::rank(&mut self.hall_of_the_fallen);
+    }
+
+    /// Swaps the current level out for `level`.  `arrival` is the staircase the player
+    /// comes out of, and monsters fighting beside them follow them down it; without one (a new
+    /// run, a fall through a pit) the player starts on the up stairs, alone.
+    fn load_level(&mut self, level : LevelId, player_inv: Option<&Player>, clear: bool, arrival: Option<usize>) {
+        let depth = level.depth;
 
         // FIRST unload the old level
 
         {
             let mut logs = self.ecs.write_resource::<GameLog>();
-            if depth == 0 {
-                // TODO: fix, not appropriate after town
-                logs.entries.clear();
-                logs.entries.push(String::from("Welcome to Barrow!"));
+            // only a fresh start wipes the log; trips to town and back keep it
+            if clear && player_inv.is_none() {
+                logs.clear();
+                logs.push(String::from("Welcome to Barrow!"));
             }    
         }
 
@@ -88,39 +153,27 @@ impl State {
 
         let old_map = self.ecs.remove::<Map>();
 
-        let mut old_player_pos : Option<Position> = None;
-        // let mut monster_tags : Vec<u64> = vec![];
-        // let mut item_tags : Vec<u64> = vec![]; 
-        let mut live_tags : HashMap<u64, bool> = HashMap::new();
-
-        let mut to_delete = Vec::new();
-        {
-            let players = self.ecs.read_storage::<Player>();
-            let pos = self.ecs.read_storage::<Position>();
-            let monsters = self.ecs.read_storage::<Monster>();
-            let items = self.ecs.read_storage::<Item>();
-            let containers = self.ecs.read_storage::<Container>();
-            for (e,pos,player,monster,item,container) in (&self.ecs.entities(), (&pos).maybe(), (&players).maybe(), (&monsters).maybe(), (&items).maybe(), (&containers).maybe()).join() {
-                if player.is_some() && pos.is_some() {
-                    // console::log(format!("unloading player entity {:?} {:?} at {:?}", e, player.unwrap(), pos.unwrap()));
-                    old_player_pos = Some(*pos.unwrap());
-                } else if monster.is_some() {
-                    // console::log(format!("unloading monster entity {:?} {:?}", e, monster.unwrap()));
-                    live_tags.insert(monster.unwrap().tag, true);
-                } else if item.is_some() {
-                    // console::log(format!("unloading item entity {:?} {:?}", e, item.unwrap()));
-                    live_tags.insert(item.unwrap().tag, true);
-                } else if container.is_some() {
-                    // console::log(format!("unloading container entity {:?} {:?}", e, container.unwrap()));
-                    live_tags.insert(container.unwrap().tag, true);
+        let followers = if arrival.is_some() && old_map.is_some() && !clear { player::stair_followers(&self.ecs) } else { vec![] };
+
+        // Snapshot everything but the player, then clear the world for the next level
+        let (mut saved_entities, mut following) = persistence::snapshot_level(&self.ecs, &followers);
+
+        // the pursuer never stays behind on a level; if it isn't at the player's heels it comes after them
+        if old_map.is_some() && escape::escaping(&self.ecs) {
+            let turns = self.ecs.fetch::<RunStats>().turns;
+            let mut escape = self.ecs.write_resource::<EscapeState>();
+            if let Some(pursuer) = escape.pursuer.name() {
+                let is_pursuer = |e: &persistence::EntitySnapshot| e.name.as_ref().map_or(false, |n| n.name == pursuer);
+                let hp = saved_entities.iter().filter(|e| is_pursuer(e)).find_map(|e| e.combat_stats.as_ref().map(|s| s.hp));
+                saved_entities.retain(|e| !is_pursuer(e));
+                if !following.iter().any(is_pursuer) {
+                    escape.pursuer_left_behind(turns, hp);
                 }
-                to_delete.push(e);
             }
         }
-        {
-            for del in to_delete.iter() {
-                self.ecs.delete_entity(*del).expect("Deletion failed");
-            }
+        let to_delete : Vec<Entity> = self.ecs.entities().join().collect();
+        for del in to_delete.iter() {
+            self.ecs.delete_entity(*del).expect("Deletion failed");
         }
 
         // STORE the unloaded level state in the history
@@ -128,8 +181,9 @@ impl State {
         match old_map {
             Some(ref m) => {
                 // console::log(format!("unloading map {:?} at level {}, revealed_tiles: {:?}", m.seed, m.depth, m.revealed_tiles.len()));
-                let old_level_state = LevelState { seed: m.seed, live_tags: live_tags, revealed_tiles: m.revealed_tiles.clone(), player_pos: old_player_pos };
-                self.history.levels.insert(m.depth, old_level_state);
+                let darkness = self.history.levels.get(&m.level_id()).map_or(0, |l| l.darkness);
+                let old_level_state = LevelState { seed: m.seed, revealed_tiles: m.revealed_tiles.clone(), remembered: m.remembered.clone(), tiles: m.tiles.clone(), entities: saved_entities, darkness: darkness };
+                self.history.levels.insert(m.level_id(), old_level_state);
             }
             None => {
                 // console::log("no old map to unload");
@@ -142,132 +196,137 @@ impl State {
 
         // PREPARE for building or reloading a new level
 
-        let mut rng = RandomNumberGenerator::new();
-
         let new_level_spawns : bool;
 
-        let new_level_state = match (player_inv, self.history.levels.remove(&depth)) {
-            (Some(player), Some(state)) => {
+        let mut new_level_state = match (player_inv, self.history.levels.remove(&level)) {
+            (Some(_), Some(state)) => {
                 // console::log(format!("found history for {}, retaining, seed: {}", depth, state.seed));
-                if player.has_amulet {
-                    new_level_spawns = true;
-                } else {
-                    new_level_spawns = false;
-                }
+                new_level_spawns = false;
                 state
             }
             (None, Some(_state)) => {
-                let new_seed = rng.next_u64();
+                let new_seed = self.ecs.write_resource::<RunStats>().next_level_seed();
                 // console::log(format!("found history but no player state, discarding, new seed: {}", new_seed));
                 new_level_spawns = true;
-                LevelState { seed: new_seed, live_tags: HashMap::new(), revealed_tiles: vec![], player_pos: None }
+                LevelState { seed: new_seed, revealed_tiles: vec![], remembered: vec![], tiles: vec![], entities: vec![], darkness: 0 }
             }
             (_, None) => {
-                let new_seed = rng.next_u64();
+                let new_seed = self.ecs.write_resource::<RunStats>().next_level_seed();
                 // console::log(format!("no match found in history for {}, creating new seed {}", depth, new_seed));
                 new_level_spawns = true;
-                LevelState { seed: new_seed, live_tags: HashMap::new(), revealed_tiles: vec![], player_pos: None }
+                LevelState { seed: new_seed, revealed_tiles: vec![], remembered: vec![], tiles: vec![], entities: vec![], darkness: 0 }
             }
         };
 
-        let mut map : Map = Map::new_map_rooms_and_corridors(depth, new_level_state.seed);
+        let mut map : Map = Map::new_level(level, new_level_state.seed);
 
         // UGLY
         if new_level_state.revealed_tiles.len() > 0 {
             map.revealed_tiles = new_level_state.revealed_tiles.clone();
         };
-
-        // let (player_x, player_y) = match (new_level_state.player_pos, ascend) {
-        //     (None, false) => map.rooms[0].center(),
-        //     (None, true)  => map.rooms[map.rooms.len() - 1].center(),
-        //     (Some(pos),_) => (pos.x, pos.y)
-        // };
-
-        let (player_x, player_y) = match (new_level_state.player_pos, ascend) {
-            (_, false) => map.rooms[0].center(),
-            (_, true)  => map.rooms[map.rooms.len() - 1].center()
+        // what the player last saw of the level's items and monsters
+        if new_level_state.remembered.len() > 0 {
+            map.remembered = new_level_state.remembered.clone();
+        };
+        // opened doors, sprung traps, cleared rubble and looted sarcophagi
+        if new_level_state.tiles.len() > 0 {
+            map.tiles = new_level_state.tiles.clone();
         };
 
+        {
+            let mut stats = self.ecs.write_resource::<RunStats>();
+            stats.deepest_depth = i32::max(stats.deepest_depth, depth);
+            stats.visited.insert(level);
+        }
 
-        // ugly
-        rng = RandomNumberGenerator::seeded(new_level_state.seed);
+        let mut rng = RandomNumberGenerator::seeded(new_level_state.seed);
+
+        if new_level_spawns {
+            if level.branch != Branch::Barrow {
+                spawner::populate_branch(&mut self.ecs, &mut rng, &map);
+            } else if depth == 1 {
+                spawner::populate_level_1(&mut self.ecs, &mut rng, &map);
+            } else if depth == 2 {
+                spawner::populate_level_2(&mut self.ecs, &mut rng, &map);
+            } else if depth == 3 {
+                spawner::populate_level_3(&mut self.ecs, &mut rng, &map);
+            } else if depth == 4 {
+                spawner::populate_level_4(&mut self.ecs, &mut rng, &map);
+            } else if depth == 5 {
+                spawner::populate_level_5(&mut self.ecs, &mut rng, &map);
+            } else if depth == 6 {
+                spawner::populate_level_6(&mut self.ecs, &mut rng, &map);
+            } else { 
+                spawner::populate_level_5(&mut self.ecs, &mut rng, &map);
+            }
 
-        // self.ecs.insert::<Player>(player_inv);
-        let player_entity = spawner::player(&mut self.ecs, player_x, player_y, player_inv);
-        self.ecs.insert(player_entity);    
+            if self.ecs.fetch::<RunSettings>().modifiers.double_monsters {
+                spawner::double_monsters(&mut self.ecs, &map);
+            }
+            spawner::braziers(&mut self.ecs, &mut rng, &map);
+        } else {
+            persistence::restore_level(&mut self.ecs, &new_level_state.entities);
+        }
 
-        // Get vector of entity tags from spawner
-        // todo: 
-        if player_inv.is_some() && player_inv.unwrap().has_amulet {
-            // console::log(format!("loading level {:?} in amulet_mode", depth));
-            spawner::populate_level_4(&mut self.ecs, &mut rng, &map);
-        } else if depth == 1 {
-            spawner::populate_level_1(&mut self.ecs, &mut rng, &map);
-        } else if depth == 2 {
-            spawner::populate_level_2(&mut self.ecs, &mut rng, &map);
-        } else if depth == 3 {
-            spawner::populate_level_3(&mut self.ecs, &mut rng, &map);
-        } else if depth == 4 {
-            spawner::populate_level_4(&mut self.ecs, &mut rng, &map);
-        } else if depth == 5 {
-            spawner::populate_level_5(&mut self.ecs, &mut rng, &map);
-         } else if depth == 6 {
-            spawner::populate_level_6(&mut self.ecs, &mut rng, &map);
-            // hack but whatevs
-            let stairs_down_position = map.rooms[map.rooms.len()-1].center();
-            let stairs_down_idx = map.xy_idx(stairs_down_position.0, stairs_down_position.1);
-            map.tiles[stairs_down_idx] = TileType::Floor;        
-        } else { 
-            spawner::populate_level_5(&mut self.ecs, &mut rng, &map);
+        // the Amulet's darkness rises again every time the player passes through
+        if escape::escaping(&self.ecs) {
+            let wave = new_level_state.darkness + self.ecs.fetch::<EscapeState>().doom_level() + 1;
+            let mut dark_rng = RandomNumberGenerator::seeded(new_level_state.seed.wrapping_add(wave as u64));
+            spawner::darkness(&mut self.ecs, &mut dark_rng, &map, wave);
+            new_level_state.darkness += 1;
         }
 
-        self.ecs.maintain();
-        // despawn any entities that shouldn't be respawned
-        // don't have to check if new spawn here at the top
-        if !new_level_spawns {
-            let mut to_despawn : Vec<Entity> = vec![];
-
-            {
-                let monsters = self.ecs.read_storage::<Monster>();
-                let items = self.ecs.read_storage::<Item>();
-                let containers = self.ecs.read_storage::<Container>();
-                for (e,monster,item,container) in (&self.ecs.entities(), (&monsters).maybe(), (&items).maybe(), (&containers).maybe()).join() {
-                    if monster.is_some() {
-                        let monster_tag = &monster.unwrap().tag;
-                        if new_level_state.live_tags.contains_key(monster_tag) {
-                            // could add to live_tags here
-                        } else {
-                            // console::log(format!("despawning monster {:?}", monster));
-                            to_despawn.push(e);
-                        }
-                    } else if item.is_some() {
-                        let item_tag = &item.unwrap().tag;
-                        if new_level_state.live_tags.contains_key(item_tag) {
-                            // could add to live tags here
-                        } else {
-                            // console::log(format!("despawning item {:?}", item));
-                            to_despawn.push(e);
-                        }
-                    } else if container.is_some() {
-                        let container_tag = &container.unwrap().tag;
-                        if new_level_state.live_tags.contains_key(container_tag) {
-                            // could add to live tags here
-                        } else {
-                            // console::log(format!("despawning container {:?}", container));
-                            to_despawn.push(e);
-                        }
+        // Arrive on the staircase linked to the one we took
+        let (player_x, player_y) = match map.staircase(arrival.unwrap_or(STAIRS_UP)) {
+            Some(stairs) => (stairs.idx as i32 % map.width, stairs.idx as i32 / map.width),
+            None => map.rooms[0].center()
+        };
+
+        self.ecs.write_resource::<EscapeState>().entry = Some(Position { x: player_x, y: player_y });
+
+        // followers come out of the stairs around the player, as long as there is room
+        let mut occupied : Vec<Position> = (&self.ecs.read_storage::<Position>(), &self.ecs.read_storage::<BlocksTile>()).join().map(|(pos, _)| *pos).collect();
+        occupied.push(Position { x: player_x, y: player_y });
+        let mut arrivals = vec![];
+        for mut follower in following.drain(..) {
+            let free = (-1..=1).flat_map(|dy| (-1..=1).map(move |dx| Position { x: player_x + dx, y: player_y + dy }))
+                .find(|p| map.tiles[map.xy_idx(p.x, p.y)] == TileType::Floor && !occupied.contains(p));
+            match (free, &old_map) {
+                (Some(pos), _) => {
+                    follower.position = pos;
+                    occupied.push(pos);
+                    if let Some(name) = &follower.name {
+                        self.ecs.write_resource::<GameLog>().push_category(gamelog::LogCategory::Movement, format!("#[orange]{}#[] follows you.", name.name));
+                    }
+                    arrivals.push(follower);
+                }
+                (None, Some(m)) => {
+                    // no room at the foot of the stairs; it stays where it was, except the
+                    // pursuer, which never stays behind and comes out of the stairs later instead
+                    let pursuer = self.ecs.fetch::<EscapeState>().pursuer.name();
+                    let is_pursuer = escape::escaping(&self.ecs) && follower.name.as_ref().map_or(false, |n| Some(n.name.as_str()) == pursuer);
+                    if is_pursuer {
+                        let turns = self.ecs.fetch::<RunStats>().turns;
+                        let hp = follower.combat_stats.as_ref().map(|s| s.hp);
+                        self.ecs.write_resource::<EscapeState>().pursuer_left_behind(turns, hp);
+                    } else if let Some(old_level) = self.history.levels.get_mut(&m.level_id()) {
+                        old_level.entities.push(follower);
                     }
                 }
+                (None, None) => {}
             }
-            for e in to_despawn.iter() {
-                self.ecs.delete_entity(*e).expect("Deletion failed");
-            }    
-    
         }
+        persistence::restore_level(&mut self.ecs, &arrivals);
 
-        // should populate live_tags for newly generated levels here, probably skippable though
+        // self.ecs.insert::<Player>(player_inv);
+        let player_entity = spawner::player(&mut self.ecs, player_x, player_y, player_inv);
+        self.ecs.insert(player_entity);    
+        self.ecs.write_resource::<TravelPath>().clear();
+        self.ecs.write_resource::<Animations>().active.clear();
+
+        self.ecs.maintain();
 
-        self.history.levels.insert(depth, new_level_state );
+        self.history.levels.insert(level, new_level_state );
 
         self.ecs.insert(map);
     }
@@ -287,53 +346,14 @@ impl GameState for State {
         ctx.cls();
 
         match newrunstate {
-            RunState::MainMenu{..} => {}
+            RunState::MainMenu{..} | RunState::HallOfTheFallen{..} | RunState::KeyBindings{..} => {}
             _ => {
-                draw_map(&self.ecs, ctx);
-                {
-                    let positions = self.ecs.read_storage::<Position>();
-                    let renderables = self.ecs.read_storage::<Renderable>();
-                    let items = self.ecs.read_storage::<Item>();
-                    let map = self.ecs.fetch::<Map>();
-
-                    let data = (&positions, &renderables, &items).join().collect::<Vec<_>>();
-                    for (pos, render, _item) in data.iter() {
-                        let idx = map.xy_idx(pos.x, pos.y);
-                        if map.visible_tiles[idx] { ctx.set(pos.x, pos.y, render.fg, render.bg, render.glyph) }
-                    }
-                }
-                {
-                    let positions = self.ecs.read_storage::<Position>();
-                    let renderables = self.ecs.read_storage::<Renderable>();
-                    let containers = self.ecs.read_storage::<Container>();
-                    let map = self.ecs.fetch::<Map>();
-
-                    let data = (&positions, &renderables, &containers).join().collect::<Vec<_>>();
-                    for (pos, render, _container) in data.iter() {
-                        let idx = map.xy_idx(pos.x, pos.y);
-                        if map.visible_tiles[idx] { ctx.set(pos.x, pos.y, render.fg, render.bg, render.glyph) }
-                    }
-                }
-                {
-                    let positions = self.ecs.read_storage::<Position>();
-                    let renderables = self.ecs.read_storage::<Renderable>();
-                    let stats = self.ecs.read_storage::<CombatStats>();
-                    let player = self.ecs.read_storage::<Player>();
-                    let map = self.ecs.fetch::<Map>();
-
-                    let data = (&positions, &renderables, &stats, (&player).maybe()).join().collect::<Vec<_>>();
-                    for (pos, render, _stats, player) in data.iter() {
-                        let idx = map.xy_idx(pos.x, pos.y);
-                        if map.visible_tiles[idx] { ctx.set(pos.x, pos.y, render.fg, render.bg, render.glyph) }
-                        if player.is_some() && player.unwrap().has_amulet == true {
-                            let anim_index = map.frame_count.rem_euclid(60);
-                            let anim_index_cycle = if anim_index > 30 { 30 - (anim_index - 30) } else { anim_index };
-                            ctx.set(pos.x, pos.y, RGB::from_u8(255u8,8u8 * anim_index_cycle as u8, 0), render.bg, render.glyph);
-                        }
-                    }                    
-                }
+                let mut lighting = LightingSystem{};
+                lighting.run_now(&self.ecs);
+                let mut memory = MemorySystem{};
+                memory.run_now(&self.ecs);
                 player::update_targeting(&self.ecs, ctx);
-                gui::draw_ui(&self.ecs, ctx);
+                renderer::render(&self.ecs, ctx);
             }
         }
 
@@ -347,9 +367,16 @@ impl GameState for State {
                 newrunstate = player_input(self, ctx);
             }
             RunState::PlayerTurn => {
+                self.ecs.write_resource::<RunStats>().turns += 1;
+                player::burn_torch(&self.ecs);
+                action_system::rot_corpses(&self.ecs);
+                escape::tick(&mut self.ecs);
                 self.run_systems();
                 self.ecs.maintain();
-                newrunstate = RunState::MonsterTurn;
+                newrunstate = match features::fell_through_pit(&self.ecs) {
+                    Some(level) => RunState::Descend { level: level, stairs: None },
+                    None => RunState::MonsterTurn
+                };
             }
             RunState::MonsterTurn => {
                 self.run_systems();
@@ -368,7 +395,7 @@ impl GameState for State {
                             player_inv = *players.get(*player_entity).unwrap();
                         }
                     
-                        self.load_level(1, Some(&player_inv), false, false);
+                        self.load_level(LevelId::barrow(1), Some(&player_inv), false, Some(STAIRS_UP));
                         newrunstate = RunState::PreRun;
                     }
                     LongRest => {
@@ -379,7 +406,7 @@ impl GameState for State {
                             player_inv = *players.get(*player_entity).unwrap();
                         }
                         self.history.levels.clear();
-                        self.load_level(1, Some(&player_inv), true, false);
+                        self.load_level(LevelId::barrow(1), Some(&player_inv), true, Some(STAIRS_UP));
                         newrunstate = RunState::Shopping { menu_selection : 0 };
                     }
                     Deepest => {
@@ -389,7 +416,7 @@ impl GameState for State {
                             let players = self.ecs.write_storage::<Player>();    
                             player_inv = *players.get(*player_entity).unwrap();
                         }
-                        self.load_level(player_inv.deepest_level, Some(&player_inv), false, false);
+                        self.load_level(LevelId::barrow(player_inv.deepest_level), Some(&player_inv), false, Some(STAIRS_UP));
                         newrunstate = RunState::PreRun;
                     }
                     Selected { selected: s } => {
@@ -400,22 +427,18 @@ impl GameState for State {
                     }
                 }
             }
-            RunState::Ascend { depth: d } => {
-                if d < 1 {
+            RunState::Ascend { level, stairs } => {
+                if level.depth < 1 {
                     let player_inv:Player;
                     {
                         let player_entity = self.ecs.fetch::<Entity>();
                         let players = self.ecs.write_storage::<Player>();    
                         player_inv = *players.get(*player_entity).unwrap();
-                        // TODO - hack to put player at correct stairs
                     }
                     if player_inv.has_amulet {
                         // console::log(format!("ascending to level {} with amulet, game ending",d));
-                        let mut log = self.ecs.write_resource::<GameLog>();
-                        log.entries.push(format!("You return to town safely with the Amulet..."));
-                        log.entries.push(format!("But Yendor's darkness clings to your spirit."));
-                        log.entries.push(format!("#[red](You have won Barrow!)"));
-                        log.entries.push(format!("#[magenta](Press ESCAPE to return to the main menu!)"));
+                        let grade = self.ecs.fetch::<EscapeState>().grade(self.ecs.fetch::<RunStats>().turns);
+                        self.ecs.write_resource::<EventQueue>().emit(GameEvent::Escaped { grade: grade });
 
                         newrunstate = RunState::GameOver;
 
@@ -432,14 +455,13 @@ impl GameState for State {
                         let player_entity = self.ecs.fetch::<Entity>();
                         let players = self.ecs.write_storage::<Player>();    
                         player_inv = *players.get(*player_entity).unwrap();
-                        // TODO - hack to put player at correct stairs
                     }
     
-                    self.load_level(d, Some(&player_inv), false, true);
+                    self.load_level(level, Some(&player_inv), false, stairs);
                     newrunstate = RunState::PreRun;
                 }
             }
-            RunState::Descend{ depth: d } => {
+            RunState::Descend{ level, stairs } => {
                 let player_inv:Player;
                 {
                     let player_entity = self.ecs.fetch::<Entity>();
@@ -447,7 +469,7 @@ impl GameState for State {
                     player_inv = *players.get(*player_entity).unwrap();
                 }
 
-                self.load_level(d, Some(&player_inv), false, false);
+                self.load_level(level, Some(&player_inv), false, stairs);
                 newrunstate = RunState::PreRun;
             }
             RunState::MainMenu{ .. } => {
@@ -459,38 +481,91 @@ impl GameState for State {
                             gui::MainMenuSelection::NewGame => {
                                 {
                                     let mut log = self.ecs.write_resource::<GameLog>();
-                                    log.entries.push(format!("You enter the barrow of an ancient lord, having heard of its riches."));
-                                    log.entries.push(format!("Many adventurers, it is said, have met their dooms within - "));
-                                    log.entries.push(format!("Will you overcome the dangers, and retrieve the barrow-lord's treasure?"));
+                                    log.push(format!("You enter the barrow of an ancient lord, having heard of its riches."));
+                                    log.push(format!("Many adventurers, it is said, have met their dooms within - "));
+                                    log.push(format!("Will you overcome the dangers, and retrieve the barrow-lord's treasure?"));
                                 }
 
-                                if self.cheat_mode {
-                                    let player = Player { food: 10, max_food: 10, coin: 600, potions: 0, atk_bonus: 0, def_bonus: 0, deepest_level: 5, has_amulet: false };
-                                    self.load_level(1,Some(&player),true, false);                                    
-                                } else {
-                                    self.load_level(1,None,true, false);
-                                }
+                                self.ecs.insert(self.settings);
+                                let run_seed = self.ecs.write_resource::<RandomNumberGenerator>().next_u64();
+                                self.ecs.insert(RunStats::new(run_seed));
+                                self.ecs.insert(EscapeState::new());
+                                self.ecs.insert(Achievements::new());
+                                self.load_level(LevelId::barrow(1), None, true, None);
                                 newrunstate = RunState::PreRun
                             },
+                            gui::MainMenuSelection::HallOfTheFallen => {
+                                newrunstate = RunState::HallOfTheFallen{ menu_selection: 0 };
+                            }
+                            gui::MainMenuSelection::KeyBindings => {
+                                newrunstate = RunState::KeyBindings{ selection: 0, capturing: false };
+                            }
                             gui::MainMenuSelection::Quit => { ::std::process::exit(0); },
-                            gui::MainMenuSelection::CheatMode => {
-                                self.cheat_mode = true;                                
+                            gui::MainMenuSelection::Difficulty => {
+                                self.settings.difficulty = self.settings.difficulty.next();
+                            }
+                            gui::MainMenuSelection::Ruleset => {
+                                self.settings.ruleset = self.settings.ruleset.next();
+                            }
+                            gui::MainMenuSelection::NoTownShop => {
+                                self.settings.modifiers.no_town_shop = !self.settings.modifiers.no_town_shop;
                             }
+                            gui::MainMenuSelection::DoubleMonsters => {
+                                self.settings.modifiers.double_monsters = !self.settings.modifiers.double_monsters;
+                            }
+                            gui::MainMenuSelection::Permadark => {
+                                self.settings.modifiers.permadark = !self.settings.modifiers.permadark;
+                            }
+                            gui::MainMenuSelection::FoodScarcity => {
+                                self.settings.modifiers.food_scarcity = !self.settings.modifiers.food_scarcity;
+                            }
+                        }
+                        if selected != gui::MainMenuSelection::NewGame && selected != gui::MainMenuSelection::HallOfTheFallen && selected != gui::MainMenuSelection::KeyBindings {
+                            newrunstate = RunState::MainMenu{ menu_selection: selected };
                         }
                     }
                 }
             }
+            RunState::LogHistory{ .. } => {
+                let result = gui::log_history(self, ctx);
+                match result {
+                    gui::LogHistoryResult::Scrolled{ offset } => newrunstate = RunState::LogHistory{ offset },
+                    gui::LogHistoryResult::Close => newrunstate = RunState::AwaitingInput
+                }
+            }
+            RunState::LevelOverview => {
+                let result = gui::level_overview(self, ctx);
+                match result {
+                    gui::OverviewResult::Open => {}
+                    gui::OverviewResult::Close => newrunstate = RunState::AwaitingInput
+                }
+            }
+            RunState::KeyBindings{ .. } => {
+                let result = gui::key_bindings(self, ctx);
+                match result {
+                    gui::KeyBindingsResult::Selected{ selection, capturing } => newrunstate = RunState::KeyBindings{ selection, capturing },
+                    gui::KeyBindingsResult::Back => newrunstate = RunState::MainMenu{ menu_selection: gui::MainMenuSelection::KeyBindings }
+                }
+            }
+            RunState::HallOfTheFallen{ .. } => {
+                let result = gui::hall_of_the_fallen(self, ctx);
+                match result {
+                    gui::HallResult::Selected{ selected } => newrunstate = RunState::HallOfTheFallen{ menu_selection: selected },
+                    gui::HallResult::Back => newrunstate = RunState::MainMenu{ menu_selection: gui::MainMenuSelection::HallOfTheFallen }
+                }
+            }
             RunState::GameOver => {
-                let result = gui::game_over(ctx);
+                self.record_run();
+                let result = gui::game_over(self, ctx);
                 match result {
                     gui::GameOverResult::NoSelection => {}
                     gui::GameOverResult::QuitToMenu => {
                         {
                             let mut log = self.ecs.write_resource::<GameLog>();
-                            log.entries.clear();
+                            log.clear();
                         }
                         self.history.levels.clear();
-                        self.load_level(1, None, true, false);
+                        self.load_level(LevelId::barrow(1), None, true, None);
                         // self.game_over_cleanup();
                         newrunstate = RunState::MainMenu{ menu_selection: gui::MainMenuSelection::NewGame };
                     }
@@ -503,16 +578,20 @@ impl GameState for State {
             *runwriter = newrunstate;
         }
         action_system::delete_the_dead(&mut self.ecs);
+        events::process_events(&mut self.ecs);
     }
 }
 
+/// Size of the UI console, in its 8x16 cells; the map console under it has twice the rows
+const UI_WIDTH : i32 = 80;
+const UI_HEIGHT : i32 = 30;
 
 fn main() -> rltk::BError {
     use rltk::RltkBuilder;
-    let context = RltkBuilder::simple(80,60)
+    let context = RltkBuilder::simple(UI_WIDTH, UI_HEIGHT * 2)
         .unwrap()
         .with_font("vga8x16.png", 8u32, 16u32)
-        .with_sparse_console(80u32, 30u32, "vga8x16.png")
+        .with_sparse_console(UI_WIDTH as u32, UI_HEIGHT as u32, "vga8x16.png")
         .with_title("Barrow")
         .build()?;
 
@@ -533,7 +612,8 @@ fn main() -> rltk::BError {
     let mut gs = State {
         ecs: World::new(),
         history: history,
-        cheat_mode: false
+        settings: RunSettings::new(),
+        hall_of_the_fallen: morgue::load_records()
     };
 
     gs.ecs.register::<Position>();
@@ -547,13 +627,31 @@ fn main() -> rltk::BError {
     gs.ecs.register::<BlocksTile>();
     gs.ecs.register::<CombatStats>();
     gs.ecs.register::<Action>();
+    gs.ecs.register::<SufferDamage>();
     gs.ecs.register::<SmartMonster>();
+    gs.ecs.register::<Boss>();
+    gs.ecs.register::<Morale>();
+    gs.ecs.register::<Inventory>();
+    gs.ecs.register::<Corpse>();
+    gs.ecs.register::<LightSource>();
     let rng = rltk::RandomNumberGenerator::new();
     gs.ecs.insert(rng);
-    gs.ecs.insert(gamelog::GameLog{ entries : vec![] });
+    gs.ecs.insert(GameLog::new());
+    gs.ecs.insert(gamelog::LogView::new());
+    gs.ecs.insert(Keymap::load());
+    gs.ecs.insert(gui::PanelLayout::new());
+    gs.ecs.insert(TravelPath::new());
+    gs.ecs.insert(Animations::new());
+    gs.ecs.insert(camera::Viewport::new(&ui::ScreenLayout::new(UI_WIDTH, UI_HEIGHT)));
+    gs.ecs.insert(EventQueue::new());
+    gs.ecs.insert(SoundCues::new());
+    gs.ecs.insert(Achievements::new());
+    gs.ecs.insert(gs.settings);
+    gs.ecs.insert(RunStats::new(0));
+    gs.ecs.insert(EscapeState::new());
 
     gs.ecs.insert(RunState::MainMenu{ menu_selection: gui::MainMenuSelection::NewGame });
-    gs.load_level(1,None,true, false);
+    gs.load_level(LevelId::barrow(1), None, true, None);
 
     rltk::main_loop(context, gs)
 }
@@ -1,210 +1,330 @@
-use std::cmp::Ordering;
-use rltk::{ RGB, Rltk, RandomNumberGenerator, BaseMap, Algorithm2D, Point, FastNoise};
-use super::{Rect, Position};
-use std::cmp::{max, min};
+use rltk::{ RGB, Rltk, RandomNumberGenerator, Point, FastNoise};
+use super::{Rect, lighting_system::brightness, camera::Camera, memory_system::RememberedGlyph, dungeon::{Branch, LevelId}};
+use std::ops::{Deref, DerefMut};
+use std::collections::VecDeque;
 use specs::prelude::*;
+use barrow_core::{Tile, GridMap, MapGrid};
 
-const MAPWIDTH : usize = 50;
-const MAPHEIGHT : usize = 43;
-const MAPCOUNT : usize = MAPHEIGHT * MAPWIDTH;
+/// Size of an ordinary level.  The flooded tunnels sprawl further, past the edges of the
+/// screen, and the camera scrolls to follow the player through them.
+const MAPWIDTH : i32 = 50;
+const MAPHEIGHT : i32 = 43;
+const TUNNELS_WIDTH : i32 = 80;
+const TUNNELS_HEIGHT : i32 = 60;
 
 #[derive(PartialEq, Copy, Clone, Debug)]
 pub enum TileType {
-    Wall, Floor, StairsUp, StairsDown
+    Wall, Floor, StairsUp, StairsDown,
+    DoorClosed, DoorOpen,
+    Trap { kind: TrapKind, hidden: bool },
+    Rubble,
+    Sarcophagus, SarcophagusOpen,
+    Water
 }
 
-#[derive(Default, Clone)]
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum TrapKind { Dart, Pit, Alarm }
+
+/// Ids of a level's staircases: up and down its own branch, and down into a side branch
+pub const STAIRS_UP : usize = 0;
+pub const STAIRS_DOWN : usize = 1;
+pub const STAIRS_BRANCH : usize = 2;
+
+/// Where a staircase leads: the level it goes to and the staircase the player arrives on there
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct StairLink {
+    pub level: LevelId,
+    pub stairs: usize
+}
+
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct Staircase {
+    pub id: usize,
+    pub idx: usize,
+    pub to: StairLink
+}
+
+impl Tile for TileType {
+    const WALL: TileType = TileType::Wall;
+    const FLOOR: TileType = TileType::Floor;
+
+    fn blocks(&self) -> bool {
+        matches!(self, TileType::Wall | TileType::Rubble | TileType::Sarcophagus | TileType::SarcophagusOpen)
+    }
+
+    fn opaque(&self) -> bool {
+        matches!(self, TileType::Wall | TileType::DoorClosed)
+    }
+}
+
+/// A level: the grid of tiles, with its staircases, the light falling on it and what the
+/// player remembers seeing there
+#[derive(Clone)]
 pub struct Map {
-    pub tiles : Vec<TileType>,
-    pub rooms : Vec<Rect>,
-    pub width : i32,
-    pub height : i32,
+    pub grid : GridMap<TileType>,
+    pub stairs : Vec<Staircase>,
+    pub branch: Branch,
     pub depth: i32,
-    pub revealed_tiles : Vec<bool>,
-    pub visible_tiles : Vec<bool>,
-    pub blocked : Vec<bool>,
-    pub tile_content : Vec<Vec<Entity>>,
+    pub light : Vec<RGB>,
+    pub remembered : Vec<Option<RememberedGlyph>>,
     pub seed : u64,
     pub frame_count : u64
 }
 
+impl Deref for Map {
+    type Target = GridMap<TileType>;
+    fn deref(&self) -> &GridMap<TileType> { &self.grid }
+}
+
+impl DerefMut for Map {
+    fn deref_mut(&mut self) -> &mut GridMap<TileType> { &mut self.grid }
+}
+
 impl Map {
-    pub fn xy_idx(&self, x: i32, y: i32) -> usize {
-        (y as usize * self.width as usize) + x as usize
-    }
+    /// Breadth-first search over revealed, unblocked tiles for the closest tile that borders
+    /// unexplored space or is one of `goals`.  Returns the steps to get there, not including `start`.
+    pub fn explore_path(&self, start: usize, goals: &[usize]) -> Option<Vec<usize>> {
+        let mut parents : Vec<Option<usize>> = vec![None; self.tiles.len()];
+        let mut queue : VecDeque<usize> = VecDeque::new();
+        parents[start] = Some(start);
+        queue.push_back(start);
+
+        while let Some(idx) = queue.pop_front() {
+            let x = idx as i32 % self.width;
+            let y = idx as i32 / self.width;
+            let neighbours : Vec<(i32, i32)> = vec![(x-1, y), (x+1, y), (x, y-1), (x, y+1), (x-1, y-1), (x+1, y-1), (x-1, y+1), (x+1, y+1)];
+
+            let is_frontier = neighbours.iter().any(|(nx, ny)| {
+                *nx >= 0 && *nx < self.width && *ny >= 0 && *ny < self.height && !self.revealed_tiles[self.xy_idx(*nx, *ny)]
+            });
+            if idx != start && (is_frontier || goals.contains(&idx)) {
+                let mut path = vec![idx];
+                let mut current = idx;
+                while let Some(parent) = parents[current] {
+                    if parent == start { break; }
+                    path.push(parent);
+                    current = parent;
+                }
+                path.reverse();
+                return Some(path);
+            }
 
-    fn apply_room_to_map(&mut self, room : &Rect) {
-        for y in room.y1 +1 ..= room.y2 {
-            for x in room.x1 + 1 ..= room.x2 {
-                let idx = self.xy_idx(x, y);
-                self.tiles[idx] = TileType::Floor;
+            for (nx, ny) in neighbours.iter() {
+                if !self.is_exit_valid(*nx, *ny) { continue; }
+                let next = self.xy_idx(*nx, *ny);
+                if parents[next].is_none() && self.revealed_tiles[next] {
+                    parents[next] = Some(idx);
+                    queue.push_back(next);
+                }
             }
         }
+        None
     }
 
-    fn apply_horizontal_tunnel(&mut self, x1:i32, x2:i32, y:i32) {
-        for x in min(x1,x2) ..= max(x1,x2) {
-            let idx = self.xy_idx(x, y);
-            if idx > 0 && idx < self.width as usize * self.height as usize {
-                self.tiles[idx as usize] = TileType::Floor;
-            }
-        }
+    pub fn level_id(&self) -> LevelId {
+        LevelId { branch: self.branch, depth: self.depth }
     }
 
-    fn apply_vertical_tunnel(&mut self, y1:i32, y2:i32, x:i32) {
-        for y in min(y1,y2) ..= max(y1,y2) {
-            let idx = self.xy_idx(x, y);
-            if idx > 0 && idx < self.width as usize * self.height as usize {
-                self.tiles[idx as usize] = TileType::Floor;
-            }
-        }
+    pub fn staircase(&self, id: usize) -> Option<&Staircase> {
+        self.stairs.iter().find(|s| s.id == id)
     }
 
-    fn is_exit_valid(&self, x:i32, y:i32) -> bool {
-        if x < 1 || x > self.width-1 || y < 1 || y > self.height-1 { return false; }
-        let idx = self.xy_idx(x, y);
-        !self.blocked[idx]
+    pub fn staircase_at(&self, idx: usize) -> Option<&Staircase> {
+        self.stairs.iter().find(|s| s.idx == idx)
     }
 
-    pub fn populate_blocked(&mut self) {
-        for (i,tile) in self.tiles.iter_mut().enumerate() {
-            self.blocked[i] = *tile == TileType::Wall;
-        }
+    /// Places a staircase; links that lead deeper get a down staircase tile
+    pub fn add_stairs(&mut self, id: usize, x: i32, y: i32, to: StairLink) {
+        let idx = self.xy_idx(x, y);
+        self.tiles[idx] = if to.level.depth > self.depth { TileType::StairsDown } else { TileType::StairsUp };
+        self.stairs.retain(|s| s.id != id);
+        self.stairs.push(Staircase { id: id, idx: idx, to: to });
     }
 
-    pub fn clear_content_index(&mut self) {
-        for content in self.tile_content.iter_mut() {
-            content.clear();
-        }
+    /// The stairs tile of the given type, if the player has seen it
+    pub fn known_stairs(&self, stairs: TileType) -> Option<usize> {
+        (0..self.tiles.len()).find(|idx| self.tiles[*idx] == stairs && self.revealed_tiles[*idx])
     }
 
-    /// Makes a new map using the algorithm from http://rogueliketutorials.com/tutorials/tcod/part-3/
-    /// This gives a handful of random rooms and corridors joining them together.
-    pub fn new_map_rooms_and_corridors(depth: i32, seed:u64) -> Map {
-        let mut rng = RandomNumberGenerator::seeded(seed);
+    /// Doors where corridors meet rooms, then hidden traps, rubble and sarcophagi inside the rooms.
+    /// Uses the level's generator rng, so a reloaded level gets the same features.
+    fn place_features(&mut self, rng: &mut RandomNumberGenerator) {
+        let rooms = self.rooms.clone();
+        let last_room = rooms.len() - 1;
 
-        let mut map = Map{
-            tiles : vec![TileType::Wall; MAPCOUNT],
-            rooms : Vec::new(),
-            width : MAPWIDTH as i32,
-            height: MAPHEIGHT as i32,
-            depth: depth,
-            revealed_tiles : vec![false; MAPCOUNT],
-            visible_tiles : vec![false; MAPCOUNT],
-            blocked : vec![false; MAPCOUNT],
-            tile_content : vec![Vec::new(); MAPCOUNT],
-            seed : seed,
-            frame_count : 0
-        };
-
-        const MAX_ROOMS : i32 = 30;
-        const MIN_SIZE : i32 = 6;
-        const MAX_SIZE : i32 = 10;
-
-        for _i in 0..MAX_ROOMS {
-            let w = rng.range(MIN_SIZE, MAX_SIZE);
-            let h = rng.range(MIN_SIZE, MAX_SIZE);
-            let x = rng.roll_dice(1, map.width - w - 1) - 1;
-            let y = rng.roll_dice(1, map.height - h - 1) - 1;
-            let new_room = Rect::new(x, y, w, h);
-            let mut ok = true;
-            for other_room in map.rooms.iter() {
-                if new_room.intersect(other_room) { ok = false }
+        for room in rooms.iter() {
+            let mut ring : Vec<(i32, i32, bool)> = vec![];
+            for x in room.x1 + 1 ..= room.x2 {
+                ring.push((x, room.y1, true));
+                ring.push((x, room.y2 + 1, true));
             }
-            if ok {
-                map.apply_room_to_map(&new_room);
-
-                if !map.rooms.is_empty() {
-                    let (new_x, new_y) = new_room.center();
-                    let (prev_x, prev_y) = map.rooms[map.rooms.len()-1].center();
-                    if rng.range(0,2) == 1 {
-                        map.apply_horizontal_tunnel(prev_x, new_x, prev_y);
-                        map.apply_vertical_tunnel(prev_y, new_y, new_x);
-                    } else {
-                        map.apply_vertical_tunnel(prev_y, new_y, prev_x);
-                        map.apply_horizontal_tunnel(prev_x, new_x, new_y);
-                    }
+            for y in room.y1 + 1 ..= room.y2 {
+                ring.push((room.x1, y, false));
+                ring.push((room.x2 + 1, y, false));
+            }
+            for (x, y, horizontal) in ring {
+                if x < 1 || x > self.width - 2 || y < 1 || y > self.height - 2 { continue; }
+                let idx = self.xy_idx(x, y);
+                if self.tiles[idx] != TileType::Floor { continue; }
+                let (side_a, side_b) = if horizontal { (self.xy_idx(x - 1, y), self.xy_idx(x + 1, y)) } else { (self.xy_idx(x, y - 1), self.xy_idx(x, y + 1)) };
+                if self.tiles[side_a] == TileType::Wall && self.tiles[side_b] == TileType::Wall && rng.range(0, 3) > 0 {
+                    self.tiles[idx] = TileType::DoorClosed;
                 }
-
-                map.rooms.push(new_room);
             }
         }
 
-        let first_room = map.rooms[0];
-        let first_room_center = first_room.center();
-
-        let cmp_room_dist = |a:&Rect, b:&Rect| -> Ordering {
-            let a_center = a.center();
-            let b_center = b.center();
-            let a_distance = rltk::DistanceAlg::Pythagoras.distance2d(Point::new(a_center.0, a_center.1), Point::new(first_room_center.0, first_room_center.1));
-            let b_distance = rltk::DistanceAlg::Pythagoras.distance2d(Point::new(b_center.0, b_center.1), Point::new(first_room_center.0, first_room_center.1));
-            return a_distance.partial_cmp(&b_distance).unwrap_or(Ordering::Equal);
-        };
-        // let mut spawn_rooms = map.rooms.clone();
-        map.rooms.sort_by(&cmp_room_dist);
+        for (i, room) in rooms.iter().enumerate() {
+            // keep the stairs rooms clear
+            if i == 0 || i == last_room { continue; }
+            let (cx, cy) = room.center();
+            let roll = rng.roll_dice(1, 12);
+            let feature = match roll {
+                1 | 2 => Some(TileType::Trap { kind: TrapKind::Dart, hidden: true }),
+                3 if self.level_id().down().is_some() => Some(TileType::Trap { kind: TrapKind::Pit, hidden: true }),
+                4 => Some(TileType::Trap { kind: TrapKind::Alarm, hidden: true }),
+                5 | 6 => Some(TileType::Rubble),
+                7 => Some(TileType::Sarcophagus),
+                _ => None
+            };
+            if let Some(tile) = feature {
+                // stay off the centre lines, which the corridors run along
+                let x = rng.range(room.x1 + 2, room.x2);
+                let y = rng.range(room.y1 + 2, room.y2);
+                let idx = self.xy_idx(x, y);
+                if x != cx && y != cy && self.tiles[idx] == TileType::Floor {
+                    self.tiles[idx] = tile;
+                }
+            }
+        }
+    }
 
-        // consider moving this out to the loader
-        let stairs_up_position = first_room_center;
-        let stairs_up_idx = map.xy_idx(stairs_up_position.0, stairs_up_position.1);
-        map.tiles[stairs_up_idx] = TileType::StairsUp;
+    /// Builds a level with its branch's generator.  The same level and seed always give the
+    /// same map, which is how levels are rebuilt when the player returns to them.
+    pub fn new_level(level: LevelId, seed: u64) -> Map {
+        let mut rng = RandomNumberGenerator::seeded(seed);
+        let (width, height) = Map::level_size(level);
+        let mut map = Map::blank(level, seed, width, height);
 
-        let stairs_down_position = map.rooms[map.rooms.len()-1].center();
-        let stairs_down_idx = map.xy_idx(stairs_down_position.0, stairs_down_position.1);
-        map.tiles[stairs_down_idx] = TileType::StairsDown;
+        match level.branch {
+            Branch::Barrow => map.carve_rooms_and_corridors(&mut rng, 6, 10),
+            Branch::Crypt => map.carve_crypt(&mut rng),
+            Branch::FloodedTunnels => map.carve_rooms_and_corridors(&mut rng, 4, 8)
+        }
+        map.sort_rooms_from_first();
+        map.link_stairs();
+        map.place_features(&mut rng);
+        if level.branch == Branch::FloodedTunnels {
+            map.flood(&mut rng);
+        }
 
         map
     }
-}
 
-impl BaseMap for Map {
-    fn is_opaque(&self, idx:usize) -> bool {
-        self.tiles[idx] == TileType::Wall
+    pub fn level_size(level: LevelId) -> (i32, i32) {
+        match level.branch {
+            Branch::FloodedTunnels => (TUNNELS_WIDTH, TUNNELS_HEIGHT),
+            _ => (MAPWIDTH, MAPHEIGHT)
+        }
     }
 
+    fn blank(level: LevelId, seed: u64, width: i32, height: i32) -> Map {
+        let count = (width * height) as usize;
+        Map{
+            grid : GridMap::new(width, height),
+            stairs : Vec::new(),
+            branch: level.branch,
+            depth: level.depth,
+            light : vec![RGB::from_f32(0.0, 0.0, 0.0); count],
+            remembered : vec![None; count],
+            seed : seed,
+            frame_count : 0
+        }
+    }
 
-    fn get_pathing_distance(&self, idx1:usize, idx2:usize) -> f32 {
-        let w = self.width as usize;
-        let p1 = Point::new(idx1 % w, idx1 / w);
-        let p2 = Point::new(idx2 % w, idx2 / w);
-        rltk::DistanceAlg::Pythagoras.distance2d(p1, p2)
+    /// Rows of small burial chambers.  Each chamber opens onto the one to its left or the one
+    /// above it, so every chamber can be reached, and a few get a second passage.
+    fn carve_crypt(&mut self, rng: &mut RandomNumberGenerator) {
+        const COLS : i32 = 5;
+        const ROWS : i32 = 5;
+        let mut grid : Vec<Rect> = vec![];
+        for row in 0..ROWS {
+            for col in 0..COLS {
+                let room = Rect::new(2 + col * 9 + rng.range(0, 2), 2 + row * 7 + rng.range(0, 2), 6, 4);
+                self.apply_room_to_map(&room);
+                grid.push(room);
+            }
+        }
+        for row in 0..ROWS {
+            for col in 0..COLS {
+                let (x, y) = grid[(row * COLS + col) as usize].center();
+                let left = if col > 0 { Some(grid[(row * COLS + col - 1) as usize].center()) } else { None };
+                let above = if row > 0 { Some(grid[((row - 1) * COLS + col) as usize].center()) } else { None };
+                let both = rng.roll_dice(1, 4) == 1;
+                let use_left = left.is_some() && (above.is_none() || rng.range(0, 2) == 0);
+                if let Some((lx, ly)) = left {
+                    if use_left || both {
+                        self.apply_horizontal_tunnel(lx, x, ly);
+                        self.apply_vertical_tunnel(ly, y, x);
+                    }
+                }
+                if let Some((ax, ay)) = above {
+                    if !use_left || both {
+                        self.apply_vertical_tunnel(ay, y, ax);
+                        self.apply_horizontal_tunnel(ax, x, y);
+                    }
+                }
+            }
+        }
+        self.rooms = grid;
     }
 
-    fn get_available_exits(&self, idx:usize) -> rltk::SmallVec<[(usize, f32); 10]> {
-        let mut exits = rltk::SmallVec::new();
-        let x = idx as i32 % self.width;
-        let y = idx as i32 / self.width;
-        let w = self.width as usize;
-
-        // Cardinal directions
-        if self.is_exit_valid(x-1, y) { exits.push((idx-1, 1.0)) };
-        if self.is_exit_valid(x+1, y) { exits.push((idx+1, 1.0)) };
-        if self.is_exit_valid(x, y-1) { exits.push((idx-w, 1.0)) };
-        if self.is_exit_valid(x, y+1) { exits.push((idx+w, 1.0)) };
-
-        // Diagonals
-        if self.is_exit_valid(x-1, y-1) { exits.push(((idx-w)-1, 1.45)); }
-        if self.is_exit_valid(x+1, y-1) { exits.push(((idx-w)+1, 1.45)); }
-        if self.is_exit_valid(x-1, y+1) { exits.push(((idx+w)-1, 1.45)); }
-        if self.is_exit_valid(x+1, y+1) { exits.push(((idx+w)+1, 1.45)); }
-
-        exits
+    /// Up stairs in the first room, down stairs in the furthest, and the way into a side branch
+    /// in a room halfway between
+    fn link_stairs(&mut self) {
+        let level = self.level_id();
+        let (up_x, up_y) = self.rooms[0].center();
+        self.add_stairs(STAIRS_UP, up_x, up_y, level.up());
+        if let Some(down) = level.down() {
+            let (down_x, down_y) = self.rooms[self.rooms.len()-1].center();
+            self.add_stairs(STAIRS_DOWN, down_x, down_y, down);
+        }
+        if let Some(branch) = level.branch_entrance() {
+            let (branch_x, branch_y) = self.rooms[self.rooms.len() / 2].center();
+            let first = LevelId { branch: branch, depth: *branch.depths().start() };
+            self.add_stairs(STAIRS_BRANCH, branch_x, branch_y, StairLink { level: first, stairs: STAIRS_UP });
+        }
+    }
+
+    /// Floods the corridors and pools the water out into the rooms around them
+    fn flood(&mut self, rng: &mut RandomNumberGenerator) {
+        let in_room = |map: &Map, x: i32, y: i32| map.rooms.iter().any(|r| x > r.x1 && x <= r.x2 && y > r.y1 && y <= r.y2);
+        for y in 1 .. self.height - 1 {
+            for x in 1 .. self.width - 1 {
+                let idx = self.xy_idx(x, y);
+                if self.tiles[idx] != TileType::Floor { continue; }
+                if !in_room(self, x, y) || rng.roll_dice(1, 6) == 1 {
+                    self.tiles[idx] = TileType::Water;
+                }
+            }
+        }
     }
 }
 
-impl Algorithm2D for Map {
-    fn dimensions(&self) -> Point {
-        Point::new(self.width, self.height)
+impl MapGrid for Map {
+    type Tile = TileType;
+
+    fn grid(&self) -> &GridMap<TileType> { &self.grid }
+    fn grid_mut(&mut self) -> &mut GridMap<TileType> { &mut self.grid }
+
+    fn reveal(&mut self, _visible: &[Point]) {
+        // what the player sees also depends on light, so LightingSystem marks the visible tiles
     }
 }
 
 pub fn draw_map(ecs: &World, ctx : &mut Rltk) {
+    let camera = Camera::new(ecs);
     let mut map = ecs.fetch_mut::<Map>();
     let mut noise = FastNoise::new();
-    let player_ent = ecs.fetch::<Entity>();
-    let positions = ecs.read_storage::<Position>();
-
-    let player_ent_pos = positions.get(*player_ent).unwrap();
-    let player_pos = Point::new(player_ent_pos.x,player_ent_pos.y);
 
     noise.set_seed(map.seed);
 
@@ -213,97 +333,147 @@ pub fn draw_map(ecs: &World, ctx : &mut Rltk) {
 
     let mut y = 0;
     let mut x = 0;
-    for (idx,tile) in map.tiles.iter().enumerate() {
-        // Render a tile depending upon the tile type
-
+    for idx in 0..map.tiles.len() {
         if map.revealed_tiles[idx] {
-            let glyph;
-            let mut fg;
-            let mut bg;
-
-            let distance = rltk::DistanceAlg::Pythagoras.distance2d(Point::new(x, y), player_pos);
-            let dist_factor = 1.0 - ((distance - 3.0).max(0.0)/ 9.0) - (noise.get_noise3d(0.08 * x as f32, 0.08 * y as f32, 0.14 * map.frame_count as f32) * 0.1);
-            // console::log(format!("{},{} distance: {}, dist_factor: {}", x,y, distance, dist_factor));            
-
-            match tile {
-                TileType::Floor => {
-                    glyph = rltk::to_cp437('.');
-                    fg = RGB::from_f32(
-                        0.25 * dist_factor, 
-                        0.2 * dist_factor, 
-                        0.15 * dist_factor
-                    );
-                    // fg = RGB::from_f32(0.25, 0.2, 0.15);
-
-                    bg = RGB::from_f32(
-                        dist_factor * 0.1,
-                        dist_factor * 0.07,
-                        dist_factor * 0.05);
-                }
-                TileType::Wall => {
-                    glyph = rltk::to_cp437('#');
-                    // fg = RGB::from_f32(
-                    //     dist_factor * (0.4 + 0.04 * (noise.get_noise(1000.0 + x as f32, 800.0 + y as f32))), 
-                    //     dist_factor * (0.2 + 0.08 * (noise.get_noise(500.0 + x as f32, 700.0 + y as f32))),
-                    //     dist_factor * (0.0 + 0.05 * (noise.get_noise(300.0 + x as f32, 600.0 + y as f32)))
-                    // );
-                    fg = RGB::from_f32(
-                        dist_factor * (0.5 + 0.08 * (noise.get_noise(500.0 + x as f32, 800.0 + y as f32)) ), 
-                        dist_factor * (0.5 + 0.08 * (noise.get_noise(500.0 + x as f32, 800.0 + y as f32)) ),
-                        dist_factor * (0.5 + 0.08 * (noise.get_noise(500.0 + x as f32, 800.0 + y as f32)) )
-                    );
-
-                    bg = RGB::from_f32(0.15,0.1,0.0);
-                }
-                TileType::StairsUp => {
-                    glyph = rltk::to_cp437('<');
-                    fg = RGB::from_f32(0.65,0.65,0.65);
-                    bg = RGB::from_f32(0.15,0.1,0.0);
-                }
-                TileType::StairsDown => {
-                    glyph = rltk::to_cp437('>');
-                    fg = RGB::from_f32(0.65,0.65,0.65);
-                    bg = RGB::from_f32(0.15,0.1,0.0);
-                }
+            let (glyph, fg, bg) = tile_glyph(&map, idx, &noise, map.visible_tiles[idx], map.light[idx]);
+            if let Some((sx, sy)) = camera.to_screen(x, y) {
+                ctx.set(sx, sy, fg, bg, glyph);
             }
-            if !map.visible_tiles[idx] {
-                 match tile { 
-                    TileType::Floor => {
-                        fg = RGB::named(rltk::BLACK);
-                        bg = RGB::named(rltk::BLACK);
-                    }
-                    TileType::Wall => {
-                        // fg = RGB::from_f32(
-                        //     0.18 + 0.02 * (noise.get_noise(1000.0 + x as f32, 800.0 + y as f32)), 
-                        //     0.1 + 0.03 * (noise.get_noise(500.0 + x as f32, 700.0 + y as f32)),
-                        //     0.0 + 0.02 * (noise.get_noise(300.0 + x as f32, 600.0 + y as f32))
-                        // );                        
-                        fg = RGB::from_f32(
-                            0.25 + 0.05 * (noise.get_noise(500.0 + x as f32, 800.0 + y as f32)), 
-                            0.25 + 0.05 * (noise.get_noise(500.0 + x as f32, 800.0 + y as f32)),
-                            0.25 + 0.05 * (noise.get_noise(500.0 + x as f32, 800.0 + y as f32))
-                        );
-                        bg = RGB::named(rltk::BLACK);
-                    }
-                    TileType::StairsUp => {
-                        fg = RGB::from_f32(0.4,0.4,0.4);
-                        bg = RGB::named(rltk::BLACK);
-                    }
-                    TileType::StairsDown => {
-                        fg = RGB::from_f32(0.4,0.4,0.4);
-                        bg = RGB::named(rltk::BLACK);
-                    }
-    
-                }
-            }
-            ctx.set(x, y, fg, bg, glyph);
         }
 
         // Move the coordinates
         x += 1;
-        if x > MAPWIDTH as i32-1 {
+        if x > map.width-1 {
             x = 0;
             y += 1;
         }
     }
 }
+
+/// Glyph and colours for a revealed tile under the given light, or greyed out as a memory
+/// when it is not in view.  Shared by the map view and the level overview.
+pub fn tile_glyph(map: &Map, idx: usize, noise: &FastNoise, in_view: bool, light: RGB) -> (rltk::FontCharType, RGB, RGB) {
+    let x = idx as i32 % map.width;
+    let y = idx as i32 / map.width;
+    let tile = &map.tiles[idx];
+    let glyph;
+    let mut fg;
+    let mut bg;
+
+    // brightness comes from the light map, with a little flicker on top
+    let light_level = brightness(light);
+    let dist_factor = light_level - (noise.get_noise3d(0.08 * x as f32, 0.08 * y as f32, 0.14 * map.frame_count as f32) * 0.1 * light_level);
+    let tint = if light_level > 0.0 { RGB::from_f32(light.r / light_level, light.g / light_level, light.b / light_level) } else { RGB::from_f32(1.0, 1.0, 1.0) };
+
+    match tile {
+        TileType::Floor | TileType::Trap { hidden: true, .. } => {
+            glyph = rltk::to_cp437('.');
+            fg = RGB::from_f32(
+                0.25 * dist_factor, 
+                0.2 * dist_factor, 
+                0.15 * dist_factor
+            );
+            // fg = RGB::from_f32(0.25, 0.2, 0.15);
+
+            bg = RGB::from_f32(
+                dist_factor * 0.1,
+                dist_factor * 0.07,
+                dist_factor * 0.05);
+        }
+        TileType::Wall => {
+            glyph = rltk::to_cp437('#');
+            // fg = RGB::from_f32(
+            //     dist_factor * (0.4 + 0.04 * (noise.get_noise(1000.0 + x as f32, 800.0 + y as f32))), 
+            //     dist_factor * (0.2 + 0.08 * (noise.get_noise(500.0 + x as f32, 700.0 + y as f32))),
+            //     dist_factor * (0.0 + 0.05 * (noise.get_noise(300.0 + x as f32, 600.0 + y as f32)))
+            // );
+            fg = RGB::from_f32(
+                dist_factor * (0.5 + 0.08 * (noise.get_noise(500.0 + x as f32, 800.0 + y as f32)) ), 
+                dist_factor * (0.5 + 0.08 * (noise.get_noise(500.0 + x as f32, 800.0 + y as f32)) ),
+                dist_factor * (0.5 + 0.08 * (noise.get_noise(500.0 + x as f32, 800.0 + y as f32)) )
+            );
+
+            bg = RGB::from_f32(0.15,0.1,0.0);
+        }
+        TileType::StairsUp => {
+            glyph = rltk::to_cp437('<');
+            fg = RGB::from_f32(0.65,0.65,0.65);
+            bg = RGB::from_f32(0.15,0.1,0.0);
+        }
+        TileType::StairsDown => {
+            glyph = rltk::to_cp437('>');
+            fg = RGB::from_f32(0.65,0.65,0.65);
+            bg = RGB::from_f32(0.15,0.1,0.0);
+        }
+        TileType::DoorClosed => {
+            glyph = rltk::to_cp437('+');
+            fg = RGB::from_f32(0.6 * dist_factor, 0.4 * dist_factor, 0.2 * dist_factor);
+            bg = RGB::from_f32(0.15,0.1,0.0);
+        }
+        TileType::DoorOpen => {
+            glyph = rltk::to_cp437('\'');
+            fg = RGB::from_f32(0.6 * dist_factor, 0.4 * dist_factor, 0.2 * dist_factor);
+            bg = RGB::from_f32(dist_factor * 0.1, dist_factor * 0.07, dist_factor * 0.05);
+        }
+        TileType::Trap { .. } => {
+            glyph = rltk::to_cp437('^');
+            fg = RGB::from_f32(0.8 * dist_factor, 0.2 * dist_factor, 0.1 * dist_factor);
+            bg = RGB::from_f32(dist_factor * 0.1, dist_factor * 0.07, dist_factor * 0.05);
+        }
+        TileType::Rubble => {
+            glyph = rltk::to_cp437('%');
+            fg = RGB::from_f32(0.45 * dist_factor, 0.4 * dist_factor, 0.35 * dist_factor);
+            bg = RGB::from_f32(dist_factor * 0.1, dist_factor * 0.07, dist_factor * 0.05);
+        }
+        TileType::Sarcophagus => {
+            glyph = rltk::to_cp437('∩');
+            fg = RGB::from_f32(0.7 * dist_factor, 0.7 * dist_factor, 0.6 * dist_factor);
+            bg = RGB::from_f32(dist_factor * 0.1, dist_factor * 0.07, dist_factor * 0.05);
+        }
+        TileType::SarcophagusOpen => {
+            glyph = rltk::to_cp437('_');
+            fg = RGB::from_f32(0.5 * dist_factor, 0.5 * dist_factor, 0.45 * dist_factor);
+            bg = RGB::from_f32(dist_factor * 0.1, dist_factor * 0.07, dist_factor * 0.05);
+        }
+        TileType::Water => {
+            glyph = rltk::to_cp437('~');
+            fg = RGB::from_f32(0.2 * dist_factor, 0.35 * dist_factor, 0.6 * dist_factor);
+            bg = RGB::from_f32(dist_factor * 0.03, dist_factor * 0.06, dist_factor * 0.12);
+        }
+    }
+    fg = fg * tint;
+    if !in_view {
+         match tile { 
+            TileType::Floor | TileType::Trap { hidden: true, .. } => {
+                fg = RGB::named(rltk::BLACK);
+                bg = RGB::named(rltk::BLACK);
+            }
+            TileType::Wall => {
+                // fg = RGB::from_f32(
+                //     0.18 + 0.02 * (noise.get_noise(1000.0 + x as f32, 800.0 + y as f32)), 
+                //     0.1 + 0.03 * (noise.get_noise(500.0 + x as f32, 700.0 + y as f32)),
+                //     0.0 + 0.02 * (noise.get_noise(300.0 + x as f32, 600.0 + y as f32))
+                // );                        
+                fg = RGB::from_f32(
+                    0.25 + 0.05 * (noise.get_noise(500.0 + x as f32, 800.0 + y as f32)), 
+                    0.25 + 0.05 * (noise.get_noise(500.0 + x as f32, 800.0 + y as f32)),
+                    0.25 + 0.05 * (noise.get_noise(500.0 + x as f32, 800.0 + y as f32))
+                );
+                bg = RGB::named(rltk::BLACK);
+            }
+            TileType::StairsUp => {
+                fg = RGB::from_f32(0.4,0.4,0.4);
+                bg = RGB::named(rltk::BLACK);
+            }
+            TileType::StairsDown => {
+                fg = RGB::from_f32(0.4,0.4,0.4);
+                bg = RGB::named(rltk::BLACK);
+            }
+            _ => {
+                fg = RGB::from_f32(0.3,0.3,0.3);
+                bg = RGB::named(rltk::BLACK);
+            }
+        }
+    }
+    (glyph, fg, bg)
+}
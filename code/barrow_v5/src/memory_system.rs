@@ -0,0 +1,49 @@
+use specs::prelude::*;
+use rltk::RGB;
+use super::{Map, Position, Renderable, Item, Container, Monster};
+
+/// The last thing the player saw on a tile that is now out of view
+#[derive(Clone, Copy, Debug)]
+pub struct RememberedGlyph {
+    pub glyph: rltk::FontCharType,
+    pub fg: RGB,
+    pub render_order: i32
+}
+
+/// Keeps the player's memory of items, containers and monsters up to date.  Every tile in view
+/// is forgotten and then refilled with whatever is standing on it now, so memories of things
+/// that have moved or been taken are cleared as soon as the player looks again.
+pub struct MemorySystem {}
+
+impl<'a> System<'a> for MemorySystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = ( WriteExpect<'a, Map>,
+                        ReadStorage<'a, Position>,
+                        ReadStorage<'a, Renderable>,
+                        ReadStorage<'a, Item>,
+                        ReadStorage<'a, Container>,
+                        ReadStorage<'a, Monster>);
+
+    fn run(&mut self, data : Self::SystemData) {
+        let (mut map, positions, renderables, items, containers, monsters) = data;
+
+        for idx in 0..map.tiles.len() {
+            if map.visible_tiles[idx] { map.remembered[idx] = None; }
+        }
+
+        let remembered = (&positions, &renderables, (&items).maybe(), (&containers).maybe(), (&monsters).maybe()).join()
+            .filter(|(_, _, item, container, monster)| item.is_some() || container.is_some() || monster.is_some())
+            .map(|(pos, render, _, _, _)| (map.xy_idx(pos.x, pos.y), render))
+            .filter(|(idx, _)| map.visible_tiles[*idx])
+            .collect::<Vec<_>>();
+        for (idx, render) in remembered.iter() {
+            let replace = match map.remembered[*idx] {
+                Some(memory) => render.render_order > memory.render_order,
+                None => true
+            };
+            if replace {
+                map.remembered[*idx] = Some(RememberedGlyph { glyph: render.glyph, fg: render.fg, render_order: render.render_order });
+            }
+        }
+    }
+}
@@ -1,5 +1,5 @@
 use specs::prelude::*;
-use super::{Viewshed, Monster, Map, Position, Action, CombatStats, RunState, SmartMonster};
+use super::{Viewshed, Monster, Map, Position, Action, CombatStats, RunState, SmartMonster, Morale, morale};
 use super::Command::*;
 // use super::AttackMove::*;
 use super::WaitMove::*;
@@ -20,16 +20,18 @@ impl<'a> System<'a> for MonsterAI {
                         WriteStorage<'a, Action>,
                         WriteStorage<'a, CombatStats>,
                         WriteStorage<'a, SmartMonster>,
+                        ReadStorage<'a, Morale>,
                         WriteExpect<'a, rltk::RandomNumberGenerator>
                     );
 
     fn run(&mut self, data : Self::SystemData) {
-        let (mut map, player_entity, runstate, entities, viewsheds, monster, position, mut actions, mut combat_stats, mut smart_monsters, mut rng) = data;
+        let (mut map, player_entity, runstate, entities, viewsheds, monster, position, mut actions, mut combat_stats, mut smart_monsters, morales, mut rng) = data;
 
         if *runstate != RunState::MonsterTurn { return; }
 
         let player_ent_pos = position.get(*player_entity).unwrap();
         let player_pos = Point::new(player_ent_pos.x, player_ent_pos.y);
+        let mut flee_map = None;
     
         for (entity, viewshed, _monster, pos, mut stats, mut smart_monster) in (&entities, &viewsheds, &monster, &position, &mut combat_stats, &mut smart_monsters).join() {
             stats.visible_targets.clear();                    
@@ -49,6 +51,22 @@ impl<'a> System<'a> for MonsterAI {
     
             // TODO: handle Stun properly
             let distance = rltk::DistanceAlg::Pythagoras.distance2d(Point::new(pos.x, pos.y), player_pos);
+
+            if morales.get(entity).map_or(false, |m| m.fleeing) {
+                let flee = flee_map.get_or_insert_with(|| morale::flee_map(&*map, player_pos));
+                let action = match morale::flee_step(flee, &*map, pos) {
+                    Some(step) => {
+                        let idx = map.xy_idx(pos.x, pos.y);
+                        map.blocked[idx] = false;
+                        Action { command: MoveCommand, cost: 0, stance_after: Ready, target: None, position: Some(step) }
+                    }
+                    // cornered, it lashes out
+                    None if distance < 1.5 => Action { command: AttackCommand(smart_monster.primary_attack), cost: smart_monster.primary_attack_cost, stance_after: smart_monster.primary_stance, target: Some(*player_entity), position: None },
+                    None => Action { command: WaitCommand(Wait), cost: -10, stance_after: Ready, target: None, position: None }
+                };
+                actions.insert(entity, action).expect("Unable to insert flee");
+                continue;
+            }
             if distance < 1.5 {
                 if stats.ep >= smart_monster.recover_ep_threshold {
                     actions.insert(entity, Action{ 
@@ -91,7 +109,7 @@ impl<'a> System<'a> for MonsterAI {
                 let path = rltk::a_star_search(
                     map.xy_idx(pos.x, pos.y),
                     map.xy_idx(player_pos.x, player_pos.y),
-                    &mut *map
+                    &map.grid
                 );
                 if path.success && path.steps.len()>1 {
                     let idx = map.xy_idx(pos.x, pos.y);
@@ -120,7 +138,7 @@ impl<'a> System<'a> for MonsterAI {
                 let path = rltk::a_star_search(
                     map.xy_idx(pos.x, pos.y),
                     map.xy_idx(targ_loc.x, targ_loc.y),
-                    &mut *map
+                    &map.grid
                 );
                 let dice_roll = rng.range(0.0,1.0);
                 if path.success && path.steps.len()>1 && dice_roll < smart_monster.invisible_chase_chance {
@@ -0,0 +1,173 @@
+use specs::prelude::*;
+use rltk::{Point, DijkstraMap};
+use super::{Morale, Inventory, Item, CombatStats, Monster, Player, Name, Position, Viewshed, Map, EventQueue, GameEvent, spawner};
+
+/// Courage of monsters that never break: the undead, and anything bound to the barrow's master
+pub const FEARLESS : i32 = 100;
+
+/// A single hit taking this share of a monster's max hp shakes it
+const HEAVY_HIT_PERCENT : i32 = 20;
+/// Allies in sight that it takes for a frightened monster to find its nerve again
+const PACK_SIZE : usize = 2;
+/// How far the flee map looks; beyond this a monster is as far away as it needs to be
+const FLEE_DEPTH : f32 = 20.0;
+
+/// Goes over the hits and deaths of the pass that has just been resolved.  Monsters lose morale when
+/// they are badly hurt, when they watch an ally die and while they face the Amulet's bearer, and
+/// win it back once they are healed or have a pack around them.  A monster whose morale runs
+/// out flees, and may drop what it is carrying as it goes.
+pub fn update_morale(ecs: &mut World) {
+    let events : Vec<GameEvent> = ecs.fetch::<EventQueue>().events.clone();
+    let mut dropped : Vec<(Position, Vec<Item>)> = vec![];
+    {
+        let player_entity = *ecs.fetch::<Entity>();
+        let entities = ecs.entities();
+        let mut morales = ecs.write_storage::<Morale>();
+        let combat_stats = ecs.read_storage::<CombatStats>();
+        let positions = ecs.read_storage::<Position>();
+        let viewsheds = ecs.read_storage::<Viewshed>();
+        let monsters = ecs.read_storage::<Monster>();
+        let players = ecs.read_storage::<Player>();
+        let names = ecs.read_storage::<Name>();
+        let mut inventories = ecs.write_storage::<Inventory>();
+        let mut queue = ecs.write_resource::<EventQueue>();
+        let mut rng = ecs.write_resource::<rltk::RandomNumberGenerator>();
+
+        let player_pos = match positions.get(player_entity) {
+            Some(pos) => Point::new(pos.x, pos.y),
+            None => return
+        };
+        let amulet = players.get(player_entity).map_or(false, |p| p.has_amulet);
+        // monsters killed this pass, which are deleted once the pass is over
+        let fallen : Vec<(Entity, Point)> = (&entities, &monsters, &positions, &combat_stats).join()
+            .filter(|(_, _, _, stats)| stats.hp <= 0)
+            .map(|(e, _, pos, _)| (e, Point::new(pos.x, pos.y)))
+            .collect();
+        // the monsters still willing to fight, which make a pack for any frightened ones nearby
+        let steady : Vec<(Entity, Point)> = (&entities, &monsters, &positions, &combat_stats, (&morales).maybe()).join()
+            .filter(|(_, _, _, stats, morale)| stats.hp > 0 && morale.map_or(true, |m| !m.fleeing))
+            .map(|(e, _, pos, _, _)| (e, Point::new(pos.x, pos.y)))
+            .collect();
+
+        for (entity, morale, stats, pos, viewshed, name) in (&entities, &mut morales, &combat_stats, &positions, &viewsheds, &names).join() {
+            if morale.courage >= FEARLESS || stats.hp <= 0 { continue; }
+
+            for event in events.iter() {
+                match event {
+                    GameEvent::Attacked { target, hp_damage, .. } if *target == entity && hp_damage * 100 >= HEAVY_HIT_PERCENT * stats.max_hp => {
+                        morale.morale -= 2;
+                    }
+                    _ => {}
+                }
+            }
+            let deaths_seen = fallen.iter().filter(|(e, p)| *e != entity && viewshed.visible_tiles.contains(p)).count() as i32;
+            morale.morale -= 3 * deaths_seen;
+
+            let sees_player = viewshed.visible_tiles.contains(&player_pos);
+            if amulet && sees_player {
+                morale.morale -= 1;
+            }
+            if stats.hp * 4 >= stats.max_hp * 3 && !sees_player {
+                morale.morale += 1;
+            }
+            let pack = steady.iter().filter(|(e, p)| *e != entity && viewshed.visible_tiles.contains(p)).count();
+            if pack >= PACK_SIZE {
+                morale.morale += 1;
+            }
+            morale.morale = i32::max(-morale.courage, i32::min(morale.courage, morale.morale));
+
+            if !morale.fleeing && morale.morale <= 0 {
+                morale.fleeing = true;
+                queue.emit(GameEvent::Fled { entity: entity, name: name.name.clone() });
+                if let Some(inventory) = inventories.get_mut(entity) {
+                    if !inventory.items.is_empty() && rng.roll_dice(1, 2) == 1 {
+                        queue.emit(GameEvent::Dropped { name: name.name.clone() });
+                        dropped.push((*pos, inventory.items.drain(..).collect()));
+                    }
+                }
+            } else if morale.fleeing && morale.morale * 2 >= morale.courage {
+                morale.fleeing = false;
+                queue.emit(GameEvent::Rallied { entity: entity, name: name.name.clone() });
+            }
+        }
+    }
+
+    for (pos, items) in dropped {
+        for item in items.iter() {
+            spawner::drop_item(ecs, (pos.x, pos.y), item);
+        }
+    }
+}
+
+/// Distances from the player, for fleeing monsters to climb away from
+pub fn flee_map(map: &Map, player_pos: Point) -> DijkstraMap {
+    DijkstraMap::new(map.width, map.height, &[map.xy_idx(player_pos.x, player_pos.y)], &map.grid, FLEE_DEPTH)
+}
+
+/// The step that takes a fleeing monster further from the player, or None if it is cornered
+pub fn flee_step(flee: &DijkstraMap, map: &Map, pos: &Position) -> Option<Position> {
+    let idx = map.xy_idx(pos.x, pos.y);
+    DijkstraMap::find_highest_exit(flee, idx, &map.grid)
+        .filter(|exit| flee.map[*exit] > flee.map[idx])
+        .map(|exit| Position { x: exit as i32 % map.width, y: exit as i32 / map.width })
+}
+
+/// Whether a monster's nerve has broken
+pub fn is_fleeing(ecs: &World, entity: Entity) -> bool {
+    ecs.read_storage::<Morale>().get(entity).map_or(false, |m| m.fleeing)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Renderable, AttackMove, Items::Coin, ruleset::tests::{arena, Arena}};
+
+    /// A monster with shaky nerve that has just taken a heavy hit from the player
+    fn shaken(carrying: Option<Vec<Item>>) -> Arena {
+        let mut arena = arena(false);
+        let (player, monster) = (arena.player, arena.monster);
+        arena.world.register::<Morale>();
+        arena.world.register::<Renderable>();
+        arena.world.write_storage::<Morale>().insert(monster, Morale { courage: 4, morale: 1, fleeing: false }).expect("Unable to insert morale");
+        arena.world.write_storage::<Viewshed>().insert(monster, Viewshed { visible_tiles: vec![], range: 8, dirty: false }).expect("Unable to insert viewshed");
+        if let Some(items) = carrying {
+            arena.world.write_storage::<Inventory>().insert(monster, Inventory { items: items, picks_up: true }).expect("Unable to insert inventory");
+        }
+        arena.world.write_resource::<EventQueue>().emit(GameEvent::Attacked {
+            attacker: player, attacker_name: String::from("Player"),
+            target: monster, target_name: String::from("Kobold"), target_pos: Position { x: 11, y: 10 },
+            attack: AttackMove::Smash, hp_damage: 10, ep_damage: 0
+        });
+        update_morale(&mut arena.world);
+        arena.world.maintain();
+        assert!(arena.world.read_storage::<Morale>().get(monster).unwrap().fleeing);
+        arena
+    }
+
+    fn coins_on_the_ground(arena: &Arena) -> Vec<(i32, u64)> {
+        (&arena.world.read_storage::<Item>(), &arena.world.read_storage::<Position>()).join()
+            .filter_map(|(item, _)| match item.item { Coin(amount) => Some((amount, item.tag)), _ => None })
+            .collect()
+    }
+
+    #[test]
+    fn a_fleeing_monster_only_drops_what_it_carries() {
+        let arena = shaken(Some(vec![Item { item: Coin(7), tag: 42 }]));
+        let carried : Vec<(i32, u64)> = arena.world.read_storage::<Inventory>().get(arena.monster).unwrap().items.iter()
+            .filter_map(|item| match item.item { Coin(amount) => Some((amount, item.tag)), _ => None })
+            .collect();
+        // whether it lets go is a coin toss, but the purse is never copied
+        let mut everywhere = coins_on_the_ground(&arena);
+        everywhere.extend(carried);
+        assert_eq!(everywhere, vec![(7, 42)]);
+    }
+
+    #[test]
+    fn a_fleeing_monster_with_nothing_to_drop_drops_nothing() {
+        for carrying in [None, Some(vec![])].iter() {
+            let arena = shaken(carrying.clone());
+            assert!(coins_on_the_ground(&arena).is_empty());
+            assert!(!arena.events().iter().any(|e| matches!(e, GameEvent::Dropped { .. })));
+        }
+    }
+}
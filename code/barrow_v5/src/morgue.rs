@@ -0,0 +1,113 @@
+use super::{RunStats, RunSettings};
+
+/// The morgue file lives next to the executable; the web build keeps the same lines in localStorage
+const MORGUE_FILE : &str = "barrow_morgue.txt";
+
+/// One finished run, as listed in the Hall of the Fallen
+#[derive(PartialEq, Clone, Debug)]
+pub struct RunRecord {
+    pub victory: bool,
+    pub depth: i32,
+    pub turns: i32,
+    pub kills: i32,
+    pub coins: i32,
+    pub food: i32,
+    pub seed: u64,
+    pub difficulty: String,
+    pub cause: String,
+    pub kill_summary: String
+}
+
+impl RunRecord {
+    pub fn from_run(stats: &RunStats, settings: &RunSettings) -> RunRecord {
+        RunRecord {
+            victory: stats.victory,
+            depth: stats.deepest_depth,
+            turns: stats.turns,
+            kills: stats.total_kills(),
+            coins: stats.coins_earned,
+            food: stats.food_eaten,
+            seed: stats.seed,
+            difficulty: settings.describe(),
+            cause: stats.cause_of_death.clone().unwrap_or(String::from("Unknown")),
+            kill_summary: stats.kill_summary()
+        }
+    }
+
+    pub fn score(&self) -> i32 {
+        let victory_bonus = if self.victory { 1000 } else { 0 };
+        victory_bonus + self.depth * 100 + self.kills * 10 + self.coins
+    }
+
+    fn to_line(&self) -> String {
+        format!("{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            self.victory, self.depth, self.turns, self.kills, self.coins, self.food, self.seed,
+            self.difficulty.replace('\t', " "), self.cause.replace('\t', " "), self.kill_summary.replace('\t', " "))
+    }
+
+    fn from_line(line: &str) -> Option<RunRecord> {
+        let fields : Vec<&str> = line.split('\t').collect();
+        if fields.len() != 10 { return None; }
+        Some(RunRecord {
+            victory: fields[0].parse().ok()?,
+            depth: fields[1].parse().ok()?,
+            turns: fields[2].parse().ok()?,
+            kills: fields[3].parse().ok()?,
+            coins: fields[4].parse().ok()?,
+            food: fields[5].parse().ok()?,
+            seed: fields[6].parse().ok()?,
+            difficulty: fields[7].to_string(),
+            cause: fields[8].to_string(),
+            kill_summary: fields[9].to_string()
+        })
+    }
+}
+
+/// Sorts records best first
+pub fn rank(records: &mut Vec<RunRecord>) {
+    records.sort_by(|a, b| b.score().cmp(&a.score()));
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn load_records() -> Vec<RunRecord> {
+    let mut records : Vec<RunRecord> = match std::fs::read_to_string(MORGUE_FILE) {
+        Ok(contents) => contents.lines().filter_map(RunRecord::from_line).collect(),
+        Err(_) => vec![]
+    };
+    rank(&mut records);
+    records
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn load_records() -> Vec<RunRecord> {
+    let mut records : Vec<RunRecord> = match super::web_storage::read(MORGUE_FILE) {
+        Some(contents) => contents.lines().filter_map(RunRecord::from_line).collect(),
+        None => vec![]
+    };
+    rank(&mut records);
+    records
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn save_record(record: &RunRecord) {
+    use std::io::Write;
+    let file = std::fs::OpenOptions::new().create(true).append(true).open(MORGUE_FILE);
+    match file {
+        Ok(mut f) => {
+            if writeln!(f, "{}", record.to_line()).is_err() {
+                rltk::console::log("unable to write morgue file");
+            }
+        }
+        Err(_) => rltk::console::log("unable to open morgue file")
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn save_record(record: &RunRecord) {
+    let mut contents = super::web_storage::read(MORGUE_FILE).unwrap_or_default();
+    contents.push_str(&record.to_line());
+    contents.push('\n');
+    if !super::web_storage::write(MORGUE_FILE, &contents) {
+        rltk::console::log("unable to save the morgue to local storage");
+    }
+}
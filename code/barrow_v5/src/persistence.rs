@@ -0,0 +1,98 @@
+use specs::prelude::*;
+use super::{Position, Renderable, Name, Monster, Item, Container, CombatStats, SmartMonster, Boss, Morale, Inventory, Corpse, Viewshed, BlocksTile, LightSource, Player};
+
+/// Everything needed to rebuild one entity when its level is loaded again.  References to
+/// other entities (targets) are dropped, since those entities are rebuilt with new ids.
+#[derive(Clone)]
+pub struct EntitySnapshot {
+    pub position: Position,
+    pub renderable: Option<Renderable>,
+    pub name: Option<Name>,
+    pub monster: Option<Monster>,
+    pub item: Option<Item>,
+    pub container: Option<Container>,
+    pub combat_stats: Option<CombatStats>,
+    pub smart_monster: Option<SmartMonster>,
+    pub boss: Option<Boss>,
+    pub morale: Option<Morale>,
+    pub inventory: Option<Inventory>,
+    pub corpse: Option<Corpse>,
+    pub viewshed_range: Option<i32>,
+    pub light: Option<LightSource>,
+    pub blocks_tile: bool
+}
+
+/// Captures every entity on the current level except the player.  The followers are returned
+/// separately, so they can be rebuilt on the level the player is heading to instead.
+pub fn snapshot_level(ecs: &World, followers: &[Entity]) -> (Vec<EntitySnapshot>, Vec<EntitySnapshot>) {
+    let entities = ecs.entities();
+    let positions = ecs.read_storage::<Position>();
+    let players = ecs.read_storage::<Player>();
+    let renderables = ecs.read_storage::<Renderable>();
+    let names = ecs.read_storage::<Name>();
+    let monsters = ecs.read_storage::<Monster>();
+    let items = ecs.read_storage::<Item>();
+    let containers = ecs.read_storage::<Container>();
+    let combat_stats = ecs.read_storage::<CombatStats>();
+    let smart_monsters = ecs.read_storage::<SmartMonster>();
+    let bosses = ecs.read_storage::<Boss>();
+    let morales = ecs.read_storage::<Morale>();
+    let inventories = ecs.read_storage::<Inventory>();
+    let corpses = ecs.read_storage::<Corpse>();
+    let viewsheds = ecs.read_storage::<Viewshed>();
+    let blocks = ecs.read_storage::<BlocksTile>();
+    let lights = ecs.read_storage::<LightSource>();
+
+    let mut snapshot = vec![];
+    let mut following = vec![];
+    for (entity, position, _player) in (&entities, &positions, !&players).join() {
+        let saved = if followers.contains(&entity) { &mut following } else { &mut snapshot };
+        saved.push(EntitySnapshot {
+            position: *position,
+            renderable: renderables.get(entity).cloned(),
+            name: names.get(entity).cloned(),
+            monster: monsters.get(entity).cloned(),
+            item: items.get(entity).cloned(),
+            container: containers.get(entity).cloned(),
+            combat_stats: combat_stats.get(entity).map(|stats| {
+                let mut stats = stats.clone();
+                stats.current_target = None;
+                stats.visible_targets.clear();
+                stats
+            }),
+            smart_monster: smart_monsters.get(entity).cloned(),
+            boss: bosses.get(entity).cloned(),
+            morale: morales.get(entity).cloned(),
+            inventory: inventories.get(entity).cloned(),
+            corpse: corpses.get(entity).cloned(),
+            viewshed_range: viewsheds.get(entity).map(|v| v.range),
+            light: lights.get(entity).cloned(),
+            blocks_tile: blocks.get(entity).is_some()
+        });
+    }
+    (snapshot, following)
+}
+
+/// Recreates the entities of a level exactly as they were when it was unloaded
+pub fn restore_level(ecs: &mut World, snapshot: &[EntitySnapshot]) {
+    for saved in snapshot.iter() {
+        let mut builder = ecs.create_entity().with(saved.position);
+        if let Some(renderable) = &saved.renderable { builder = builder.with(renderable.clone()); }
+        if let Some(name) = &saved.name { builder = builder.with(name.clone()); }
+        if let Some(monster) = &saved.monster { builder = builder.with(monster.clone()); }
+        if let Some(item) = &saved.item { builder = builder.with(item.clone()); }
+        if let Some(container) = &saved.container { builder = builder.with(container.clone()); }
+        if let Some(stats) = &saved.combat_stats { builder = builder.with(stats.clone()); }
+        if let Some(smart_monster) = &saved.smart_monster { builder = builder.with(smart_monster.clone()); }
+        if let Some(boss) = &saved.boss { builder = builder.with(boss.clone()); }
+        if let Some(morale) = &saved.morale { builder = builder.with(morale.clone()); }
+        if let Some(inventory) = &saved.inventory { builder = builder.with(inventory.clone()); }
+        if let Some(corpse) = &saved.corpse { builder = builder.with(corpse.clone()); }
+        if let Some(range) = saved.viewshed_range {
+            builder = builder.with(Viewshed{ visible_tiles: Vec::new(), range: range, dirty: true });
+        }
+        if let Some(light) = &saved.light { builder = builder.with(light.clone()); }
+        if saved.blocks_tile { builder = builder.with(BlocksTile{}); }
+        builder.build();
+    }
+}
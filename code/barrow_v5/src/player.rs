@@ -1,13 +1,44 @@
-use rltk::{VirtualKeyCode, Rltk, Point, console};
+use rltk::{Rltk, Point, console};
 use specs::prelude::*;
 use std::cmp::{max, min};
-use super::{Position, Player, State, CombatStats, GameLog, Map, Monster, RunState, Action, MenuCommand, Command, TileType };
+use super::{features, boss, morale, lighting_system::TORCH_FUEL_LOW, Position, Player, State, CombatStats, GameLog, Map, Monster, Container, Item, RunState, Action, Command, TileType, EventQueue, GameEvent, gamelog::LogCategory, Keymap, InputAction, gui::PanelLayout, camera::Camera, RunSettings, Branch, LevelId };
 use super::Command::*;
 use super::AttackMove::*;
-use super::WaitMove::*;
 use super::CombatStance::*;
 
+/// Steps queued by clicking on the map, travelling to stairs or auto-exploring.  One step is
+/// taken per turn until the path runs out, a key is pressed, something is picked up, or a
+/// monster comes into view that was not visible when the walk began.
+pub struct TravelPath {
+    pub steps: Vec<usize>,
+    pub monsters_seen: usize,
+    pub exploring: bool
+}
+
+impl TravelPath {
+    pub fn new() -> TravelPath {
+        TravelPath { steps: vec![], monsters_seen: 0, exploring: false }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.exploring || !self.steps.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.steps.clear();
+        self.exploring = false;
+    }
+}
+
+impl Default for TravelPath {
+    fn default() -> TravelPath {
+        TravelPath::new()
+    }
+}
+
 pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
+    if features::try_interact(delta_x, delta_y, ecs) { return; }
+
     let mut positions = ecs.write_storage::<Position>();
     let players = ecs.read_storage::<Player>();
     let entities = ecs.entities();
@@ -33,8 +64,8 @@ pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
         }
 
         if !map.blocked[destination_idx] {
-            let new_x = min(79 , max(0, pos.x + delta_x));
-            let new_y = min(49, max(0, pos.y + delta_y));
+            let new_x = min(map.width-1, max(0, pos.x + delta_x));
+            let new_y = min(map.height-1, max(0, pos.y + delta_y));
             actions.insert(entity, Action{ 
                 command: MoveCommand,
                 cost: -2,
@@ -79,30 +110,54 @@ pub fn update_targeting(ecs: &World, _ctx: &mut Rltk) {
     return; 
 }
 
+/// Each turn in the barrow burns a little torch fuel.  Warns once when the torch starts to
+/// sputter and again when it gutters out to embers.
+pub fn burn_torch(ecs: &World) {
+    let player_entity = ecs.fetch::<Entity>();
+    let mut players = ecs.write_storage::<Player>();
+    let mut events = ecs.write_resource::<EventQueue>();
+
+    if let Some(player) = players.get_mut(*player_entity) {
+        if player.torch_fuel <= 0 { return; }
+        player.torch_fuel -= 1;
+        if player.torch_fuel == TORCH_FUEL_LOW {
+            events.emit(GameEvent::TorchSputtering { fuel: player.torch_fuel });
+        } else if player.torch_fuel == 0 {
+            events.emit(GameEvent::TorchOut);
+        }
+    }
+}
+
 pub fn try_descend(ecs: &World) -> RunState {
     let player_entity = ecs.read_resource::<Entity>();
     let positions = ecs.read_storage::<Position>();
     let map = ecs.read_resource::<Map>();
     let mut player_res = ecs.write_storage::<Player>();
 
-    let mut log = ecs.write_resource::<GameLog>();
+    let mut events = ecs.write_resource::<EventQueue>();
 
     let player_pos = positions.get(*player_entity).unwrap();
     let mut player = player_res.get_mut(*player_entity).unwrap();
     console::log(format!("{:?} attempting to descend at {:?}", *player_entity, player_pos));
 
-    let tile_type = map.tiles[map.xy_idx(player_pos.x, player_pos.y)];
+    let idx = map.xy_idx(player_pos.x, player_pos.y);
 
-    if tile_type == TileType::StairsDown {
-        let next_level = map.depth + 1;
-        log.entries.push(format!("You descend deeper into the barrow..."));
-        log.entries.push(format!("(loading level {})", next_level));
-        player.deepest_level = next_level;
+    match map.staircase_at(idx) {
+        Some(_) if map.tiles[idx] == TileType::StairsDown && boss::stairs_sealed(ecs) => {
+            events.emit(GameEvent::StairsSealed);
+            return RunState::AwaitingInput
+        }
+        Some(stairs) if map.tiles[idx] == TileType::StairsDown => {
+            let next_level = stairs.to.level;
+            events.emit(GameEvent::Descended { level: next_level });
+            if next_level.branch == Branch::Barrow {
+                player.deepest_level = i32::max(player.deepest_level, next_level.depth);
+            }
 
-        return RunState::Descend { depth: next_level };    
-    } else {
-        return RunState::AwaitingInput
-    }    
+            return RunState::Descend { level: next_level, stairs: Some(stairs.to.stairs) };
+        }
+        _ => return RunState::AwaitingInput
+    }
 }
 
 pub fn try_ascend(ecs: &World) -> RunState {
@@ -111,38 +166,46 @@ pub fn try_ascend(ecs: &World) -> RunState {
     let map = ecs.read_resource::<Map>();
     let player_res = ecs.read_storage::<Player>();
 
-    let mut log = ecs.write_resource::<GameLog>();
+    let mut events = ecs.write_resource::<EventQueue>();
 
     let player_pos = positions.get(*player_entity).unwrap();
     let player = player_res.get(*player_entity).unwrap();
     // console::log(format!("{:?} attempting to ascend at {:?}", *player_entity, player_pos));
 
-    let tile_type = map.tiles[map.xy_idx(player_pos.x, player_pos.y)];
-
-    if tile_type == TileType::StairsUp {
-        if player.has_amulet {
-            let next_level = map.depth - 1;
-            if next_level > 0 {
-                log.entries.push(format!("You ascend toward town, but the amulet's darkness pervades your mind..."));
-                log.entries.push(format!("(loading level {})", next_level));
-            }
-            // console::log(format!("ascending to {} with amulet", next_level));
-            return RunState::Ascend { depth: next_level }
+    let idx = map.xy_idx(player_pos.x, player_pos.y);
 
-        } else {
-            let next_level = map.depth - 1;
-            if next_level > 0 {
-                log.entries.push(format!("You retreat from the depths (loading level {})", next_level));
-            } else {
-                log.entries.push(format!("You ascend toward town, but the barrow beckons you to return..."));
-            }
+    match map.staircase_at(idx) {
+        Some(_) if map.tiles[idx] == TileType::StairsUp && boss::stairs_sealed(ecs) => {
+            events.emit(GameEvent::StairsSealed);
+            return RunState::AwaitingInput
+        }
+        Some(stairs) if map.tiles[idx] == TileType::StairsUp => {
+            let next_level = stairs.to.level;
+            events.emit(GameEvent::Ascended { depth: next_level.depth, has_amulet: player.has_amulet });
             // console::log(format!("ascending to {}", next_level));
-            return RunState::Ascend { depth: next_level }
-            // return RunState::Shopping { menu_selection: 0 };        
+            return RunState::Ascend { level: next_level, stairs: Some(stairs.to.stairs) }
         }
-    } else {
-        return RunState::AwaitingInput
-    }    
+        _ => return RunState::AwaitingInput
+    }
+}
+
+/// Monsters next to the player that are fighting them, and so take the stairs after them
+pub fn stair_followers(ecs: &World) -> Vec<Entity> {
+    let player_entity = ecs.fetch::<Entity>();
+    let entities = ecs.entities();
+    let positions = ecs.read_storage::<Position>();
+    let monsters = ecs.read_storage::<Monster>();
+    let combat_stats = ecs.read_storage::<CombatStats>();
+
+    let player_pos = match positions.get(*player_entity) {
+        Some(pos) => Point::new(pos.x, pos.y),
+        None => return vec![]
+    };
+    (&entities, &positions, &monsters, &combat_stats).join()
+        .filter(|(entity, pos, _, stats)| stats.hp > 0 && stats.current_target == Some(*player_entity) && !morale::is_fleeing(ecs, *entity)
+            && rltk::DistanceAlg::Pythagoras.distance2d(Point::new(pos.x, pos.y), player_pos) < 1.5)
+        .map(|(entity, _, _, _)| entity)
+        .collect()
 }
 
 pub fn try_quick_ascend(ecs: &World) -> RunState {
@@ -151,6 +214,7 @@ pub fn try_quick_ascend(ecs: &World) -> RunState {
     let map = ecs.read_resource::<Map>();
     let player_res = ecs.read_storage::<Player>();
     let mut log = ecs.write_resource::<GameLog>();
+    let mut events = ecs.write_resource::<EventQueue>();
 
     let player_pos = positions.get(*player_entity).unwrap();
     let player = player_res.get(*player_entity).unwrap();
@@ -159,14 +223,17 @@ pub fn try_quick_ascend(ecs: &World) -> RunState {
     let tile_type = map.tiles[map.xy_idx(player_pos.x, player_pos.y)];
 
     if tile_type == TileType::StairsUp {
-        if player.has_amulet {
-            log.entries.push(format!("The amulet's darkness is a heavy burden as you return to the barrow's entrance\n(cannot quick ascend, try regular ascend)"));
+        if boss::stairs_sealed(ecs) {
+            events.emit(GameEvent::StairsSealed);
+            return RunState::AwaitingInput;
+        } else if player.has_amulet {
+            log.push_category(LogCategory::Movement, format!("The amulet's darkness is a heavy burden as you return to the barrow's entrance\n(cannot quick ascend, try regular ascend)"));
             console::log(format!("cannot quick ascend"));
             return RunState::AwaitingInput;
         } else {
-            log.entries.push(format!("You quickly ascend to town, but the barrow beckons you to return..."));
+            events.emit(GameEvent::QuickAscended);
             console::log(format!("quick ascending to town"));
-            return RunState::Ascend { depth: 0 }                
+            return RunState::Ascend { level: LevelId::barrow(0), stairs: None }                
         }
     } else {
         return RunState::AwaitingInput
@@ -192,51 +259,6 @@ pub fn try_select_target(selection: usize, ecs: &World) -> RunState {
     return RunState::AwaitingInput
 }
 
-pub fn get_available_moves(player_stats: &CombatStats) -> Vec<MenuCommand> {
-    match player_stats.stance {
-        Ready => {
-            return vec![
-                MenuCommand { command: WaitCommand(Wait), cost: -10, stance_after: Ready, enabled: true },
-                MenuCommand { command: AttackCommand(Melee), cost: 0, stance_after: Ready, enabled: true },
-                MenuCommand { command: AttackCommand(Smash), cost: 15, stance_after: Power, enabled: true },
-                MenuCommand { command: AttackCommand(Bash), cost: 10, stance_after: Guard, enabled: true },
-                MenuCommand { command: WaitCommand(Fend), cost: 0, stance_after: Ready, enabled: true },
-                MenuCommand { command: WaitCommand(Block), cost: 0, stance_after: Guard, enabled: true }
-            ]        
-        },
-        Power => {
-            return vec![
-                MenuCommand { command: WaitCommand(Wait), cost: -10, stance_after: Ready, enabled: true },
-                MenuCommand { command: AttackCommand(Melee), cost: 0, stance_after: Ready, enabled: true },
-                MenuCommand { command: AttackCommand(Smash), cost: 15, stance_after: Power, enabled: true },
-                MenuCommand { command: AttackCommand(Bash), cost: 10, stance_after: Guard, enabled: false },
-                MenuCommand { command: WaitCommand(Fend), cost: 0, stance_after: Ready, enabled: false },
-                MenuCommand { command: WaitCommand(Block), cost: -5, stance_after: Guard, enabled: false }
-            ]        
-        },
-        Guard => {
-            return vec![
-                MenuCommand { command: WaitCommand(Wait), cost: -10, stance_after: Ready, enabled: true },
-                MenuCommand { command: AttackCommand(Melee), cost: 0, stance_after: Ready, enabled: true },
-                MenuCommand { command: AttackCommand(Smash), cost: 15, stance_after: Guard, enabled: false },
-                MenuCommand { command: AttackCommand(Bash), cost: 10, stance_after: Guard, enabled: true },
-                MenuCommand { command: WaitCommand(Fend), cost: 0, stance_after: Ready, enabled: false },
-                MenuCommand { command: WaitCommand(Block), cost: 0, stance_after: Guard, enabled: true }
-            ]        
-        },
-        Stun => {
-            return vec![
-                MenuCommand { command: WaitCommand(Wait), cost: -10, stance_after: Ready, enabled: true },                
-                MenuCommand { command: AttackCommand(Melee), cost: 0, stance_after: Ready, enabled: false },
-                MenuCommand { command: AttackCommand(Smash), cost: 15, stance_after: Power, enabled: false },
-                MenuCommand { command: AttackCommand(Bash), cost: 10, stance_after: Guard, enabled: false },
-                MenuCommand { command: WaitCommand(Fend), cost: 0, stance_after: Ready, enabled: false },
-                MenuCommand { command: WaitCommand(Block), cost: -5, stance_after: Guard, enabled: false }
-            ]        
-        }
-    }
-}
-
 pub fn try_attack_menu(offset:usize, ecs: &World) -> RunState {
     let combat_stats = ecs.write_storage::<CombatStats>();
     let mut actions = ecs.write_storage::<Action>();
@@ -244,10 +266,14 @@ pub fn try_attack_menu(offset:usize, ecs: &World) -> RunState {
     let positions = ecs.read_storage::<Position>();
     let mut log = ecs.write_resource::<GameLog>();
     let entities = ecs.entities();
+    let rules = ecs.fetch::<RunSettings>().ruleset.rules();
 
     for (_player,player_entity, stats,player_pos) in (&player, &entities, &combat_stats, &positions).join() {
-        let commands = get_available_moves(&stats);
-        let selected_command = commands[offset];
+        // rulesets offer different numbers of moves, so the key may not map to anything
+        let selected_command = match rules.available_moves(&stats).get(offset) {
+            Some(command) => *command,
+            None => return RunState::AwaitingInput
+        };
 
         if selected_command.enabled == false {
             return RunState::AwaitingInput;
@@ -296,7 +322,7 @@ pub fn try_attack_menu(offset:usize, ecs: &World) -> RunState {
                             return RunState::PlayerTurn              
                         },
                         AttackCommand(_) => {
-                            log.entries.push(format!("Current target is out of range, cannot attack"));
+                            log.push_category(LogCategory::Combat, format!("Current target is out of range, cannot attack"));
                             return RunState::AwaitingInput 
                         }
                         _ => { return RunState::AwaitingInput }
@@ -304,8 +330,6 @@ pub fn try_attack_menu(offset:usize, ecs: &World) -> RunState {
                 }
             },
             None => {
-                let commands = get_available_moves(&stats);
-                let selected_command = commands[offset];
                 let action = match selected_command.command {
                     AttackCommand(_a) => { 
                         // console::log(format!("no target selected, can't attack"));
@@ -331,76 +355,191 @@ pub fn try_attack_menu(offset:usize, ecs: &World) -> RunState {
 
 }
 
+/// Queues a path to a revealed map tile, using the same A* search as the monsters
+pub fn try_travel_to(x: i32, y: i32, ecs: &World) -> RunState {
+    let player_entity = ecs.read_resource::<Entity>();
+    let positions = ecs.read_storage::<Position>();
+    let combat_stats = ecs.read_storage::<CombatStats>();
+    let map = ecs.fetch::<Map>();
+    let mut travel = ecs.write_resource::<TravelPath>();
+
+    travel.clear();
+    let destination_idx = map.xy_idx(x, y);
+    if !map.revealed_tiles[destination_idx] { return RunState::AwaitingInput; }
+
+    let player_pos = positions.get(*player_entity).unwrap();
+    let path = rltk::a_star_search(map.xy_idx(player_pos.x, player_pos.y), destination_idx, &map.grid);
+    if path.success && path.steps.len() > 1 {
+        travel.steps = path.steps[1..].to_vec();
+        travel.monsters_seen = combat_stats.get(*player_entity).map_or(0, |s| s.visible_targets.len());
+    }
+    return RunState::AwaitingInput
+}
+
+/// Starts auto-exploring; the path is recomputed every step as new tiles are revealed
+pub fn try_auto_explore(ecs: &World) -> RunState {
+    let player_entity = ecs.read_resource::<Entity>();
+    let combat_stats = ecs.read_storage::<CombatStats>();
+    let mut travel = ecs.write_resource::<TravelPath>();
+
+    travel.clear();
+    travel.exploring = true;
+    travel.monsters_seen = combat_stats.get(*player_entity).map_or(0, |s| s.visible_targets.len());
+    return RunState::AwaitingInput
+}
+
+/// Paths to the up or down stairs, if they have been seen on this level
+pub fn try_travel_to_stairs(stairs: TileType, ecs: &World) -> RunState {
+    let destination = {
+        let map = ecs.fetch::<Map>();
+        map.known_stairs(stairs).map(|idx| (idx as i32 % map.width, idx as i32 / map.width))
+    };
+    match destination {
+        Some((x, y)) => return try_travel_to(x, y, ecs),
+        None => {
+            let mut log = ecs.write_resource::<GameLog>();
+            let direction = if stairs == TileType::StairsUp { "up" } else { "down" };
+            log.push_category(LogCategory::Movement, format!("You have not found the way {} yet.", direction));
+            return RunState::AwaitingInput
+        }
+    }
+}
+
+/// Takes the next queued step, if it is still safe to keep walking
+fn continue_travel(ecs: &mut World) -> RunState {
+    let next_step = {
+        let player_entity = ecs.read_resource::<Entity>();
+        let positions = ecs.read_storage::<Position>();
+        let combat_stats = ecs.read_storage::<CombatStats>();
+        let containers = ecs.read_storage::<Container>();
+        let items = ecs.read_storage::<Item>();
+        let map = ecs.fetch::<Map>();
+        let mut travel = ecs.write_resource::<TravelPath>();
+
+        let visible_monsters = combat_stats.get(*player_entity).map_or(0, |s| s.visible_targets.len());
+        if visible_monsters > travel.monsters_seen {
+            travel.clear();
+            return RunState::AwaitingInput;
+        }
+        travel.monsters_seen = visible_monsters;
+
+        let player_pos = positions.get(*player_entity).unwrap();
+        if travel.exploring {
+            // head for unexplored space, or for any loot spotted on the way
+            let loot : Vec<usize> = (&positions, (&containers).maybe(), (&items).maybe()).join()
+                .filter(|(_, c, i)| c.is_some() || i.is_some())
+                .map(|(p, _, _)| map.xy_idx(p.x, p.y))
+                .filter(|idx| map.revealed_tiles[*idx])
+                .collect();
+            match map.explore_path(map.xy_idx(player_pos.x, player_pos.y), &loot) {
+                Some(path) => travel.steps = path,
+                None => {
+                    travel.clear();
+                    ecs.write_resource::<GameLog>().push_category(LogCategory::Movement, "There is nothing left to explore here.");
+                    return RunState::AwaitingInput;
+                }
+            }
+        }
+
+        let idx = travel.steps.remove(0);
+        let known_trap = match map.tiles[idx] { TileType::Trap { hidden: false, .. } => true, _ => false };
+        if map.blocked[idx] || known_trap {
+            travel.clear();
+            return RunState::AwaitingInput;
+        }
+        (idx as i32 % map.width - player_pos.x, idx as i32 / map.width - player_pos.y)
+    };
+    try_move_player(next_step.0, next_step.1, ecs);
+    return RunState::PlayerTurn
+}
+
+/// Left clicks: a map tile walks there, a monster becomes the target, and sidebar rows
+/// select targets or run commands just like their keys
+fn mouse_input(gs: &mut State, ctx: &mut Rltk) -> RunState {
+    ctx.set_active_console(1);
+    let panel_pos = ctx.mouse_pos();
+    ctx.set_active_console(0);
+    let map_pos = ctx.mouse_pos();
+
+    let in_sidebar = gs.ecs.fetch::<PanelLayout>().sidebar.map_or(false, |s| s.contains(panel_pos.0, panel_pos.1));
+    if in_sidebar {
+        let (target, command) = {
+            let layout = gs.ecs.fetch::<PanelLayout>();
+            (layout.targets.iter().find(|(y, _)| *y == panel_pos.1).map(|(_, n)| *n),
+             layout.commands.iter().find(|(y, _)| *y == panel_pos.1).map(|(_, offset)| *offset))
+        };
+        if let Some(n) = target { return try_select_target(n, &gs.ecs); }
+        if let Some(offset) = command { return try_attack_menu(offset, &gs.ecs); }
+        return RunState::AwaitingInput;
+    }
+
+    let map_pos = match Camera::new(&gs.ecs).to_world(map_pos.0, map_pos.1) {
+        Some(world) => world,
+        None => return RunState::AwaitingInput
+    };
+
+    {
+        let mut combat_stats = gs.ecs.write_storage::<CombatStats>();
+        let players = gs.ecs.read_storage::<Player>();
+        let positions = gs.ecs.read_storage::<Position>();
+        for (_player, stats) in (&players, &mut combat_stats).join() {
+            let clicked = stats.visible_targets.iter().find(|t| {
+                positions.get(**t).map_or(false, |p| p.x == map_pos.0 && p.y == map_pos.1)
+            }).copied();
+            if clicked.is_some() {
+                stats.current_target = clicked;
+                return RunState::AwaitingInput;
+            }
+        }
+    }
+
+    return try_travel_to(map_pos.0, map_pos.1, &gs.ecs);
+}
+
 pub fn player_input(gs: &mut State, ctx: &mut Rltk) -> RunState {
-    // Player movement
-    match ctx.key {
+    if ctx.key.is_some() {
+        gs.ecs.write_resource::<TravelPath>().clear();
+    } else if ctx.left_click {
+        return mouse_input(gs, ctx);
+    } else if gs.ecs.fetch::<TravelPath>().is_active() {
+        return continue_travel(&mut gs.ecs);
+    }
+
+    let action = match ctx.key {
         None => { return RunState::AwaitingInput } // Nothing happened
-        Some(key) => match key {
-            VirtualKeyCode::Left |
-            VirtualKeyCode::Numpad4 |
-            VirtualKeyCode::A => try_move_player(-1, 0, &mut gs.ecs),
+        Some(key) => gs.ecs.fetch::<Keymap>().action_for(key)
+    };
+    match action {
+        None => { return RunState::AwaitingInput }
+        Some(action) => match action {
+            InputAction::MoveWest => try_move_player(-1, 0, &mut gs.ecs),
+            InputAction::MoveEast => try_move_player(1, 0, &mut gs.ecs),
+            InputAction::MoveNorth => try_move_player(0, -1, &mut gs.ecs),
+            InputAction::MoveSouth => try_move_player(0, 1, &mut gs.ecs),
 
-            VirtualKeyCode::Right |
-            VirtualKeyCode::Numpad6 |
-            VirtualKeyCode::D => try_move_player(1, 0, &mut gs.ecs),
+            // Diagonals
+            InputAction::MoveNorthWest => try_move_player(-1, -1, &mut gs.ecs),
+            InputAction::MoveNorthEast => try_move_player(1, -1, &mut gs.ecs),
+            InputAction::MoveSouthEast => try_move_player(1, 1, &mut gs.ecs),
+            InputAction::MoveSouthWest => try_move_player(-1, 1, &mut gs.ecs),
 
-            VirtualKeyCode::Up |
-            VirtualKeyCode::Numpad8 |
-            VirtualKeyCode::W => try_move_player(0, -1, &mut gs.ecs),
+            InputAction::Target(n) => return try_select_target(n, &gs.ecs),
 
-            VirtualKeyCode::Down |
-            VirtualKeyCode::Numpad2 |
-            VirtualKeyCode::S => try_move_player(0, 1, &mut gs.ecs),
+            // Wait, attack and defend
+            InputAction::AttackMenu(offset) => return try_attack_menu(offset, &gs.ecs),
 
-            // Diagonals
-            VirtualKeyCode::Numpad7 |
-            VirtualKeyCode::Q => try_move_player(-1, -1, &mut gs.ecs),
-
-            VirtualKeyCode::Numpad9 |
-            VirtualKeyCode::E => try_move_player(1, -1, &mut gs.ecs),
-
-            VirtualKeyCode::Numpad3 |
-            VirtualKeyCode::C => try_move_player(1, 1, &mut gs.ecs),
-
-            VirtualKeyCode::Numpad1 |
-            VirtualKeyCode::Z => try_move_player(-1, 1, &mut gs.ecs),
-
-            // Num Keys
-            VirtualKeyCode::Key1 => return try_select_target(1, &gs.ecs),
-            VirtualKeyCode::Key2 => return try_select_target(2, &gs.ecs),
-            VirtualKeyCode::Key3 => return try_select_target(3, &gs.ecs),
-            VirtualKeyCode::Key4 => return try_select_target(4, &gs.ecs),
-            VirtualKeyCode::Key5 => return try_select_target(5, &gs.ecs),
-            VirtualKeyCode::Key6 => return try_select_target(6, &gs.ecs),
-            VirtualKeyCode::Key7 => return try_select_target(7, &gs.ecs),
-            VirtualKeyCode::Key8 => return try_select_target(8, &gs.ecs),
-            VirtualKeyCode::Key9 => return try_select_target(9, &gs.ecs),
-
-            // Skip
-            VirtualKeyCode::Numpad5 => return try_attack_menu(0, &gs.ecs),
-            VirtualKeyCode::Space => return try_attack_menu(0, &gs.ecs),
-            VirtualKeyCode::X => return try_attack_menu(0, &gs.ecs),
-
-            // Attack
-            VirtualKeyCode::J => return try_attack_menu(1, &gs.ecs),
-            VirtualKeyCode::K => return try_attack_menu(2, &gs.ecs),
-            VirtualKeyCode::L => return try_attack_menu(3, &gs.ecs),
-
-            VirtualKeyCode::N => return try_attack_menu(4, &gs.ecs),
-            VirtualKeyCode::M => return try_attack_menu(5, &gs.ecs),
-
-            // Ascend
-            VirtualKeyCode::Comma => {
-                return try_ascend(&gs.ecs)
-            },
-            VirtualKeyCode::T => {
-                return try_quick_ascend(&gs.ecs)
-            }
+            InputAction::Ascend => return try_ascend(&gs.ecs),
+            InputAction::QuickAscend => return try_quick_ascend(&gs.ecs),
+            InputAction::Descend => return try_descend(&gs.ecs),
+
+            InputAction::MessageHistory => return RunState::LogHistory { offset: 0 },
+            InputAction::LevelOverview => return RunState::LevelOverview,
 
-            // Descend
-            VirtualKeyCode::Period => return try_descend(&gs.ecs),
+            InputAction::AutoExplore => return try_auto_explore(&gs.ecs),
+            InputAction::TravelUpStairs => return try_travel_to_stairs(TileType::StairsUp, &gs.ecs),
+            InputAction::TravelDownStairs => return try_travel_to_stairs(TileType::StairsDown, &gs.ecs),
 
-            _ => { return RunState::AwaitingInput }
+            InputAction::CloseDoor => return features::try_close_door(&mut gs.ecs)
         },
     }
     RunState::PlayerTurn
@@ -0,0 +1,76 @@
+use specs::prelude::*;
+use rltk::{Rltk, RGB};
+use std::collections::HashMap;
+use super::{Map, Position, Renderable, Player, CombatStats, draw_map, animation, gui, camera::Camera};
+
+/// Frames each entity in a stack of items is shown for before the next one takes its turn
+const STACK_CYCLE_FRAMES : u64 = 45;
+
+/// Draws one frame of the game: the map, the entities on it by layer, the animations
+/// playing over them and finally the UI panels
+pub fn render(ecs: &World, ctx: &mut Rltk) {
+    draw_map(ecs, ctx);
+    draw_memories(ecs, ctx);
+    draw_entities(ecs, ctx);
+    animation::draw_animations(ecs, ctx);
+    gui::draw_ui(ecs, ctx);
+}
+
+/// Draws the remembered items, containers and monsters on revealed tiles out of view, dimmed
+fn draw_memories(ecs: &World, ctx: &mut Rltk) {
+    let camera = Camera::new(ecs);
+    let map = ecs.fetch::<Map>();
+
+    for (idx, memory) in map.remembered.iter().enumerate() {
+        if let Some(memory) = memory {
+            if map.visible_tiles[idx] || !map.revealed_tiles[idx] { continue; }
+            let (x, y) = (idx as i32 % map.width, idx as i32 / map.width);
+            if let Some((sx, sy)) = camera.to_screen(x, y) {
+                ctx.set(sx, sy, memory.fg * 0.4, RGB::named(rltk::BLACK), memory.glyph);
+            }
+        }
+    }
+}
+
+/// Draws every visible entity, highest render_order on top.  A tile holding several entities
+/// gets a tinted background; if one of them is a monster or the player it is always the one
+/// shown, and otherwise the items in the stack take turns being shown.
+fn draw_entities(ecs: &World, ctx: &mut Rltk) {
+    let camera = Camera::new(ecs);
+    let entities = ecs.entities();
+    let positions = ecs.read_storage::<Position>();
+    let renderables = ecs.read_storage::<Renderable>();
+    let players = ecs.read_storage::<Player>();
+    let combat_stats = ecs.read_storage::<CombatStats>();
+    let map = ecs.fetch::<Map>();
+
+    let mut stacks : HashMap<(i32, i32), Vec<(Entity, &Renderable)>> = HashMap::new();
+    for (entity, pos, render) in (&entities, &positions, &renderables).join() {
+        let idx = map.xy_idx(pos.x, pos.y);
+        if map.visible_tiles[idx] {
+            stacks.entry((pos.x, pos.y)).or_insert_with(Vec::new).push((entity, render));
+        }
+    }
+
+    for ((x, y), stack) in stacks.iter_mut() {
+        stack.sort_by(|a, b| b.1.render_order.cmp(&a.1.render_order));
+        let has_actor = stack.iter().any(|(e, _)| combat_stats.get(*e).is_some());
+        let shown = if stack.len() > 1 && !has_actor { (map.frame_count / STACK_CYCLE_FRAMES) as usize % stack.len() } else { 0 };
+        let (entity, render) = stack[shown];
+
+        let mut fg = render.fg;
+        if let Some(player) = players.get(entity) {
+            if player.has_amulet {
+                // the amulet pulses red while it is carried
+                let anim_index = map.frame_count.rem_euclid(60);
+                let anim_index_cycle = if anim_index > 30 { 30 - (anim_index - 30) } else { anim_index };
+                fg = RGB::from_u8(255u8, 8u8 * anim_index_cycle as u8, 0);
+            }
+        }
+        // a tinted background marks a stack so the player knows to look twice
+        let bg = if stack.len() > 1 { RGB::from_u8(40, 40, 60) } else { render.bg };
+        if let Some((sx, sy)) = camera.to_screen(*x, *y) {
+            ctx.set(sx, sy, fg, bg, render.glyph);
+        }
+    }
+}
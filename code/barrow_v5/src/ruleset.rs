@@ -0,0 +1,290 @@
+use specs::prelude::*;
+use super::{CombatStats, MenuCommand, monster_ai_system::MonsterAI, action_system::ActionSystem, intent_rules::Intents};
+use barrow_core::intent::IntentRules;
+use super::Command::*;
+use super::AttackMove::*;
+use super::WaitMove::*;
+use super::CombatStance::*;
+
+/// A combat model as the shared `barrow_core::Ruleset` has it, plus the moves the player is
+/// offered.  Both models share the Action and CombatStats components, so movement, doors,
+/// traps, pickups and the event log are the same whichever one is in play.
+pub trait Ruleset : barrow_core::Ruleset {
+    fn available_moves(&self, stats: &CombatStats) -> Vec<MenuCommand>;
+}
+
+/// Which ruleset a run is played under, chosen on the main menu
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum RulesetKind { Stance, Intent }
+
+impl RulesetKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            RulesetKind::Stance => "Stances",
+            RulesetKind::Intent => "Intents"
+        }
+    }
+
+    pub fn next(&self) -> RulesetKind {
+        match self {
+            RulesetKind::Stance => RulesetKind::Intent,
+            RulesetKind::Intent => RulesetKind::Stance
+        }
+    }
+
+    pub fn rules(&self) -> &'static dyn Ruleset {
+        match self {
+            RulesetKind::Stance => &StanceRules,
+            RulesetKind::Intent => &IntentRules(Intents)
+        }
+    }
+}
+
+/// Barrow's own model: every move shifts the attacker into a stance, and stances, blocks and
+/// fends decide how hard each attack lands and how much energy it drains
+pub struct StanceRules;
+
+impl Ruleset for StanceRules {
+    fn available_moves(&self, stats: &CombatStats) -> Vec<MenuCommand> {
+        match stats.stance {
+            Ready => {
+                vec![
+                    MenuCommand { command: WaitCommand(Wait), cost: -10, stance_after: Ready, enabled: true },
+                    MenuCommand { command: AttackCommand(Melee), cost: 0, stance_after: Ready, enabled: true },
+                    MenuCommand { command: AttackCommand(Smash), cost: 15, stance_after: Power, enabled: true },
+                    MenuCommand { command: AttackCommand(Bash), cost: 10, stance_after: Guard, enabled: true },
+                    MenuCommand { command: WaitCommand(Fend), cost: 0, stance_after: Ready, enabled: true },
+                    MenuCommand { command: WaitCommand(Block), cost: 0, stance_after: Guard, enabled: true }
+                ]
+            },
+            Power => {
+                vec![
+                    MenuCommand { command: WaitCommand(Wait), cost: -10, stance_after: Ready, enabled: true },
+                    MenuCommand { command: AttackCommand(Melee), cost: 0, stance_after: Ready, enabled: true },
+                    MenuCommand { command: AttackCommand(Smash), cost: 15, stance_after: Power, enabled: true },
+                    MenuCommand { command: AttackCommand(Bash), cost: 10, stance_after: Guard, enabled: false },
+                    MenuCommand { command: WaitCommand(Fend), cost: 0, stance_after: Ready, enabled: false },
+                    MenuCommand { command: WaitCommand(Block), cost: -5, stance_after: Guard, enabled: false }
+                ]
+            },
+            Guard => {
+                vec![
+                    MenuCommand { command: WaitCommand(Wait), cost: -10, stance_after: Ready, enabled: true },
+                    MenuCommand { command: AttackCommand(Melee), cost: 0, stance_after: Ready, enabled: true },
+                    MenuCommand { command: AttackCommand(Smash), cost: 15, stance_after: Guard, enabled: false },
+                    MenuCommand { command: AttackCommand(Bash), cost: 10, stance_after: Guard, enabled: true },
+                    MenuCommand { command: WaitCommand(Fend), cost: 0, stance_after: Ready, enabled: false },
+                    MenuCommand { command: WaitCommand(Block), cost: 0, stance_after: Guard, enabled: true }
+                ]
+            },
+            Stun => {
+                vec![
+                    MenuCommand { command: WaitCommand(Wait), cost: -10, stance_after: Ready, enabled: true },
+                    MenuCommand { command: AttackCommand(Melee), cost: 0, stance_after: Ready, enabled: false },
+                    MenuCommand { command: AttackCommand(Smash), cost: 15, stance_after: Power, enabled: false },
+                    MenuCommand { command: AttackCommand(Bash), cost: 10, stance_after: Guard, enabled: false },
+                    MenuCommand { command: WaitCommand(Fend), cost: 0, stance_after: Ready, enabled: false },
+                    MenuCommand { command: WaitCommand(Block), cost: -5, stance_after: Guard, enabled: false }
+                ]
+            }
+        }
+    }
+}
+
+impl barrow_core::Ruleset for StanceRules {
+    fn run_ai(&self, ecs: &World) {
+        let mut mob = MonsterAI{};
+        mob.run_now(ecs);
+    }
+
+    fn resolve(&self, ecs: &World) {
+        let mut melee = ActionSystem{};
+        melee.run_now(ecs);
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::{Action, Command, Container, EventQueue, GameEvent, Inventory, Item, Map, Monster, Name, Player, Position, SmartMonster, SufferDamage, Viewshed, LevelId, Branch, InputAction};
+    use crate::{damage_system::DamageSystem, keymap::ALL_ACTIONS};
+
+    /// A level with the player and a monster standing next to each other, in combat.  Which of
+    /// them is created first decides which one the systems reach first.
+    pub struct Arena {
+        pub world: World,
+        pub player: Entity,
+        pub monster: Entity
+    }
+
+    pub fn fighter(target: Option<Entity>) -> CombatStats {
+        CombatStats { max_hp: 30, hp: 30, hp_regen: -10, max_ep: 40, ep: 40, ep_regen: -5, defense: 0, power: 4, attack_cost: 5, stance: Ready, current_target: target, visible_targets: vec![], last_command: None }
+    }
+
+    pub fn arena(monster_first: bool) -> Arena {
+        let mut world = World::new();
+        world.register::<Position>();
+        world.register::<Player>();
+        world.register::<Monster>();
+        world.register::<Name>();
+        world.register::<Item>();
+        world.register::<Container>();
+        world.register::<Viewshed>();
+        world.register::<CombatStats>();
+        world.register::<Action>();
+        world.register::<SufferDamage>();
+        world.register::<SmartMonster>();
+        world.register::<Inventory>();
+        world.insert(Map::new_level(LevelId { branch: Branch::Barrow, depth: 1 }, 1));
+        world.insert(EventQueue::new());
+        world.insert(rltk::RandomNumberGenerator::seeded(1));
+
+        let player = |world: &mut World| world.create_entity()
+            .with(Position { x: 10, y: 10 })
+            .with(Name { name: String::from("Player") })
+            .with(Player { food: 3, max_food: 5, coin: 0, potions: 0, atk_bonus: 0, def_bonus: 0, deepest_level: 1, has_amulet: false, torch_fuel: 100 })
+            .build();
+        let monster = |world: &mut World| world.create_entity()
+            .with(Position { x: 11, y: 10 })
+            .with(Name { name: String::from("Kobold") })
+            .with(Monster { tag: 0 })
+            .build();
+        let (player, monster) = if monster_first {
+            let m = monster(&mut world);
+            (player(&mut world), m)
+        } else {
+            let p = player(&mut world);
+            (p, monster(&mut world))
+        };
+        world.insert(player);
+        {
+            let mut stats = world.write_storage::<CombatStats>();
+            stats.insert(player, fighter(Some(monster))).expect("Unable to insert stats");
+            stats.insert(monster, fighter(Some(player))).expect("Unable to insert stats");
+        }
+        Arena { world, player, monster }
+    }
+
+    impl Arena {
+        pub fn stats(&self, entity: Entity) -> CombatStats {
+            self.world.read_storage::<CombatStats>().get(entity).unwrap().clone()
+        }
+
+        pub fn set_stats(&mut self, entity: Entity, change: impl FnOnce(&mut CombatStats)) {
+            change(self.world.write_storage::<CombatStats>().get_mut(entity).unwrap());
+        }
+
+        /// Queues the move on the menu at `offset`, aimed at the other fighter
+        pub fn choose(&mut self, rules: &dyn Ruleset, entity: Entity, offset: usize) {
+            let other = if entity == self.player { self.monster } else { self.player };
+            let chosen = rules.available_moves(&self.stats(entity))[offset];
+            let target = match chosen.command { Command::AttackCommand(_) => Some(other), _ => None };
+            let action = Action { command: chosen.command, cost: chosen.cost, stance_after: chosen.stance_after, target: target, position: None };
+            self.world.write_storage::<Action>().insert(entity, action).expect("Unable to insert action");
+        }
+
+        /// Resolves the queued actions and applies the damage, as one pass of the turn
+        pub fn resolve(&mut self, rules: &dyn Ruleset) {
+            rules.resolve(&self.world);
+            DamageSystem{}.run_now(&self.world);
+            self.world.maintain();
+        }
+
+        pub fn events(&self) -> Vec<GameEvent> {
+            self.world.fetch::<EventQueue>().events.clone()
+        }
+
+        pub fn damage_to(&self, entity: Entity) -> i32 {
+            self.events().iter().map(|e| match e {
+                GameEvent::Attacked { target, hp_damage, .. } if *target == entity => *hp_damage,
+                _ => 0
+            }).sum()
+        }
+    }
+
+    const MELEE : usize = 1;
+    const STANCE_SMASH : usize = 2;
+
+    fn both_models() -> [(RulesetKind, &'static dyn Ruleset); 2] {
+        [(RulesetKind::Stance, RulesetKind::Stance.rules()), (RulesetKind::Intent, RulesetKind::Intent.rules())]
+    }
+
+    #[test]
+    fn menu_offsets_match_the_keys_and_the_commands() {
+        let slots = ALL_ACTIONS.iter().filter(|a| matches!(a, InputAction::AttackMenu(_))).count();
+        let stance_menu = vec![WaitCommand(Wait), AttackCommand(Melee), AttackCommand(Smash), AttackCommand(Bash), WaitCommand(Fend), WaitCommand(Block)];
+        let intent_menu = vec![WaitCommand(Wait), AttackCommand(Melee), AttackCommand(Smash), WaitCommand(Block)];
+
+        for stance in [Ready, Power, Guard, Stun].iter() {
+            let stats = CombatStats { stance: *stance, ..fighter(None) };
+            for (kind, rules) in both_models().iter() {
+                let moves = rules.available_moves(&stats);
+                let expected = if *kind == RulesetKind::Stance { &stance_menu } else { &intent_menu };
+                assert!(moves.len() <= slots, "{:?} offers more moves than there are command keys", kind);
+                assert_eq!(&moves.iter().map(|m| m.command).collect::<Vec<Command>>(), expected, "{:?} in {:?}", kind, stance);
+                // waiting is always on the first key, and it is the only way out of a stun
+                assert!(moves[0].enabled);
+                if *stance == Stun {
+                    assert!(moves.iter().skip(1).all(|m| !m.enabled), "{:?} lets a stunned fighter act", kind);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn adjacent_fighters_trade_blows() {
+        for (kind, rules) in both_models().iter() {
+            let mut arena = arena(false);
+            let (player, monster) = (arena.player, arena.monster);
+            arena.choose(*rules, player, MELEE);
+            arena.choose(*rules, monster, MELEE);
+            arena.resolve(*rules);
+
+            for entity in [player, monster].iter() {
+                let stats = arena.stats(*entity);
+                assert!(arena.damage_to(*entity) >= 4, "{:?}", kind);
+                assert_eq!(stats.hp, 30 - arena.damage_to(*entity), "{:?}", kind);
+                assert_eq!(stats.last_command, Some(AttackCommand(Melee)), "{:?}", kind);
+                // a plain attack is free with stances and costs the attack's energy with intents
+                let ep = if *kind == RulesetKind::Stance { 40 } else { 35 };
+                assert_eq!((stats.ep, stats.stance), (ep, Ready), "{:?}", kind);
+            }
+        }
+    }
+
+    #[test]
+    fn a_stunned_attacker_recovers_instead() {
+        for (kind, rules) in both_models().iter() {
+            let mut arena = arena(false);
+            let (player, monster) = (arena.player, arena.monster);
+            // the menu has the attack greyed out, but a monster's choice is not checked against it
+            arena.choose(*rules, player, MELEE);
+            arena.set_stats(player, |s| { s.stance = Stun; s.ep = 0; });
+            arena.resolve(*rules);
+
+            let stats = arena.stats(player);
+            assert_eq!(arena.stats(monster).hp, 30, "{:?}", kind);
+            let recovered = if *kind == RulesetKind::Stance { 10 } else { 5 };
+            assert_eq!((stats.ep, stats.stance), (recovered, Ready), "{:?}", kind);
+            let stunned = arena.events().iter().any(|e| matches!(e, GameEvent::Stunned { .. } | GameEvent::Exhausted { .. }));
+            assert!(stunned, "{:?}", kind);
+        }
+    }
+
+    #[test]
+    fn an_exhausted_attacker_rests_instead() {
+        for (kind, rules) in both_models().iter() {
+            let mut arena = arena(false);
+            let (player, monster) = (arena.player, arena.monster);
+            let (offset, ep, rested) = if *kind == RulesetKind::Stance { (STANCE_SMASH, 10, 20) } else { (MELEE, 3, 8) };
+            arena.set_stats(player, |s| s.ep = ep);
+            arena.choose(*rules, player, offset);
+            arena.resolve(*rules);
+
+            let stats = arena.stats(player);
+            assert_eq!(arena.stats(monster).hp, 30, "{:?}", kind);
+            assert_eq!((stats.ep, stats.stance), (rested, Ready), "{:?}", kind);
+            assert!(arena.events().iter().any(|e| matches!(e, GameEvent::Exhausted { .. })), "{:?}", kind);
+        }
+    }
+}
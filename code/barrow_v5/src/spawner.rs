@@ -1,7 +1,8 @@
 use rltk::{ RGB, RandomNumberGenerator };
 // use rltk::console;
 use specs::prelude::*;
-use super::{CombatStats, AttackMove, CombatStance, Player, Renderable, Rect, Map, Name, Position, Container, Item, Viewshed, Monster, BlocksTile, SmartMonster, SmartMonsterState };
+use super::{CombatStats, AttackMove, CombatStance, Player, Renderable, Map, Name, Position, Container, Item, Items, Viewshed, Monster, BlocksTile, SmartMonster, SmartMonsterState, Morale, Inventory, Corpse, morale::FEARLESS, Boss, BossKind, RunSettings, TileType, LightSource, Branch };
+use barrow_core::spawner::{gen_distinct_spawn_points, gen_tags};
 use super::Containers::*;
 use super::Items::*;
 // use super::Command::*;
@@ -11,9 +12,11 @@ use super::CombatStance::*;
 
 /// Spawns the player and returns his/her entity object.
 pub fn player(ecs : &mut World, player_x : i32, player_y : i32, player_state: Option<&Player>) -> Entity {
-    // TODO uncheat haha
-    let player = player_state.unwrap_or(&Player { food: 10, max_food: 10, coin: 0, potions: 0, atk_bonus: 0, def_bonus: 0, deepest_level: 0, has_amulet: false } );
-    let player_stats = CombatStats{ max_hp: 30, hp: 30, hp_regen: -10, max_ep: 40, ep: 40, ep_regen: -5, defense: 0 + &player.def_bonus, power: 4 + &player.atk_bonus, attack_cost: 5, stance: Ready, current_target: None, visible_targets: vec![], last_command: None };
+    let settings = *ecs.fetch::<RunSettings>();
+    let starting_player = settings.starting_player();
+    let player = player_state.unwrap_or(&starting_player);
+    let max_hp = settings.difficulty.player_max_hp();
+    let player_stats = CombatStats{ max_hp: max_hp, hp: max_hp, hp_regen: -10, max_ep: 40, ep: 40, ep_regen: -5, defense: 0 + &player.def_bonus, power: 4 + &player.atk_bonus, attack_cost: 5, stance: Ready, current_target: None, visible_targets: vec![], last_command: None };
     return ecs
         .create_entity()
         .with(Position { x: player_x, y: player_y })
@@ -21,11 +24,13 @@ pub fn player(ecs : &mut World, player_x : i32, player_y : i32, player_state: Op
             glyph: rltk::to_cp437('@'),
             fg: RGB::named(rltk::YELLOW),
             bg: RGB::named(rltk::BLACK),
+            render_order: 4,
         })
         .with(*player)
-        .with(Viewshed{ visible_tiles : Vec::new(), range: 8, dirty: true })
+        .with(Viewshed{ visible_tiles : Vec::new(), range: settings.viewshed_range(), dirty: true })
         .with(Name{name: "Player".to_string() })
         .with(player_stats)
+        .with(LightSource{ radius: settings.torch_radius(), color: RGB::from_f32(1.0, 0.8, 0.5), falloff: 1.2 })
         .build();
     }
 
@@ -36,12 +41,39 @@ pub fn coins(ecs: &mut World, loc: (i32, i32), tag:u64, amount: i32) {
             glyph: rltk::to_cp437('$'),
             fg: RGB::from_u8(182_u8,182_u8,182_u8),
             bg: RGB::named(rltk::BLACK),
+            render_order: 2,
         })
         .with(Name{ name : "Coins".to_string() })
         .with(Item{ item: Coin(amount), tag: tag })
         .build();
 }
 
+/// Turns rotted before a corpse is gone
+const CORPSE_ROT : i32 = 40;
+
+pub fn corpse(ecs: &mut World, loc: (i32, i32), name: &str) {
+    ecs.create_entity()
+        .with(Position{ x: loc.0, y: loc.1 })
+        .with(Renderable{
+            glyph: rltk::to_cp437('%'),
+            fg: RGB::from_u8(120_u8,30_u8,30_u8),
+            bg: RGB::named(rltk::BLACK),
+            render_order: 0,
+        })
+        .with(Name{ name : format!("{} corpse", name) })
+        .with(Corpse{ rot: CORPSE_ROT })
+        .build();
+}
+
+/// Puts a carried item back on the floor, keeping its tag
+pub fn drop_item(ecs: &mut World, loc: (i32, i32), item: &Item) {
+    match item.item {
+        Coin(amount) => coins(ecs, loc, item.tag, amount),
+        Amulet => amulet(ecs, loc, item.tag),
+        _ => {}
+    }
+}
+
 pub fn amulet(ecs: &mut World, loc: (i32, i32), tag:u64) {
     ecs.create_entity()
         .with(Position{ x: loc.0, y: loc.1 })
@@ -49,12 +81,47 @@ pub fn amulet(ecs: &mut World, loc: (i32, i32), tag:u64) {
             glyph: rltk::to_cp437('!'),
             fg: RGB::from_u8(212_u8,175_u8,55_u8),
             bg: RGB::named(rltk::BLACK),
+            render_order: 2,
         })
         .with(Name{ name : "Amulet".to_string() })
         .with(Item{ item: Amulet, tag: tag })
+        .with(LightSource{ radius: 3, color: RGB::from_f32(0.8, 0.6, 0.2), falloff: 1.5 })
         .build();
 }
 
+pub fn brazier(ecs: &mut World, loc: (i32, i32)) {
+    ecs.create_entity()
+    .with(Position{ x: loc.0, y: loc.1 })
+    .with(Renderable{
+        glyph: rltk::to_cp437('Φ'),
+        fg: RGB::from_u8(255_u8,140_u8,0_u8),
+        bg: RGB::named(rltk::BLACK),
+        render_order: 0,
+    })
+    .with(Name{ name : "Brazier".to_string() })
+    .with(BlocksTile{})
+    .with(LightSource{ radius: 7, color: RGB::from_f32(1.0, 0.6, 0.3), falloff: 1.0 })
+    .build();
+}
+
+/// Lights some rooms with a brazier in one corner.  The upper levels are better kept than the
+/// deep ones, so braziers get rarer with depth.
+pub fn braziers(ecs: &mut World, rng: &mut RandomNumberGenerator, map: &Map) {
+    let taken : Vec<(i32, i32)> = {
+        let positions = ecs.read_storage::<Position>();
+        (&positions).join().map(|p| (p.x, p.y)).collect()
+    };
+    let chance = i32::max(1, 6 - map.depth);
+    for room in map.rooms.iter() {
+        if rng.roll_dice(1, 10) > chance { continue; }
+        let corners = [(room.x1 + 1, room.y1 + 1), (room.x2, room.y1 + 1), (room.x1 + 1, room.y2), (room.x2, room.y2)];
+        let corner = corners[rng.range(0, 4) as usize];
+        if map.tiles[map.xy_idx(corner.0, corner.1)] == TileType::Floor && !taken.contains(&corner) {
+            brazier(ecs, corner);
+        }
+    }
+}
+
 pub fn barrel(ecs: &mut World, loc: (i32, i32), tag: u64) {
     ecs.create_entity()
     .with(Position{ x: loc.0, y: loc.1 })
@@ -62,6 +129,7 @@ pub fn barrel(ecs: &mut World, loc: (i32, i32), tag: u64) {
         glyph: rltk::to_cp437('?'),
         fg: RGB::from_u8(150_u8,120_u8,100_u8),
         bg: RGB::named(rltk::BLACK),
+        render_order: 1,
     })
     .with(Name{ name : "Barrel".to_string() })
     .with(Container{ container : Barrel, tag: tag })
@@ -75,35 +143,81 @@ pub fn treasure(ecs: &mut World, loc: (i32, i32), tag: u64) {
         glyph: rltk::to_cp437('?'),
         fg: RGB::from_u8(212_u8,175_u8,55_u8),
         bg: RGB::named(rltk::BLACK),
+        render_order: 1,
     })
     .with(Name{ name : "Treasure Chest".to_string() })
     .with(Container{ container : Treasure, tag: tag })
     .build();
 }
 
-pub fn orc(ecs: &mut World, loc: (i32, i32), tag: u64) { monster(ecs, loc.0, loc.1, tag, rltk::to_cp437('o'), 15, 30, 15, 4, 1, "Orc", Power, Smash, 0.2, 0, 1.0); }
-pub fn goblin(ecs: &mut World, loc: (i32, i32), tag: u64) { monster(ecs, loc.0, loc.1, tag, rltk::to_cp437('g'), 18, 20, 5, 3, 1, "Goblin", Ready, Melee, 0.4, 0, 1.0); }
-pub fn hobgoblin(ecs: &mut World, loc: (i32, i32), tag: u64) { monster(ecs, loc.0, loc.1, tag, rltk::to_cp437('h'), 25, 45, 15, 5, 1, "Hobgoblin", Guard, Bash, 0.5, 20, 0.3); }
-pub fn _ogre(ecs: &mut World, loc: (i32, i32), tag: u64) { monster(ecs, loc.0, loc.1, tag, rltk::to_cp437('O'), 40, 45, 5, 6, 3, "Ogre", Ready, Melee, 0.4, 30, 0.7); }
-pub fn troll(ecs: &mut World, loc: (i32, i32), tag: u64) { monster(ecs, loc.0, loc.1, tag, rltk::to_cp437('T'), 40, 30, 15, 5, 2, "Troll", Power, Smash, 0.3, 0, 1.0); }
-pub fn kobold(ecs: &mut World, loc: (i32, i32), tag: u64) { monster(ecs, loc.0, loc.1, tag, rltk::to_cp437('k'), 25, 30, 5, 4, 1, "Kobold", Ready, Melee, 0.2, 10, 0.6); }
-pub fn goblin_knight(ecs: &mut World, loc: (i32, i32), tag: u64) { monster(ecs, loc.0, loc.1, tag, rltk::to_cp437('G'), 35, 45, 15, 6, 2, "Goblin Knight", Guard, Bash, 0.5, 20, 0.3); }
-pub fn barrow_lord(ecs: &mut World, loc: (i32, i32), tag: u64) { monster(ecs, loc.0, loc.1, tag, rltk::to_cp437('B'), 40, 45, 15, 6, 3, "Barrow-Lord", Power, Smash, 0.4, 30, 0.7); }
+/// Gives a monster an item to carry, with a tag derived from its own
+fn carry(ecs: &mut World, monster: Entity, item: Items, tag: u64) {
+    if let Some(inventory) = ecs.write_storage::<Inventory>().get_mut(monster) {
+        inventory.items.push(Item { item: item, tag: tag ^ 0x5851_f42d_4c95_7f2d });
+    }
+}
+
+/// Goblins and their kin grab any coins they walk over
+fn looter(ecs: &mut World, monster: Entity) {
+    if let Some(inventory) = ecs.write_storage::<Inventory>().get_mut(monster) {
+        inventory.picks_up = true;
+    }
+}
 
+pub fn orc(ecs: &mut World, loc: (i32, i32), tag: u64) {
+    let orc = monster(ecs, loc.0, loc.1, tag, rltk::to_cp437('o'), 15, 30, 15, 4, 1, "Orc", Power, Smash, 0.2, 0, 1.0, 6);
+    if tag % 3 == 0 { carry(ecs, orc, Coin(5 + (tag % 7) as i32), tag); }
+}
+pub fn goblin(ecs: &mut World, loc: (i32, i32), tag: u64) {
+    let goblin = monster(ecs, loc.0, loc.1, tag, rltk::to_cp437('g'), 18, 20, 5, 3, 1, "Goblin", Ready, Melee, 0.4, 0, 1.0, 3);
+    looter(ecs, goblin);
+    if tag % 2 == 0 { carry(ecs, goblin, Coin(2 + (tag % 5) as i32), tag); }
+}
+pub fn hobgoblin(ecs: &mut World, loc: (i32, i32), tag: u64) {
+    let hobgoblin = monster(ecs, loc.0, loc.1, tag, rltk::to_cp437('h'), 25, 45, 15, 5, 1, "Hobgoblin", Guard, Bash, 0.5, 20, 0.3, 5);
+    looter(ecs, hobgoblin);
+    if tag % 2 == 0 { carry(ecs, hobgoblin, Coin(4 + (tag % 6) as i32), tag); }
+}
+pub fn _ogre(ecs: &mut World, loc: (i32, i32), tag: u64) { monster(ecs, loc.0, loc.1, tag, rltk::to_cp437('O'), 40, 45, 5, 6, 3, "Ogre", Ready, Melee, 0.4, 30, 0.7, 8); }
+pub fn troll(ecs: &mut World, loc: (i32, i32), tag: u64) { monster(ecs, loc.0, loc.1, tag, rltk::to_cp437('T'), 40, 30, 15, 5, 2, "Troll", Power, Smash, 0.3, 0, 1.0, 8); }
+pub fn kobold(ecs: &mut World, loc: (i32, i32), tag: u64) {
+    let kobold = monster(ecs, loc.0, loc.1, tag, rltk::to_cp437('k'), 25, 30, 5, 4, 1, "Kobold", Ready, Melee, 0.2, 10, 0.6, 7);
+    looter(ecs, kobold);
+}
+pub fn goblin_knight(ecs: &mut World, loc: (i32, i32), tag: u64) {
+    let knight = monster(ecs, loc.0, loc.1, tag, rltk::to_cp437('G'), 35, 45, 15, 6, 2, "Goblin Knight", Guard, Bash, 0.5, 20, 0.3, 8);
+    carry(ecs, knight, Coin(10 + (tag % 10) as i32), tag);
+}
+pub fn barrow_lord(ecs: &mut World, loc: (i32, i32), tag: u64) {
+    let lord = monster(ecs, loc.0, loc.1, tag, rltk::to_cp437('B'), 40, 45, 15, 6, 3, "Barrow-Lord", Power, Smash, 0.4, 30, 0.7, FEARLESS);
+    ecs.write_storage::<Boss>().insert(lord, Boss::new(BossKind::BarrowLord)).expect("Unable to insert boss");
+}
+pub fn skeleton(ecs: &mut World, loc: (i32, i32), tag: u64) { monster(ecs, loc.0, loc.1, tag, rltk::to_cp437('s'), 20, 25, 5, 4, 1, "Skeleton", Ready, Melee, 0.6, 0, 1.0, FEARLESS); }
+pub fn wight(ecs: &mut World, loc: (i32, i32), tag: u64) { monster(ecs, loc.0, loc.1, tag, rltk::to_cp437('W'), 30, 40, 15, 5, 2, "Wight", Power, Smash, 0.7, 10, 0.5, FEARLESS); }
+pub fn barrow_wraith(ecs: &mut World, loc: (i32, i32), tag: u64) {
+    let wraith = monster(ecs, loc.0, loc.1, tag, rltk::to_cp437('Ω'), 30, 50, 15, 7, 2, "Barrow-Wraith", Power, Smash, 1.0, 20, 0.6, FEARLESS);
+    ecs.write_storage::<Boss>().insert(wraith, Boss::new(BossKind::BarrowWraith)).expect("Unable to insert boss");
+}
+pub fn shade(ecs: &mut World, loc: (i32, i32), tag: u64) { monster(ecs, loc.0, loc.1, tag, rltk::to_cp437('S'), 12, 30, 5, 4, 0, "Shade", Ready, Melee, 0.9, 0, 1.0, FEARLESS); }
 
-fn monster<S : ToString>(ecs: &mut World, x: i32, y: i32, tag:u64, glyph : rltk::FontCharType, hp:i32, ep:i32, cost:i32, pow:i32, def:i32, name : S, stance: CombatStance, attack: AttackMove, chase_chance: f32, ep_threshold: i32, recover_ep_chance: f32) {
+
+fn monster<S : ToString>(ecs: &mut World, x: i32, y: i32, tag:u64, glyph : rltk::FontCharType, hp:i32, ep:i32, cost:i32, pow:i32, def:i32, name : S, stance: CombatStance, attack: AttackMove, chase_chance: f32, ep_threshold: i32, recover_ep_chance: f32, courage: i32) -> Entity {
+    let hp = hp * ecs.fetch::<RunSettings>().difficulty.monster_hp_percent() / 100;
     ecs.create_entity()
         .with(Position{ x, y })
         .with(Renderable{
             glyph,
             fg: RGB::named(rltk::RED),
             bg: RGB::named(rltk::BLACK),
+            render_order: 3,
         })
         .with(Viewshed{ visible_tiles : Vec::new(), range: 8, dirty: true })
         .with(Monster{ tag: tag })
         .with(Name{ name : name.to_string() })
         .with(BlocksTile{})
         .with(CombatStats{ max_hp: hp, hp: hp, hp_regen:-5, max_ep: ep, ep: ep, ep_regen:-10, defense: def, power: pow, attack_cost: 5, stance: Ready, current_target: None, visible_targets: vec![], last_command: None })
+        .with(Morale{ courage: courage, morale: courage, fleeing: false })
+        .with(Inventory{ items: vec![], picks_up: false })
         .with(SmartMonster{ 
             state: SmartMonsterState::Asleep,
             time_in_current_state: 0,
@@ -116,36 +230,71 @@ fn monster<S : ToString>(ecs: &mut World, x: i32, y: i32, tag:u64, glyph : rltk:
             visible_chase_chance: 0.9,
             invisible_chase_chance: chase_chance
         })
-        .build();
+        .build()
 }
 
-pub fn gen_spawn_points(room: &Rect, count: i32, rng: &mut RandomNumberGenerator) -> Vec<(i32, i32)> {
-    let mut res : Vec<(i32, i32)> = vec![];
-    res.push(room.center());
-    while (res.len() as i32) < count {
-        // todo - check if duplicate
-        let x = rng.range(room.x1 + 1, room.x2 + 1);
-        let y = rng.range(room.y1 + 1, room.y2 + 1);
-        res.push((x,y));
+/// Spawns a monster of the same kind as a named archetype, used by the double monsters modifier
+/// and by sarcophagi
+pub fn monster_by_name(ecs: &mut World, name: &str, loc: (i32, i32), tag: u64) {
+    match name {
+        "Orc" => orc(ecs, loc, tag),
+        "Goblin" => goblin(ecs, loc, tag),
+        "Hobgoblin" => hobgoblin(ecs, loc, tag),
+        "Ogre" => _ogre(ecs, loc, tag),
+        "Troll" => troll(ecs, loc, tag),
+        "Kobold" => kobold(ecs, loc, tag),
+        "Goblin Knight" => goblin_knight(ecs, loc, tag),
+        "Barrow-Lord" => barrow_lord(ecs, loc, tag),
+        "Skeleton" => skeleton(ecs, loc, tag),
+        "Wight" => wight(ecs, loc, tag),
+        "Barrow-Wraith" => barrow_wraith(ecs, loc, tag),
+        "Shade" => shade(ecs, loc, tag),
+        _ => {}
     }
-    // console::log(format!("spawn points for room {:?}: {:?}", room, res));
-
-    return res
 }
 
-pub fn gen_tags(count: i32, rng: &mut RandomNumberGenerator) -> Vec<u64> {
-    let mut res : Vec<u64> = vec![];
-    for _i in 0..count {
-        res.push(rng.next_u64());
+/// Spawns a twin next to every monster on the level.  Monsters are visited in tag order and the
+/// twin's tag is derived from the original, so reloading a level reproduces the same twins.
+pub fn double_monsters(ecs: &mut World, map: &Map) {
+    let mut originals : Vec<(u64, String, Position)> = vec![];
+    {
+        let monsters = ecs.read_storage::<Monster>();
+        let names = ecs.read_storage::<Name>();
+        let positions = ecs.read_storage::<Position>();
+        for (monster, name, pos) in (&monsters, &names, &positions).join() {
+            // the Barrow-Lord and his wraith stay unique
+            if name.name != "Barrow-Lord" && name.name != "Barrow-Wraith" {
+                originals.push((monster.tag, name.name.clone(), *pos));
+            }
+        }
+    }
+    originals.sort_by_key(|o| o.0);
+
+    let mut taken : Vec<Position> = originals.iter().map(|o| o.2).collect();
+    for (tag, name, pos) in originals.iter() {
+        let mut spot : Option<Position> = None;
+        'search: for dy in -1..=1 {
+            for dx in -1..=1 {
+                let candidate = Position { x: pos.x + dx, y: pos.y + dy };
+                if candidate.x < 1 || candidate.x > map.width - 2 || candidate.y < 1 || candidate.y > map.height - 2 { continue; }
+                let idx = map.xy_idx(candidate.x, candidate.y);
+                if map.tiles[idx] == TileType::Floor && !taken.contains(&candidate) {
+                    spot = Some(candidate);
+                    break 'search;
+                }
+            }
+        }
+        if let Some(spot) = spot {
+            taken.push(spot);
+            monster_by_name(ecs, name, (spot.x, spot.y), tag ^ 0x9e37_79b9_7f4a_7c15);
+        }
     }
-
-    return res
 }
 
 pub fn populate_level_1(ecs: &mut World, rng: &mut RandomNumberGenerator, map: &Map) {
     let last_room = map.rooms.len() - 1;
     for (i,room) in map.rooms.iter().enumerate().skip(1) {
-        let spawn_points = gen_spawn_points(&room, 5, rng);
+        let spawn_points = gen_distinct_spawn_points(&room, 5, rng);
         let tags = gen_tags(10, rng);
         // let (x,y) = spawn_points[0];
         // let (item_x, item_y) = spawn_points[1];
@@ -188,7 +337,7 @@ pub fn populate_level_1(ecs: &mut World, rng: &mut RandomNumberGenerator, map: &
 pub fn populate_level_2(ecs: &mut World, rng: &mut RandomNumberGenerator, map: &Map) {
     let last_room = map.rooms.len() - 1;
     for (i,room) in map.rooms.iter().enumerate().skip(1) {
-        let spawn_points = gen_spawn_points(&room, 5, rng);
+        let spawn_points = gen_distinct_spawn_points(&room, 5, rng);
         let tags = gen_tags(10, rng);
         if i == last_room {
             kobold( ecs, spawn_points[1], tags[0]);
@@ -228,7 +377,7 @@ pub fn populate_level_2(ecs: &mut World, rng: &mut RandomNumberGenerator, map: &
 pub fn populate_level_3(ecs: &mut World, rng: &mut RandomNumberGenerator, map: &Map) {
     let last_room = map.rooms.len() - 1;
     for (i,room) in map.rooms.iter().enumerate().skip(1) {
-        let spawn_points = gen_spawn_points(&room, 5, rng);
+        let spawn_points = gen_distinct_spawn_points(&room, 5, rng);
         let tags = gen_tags(10, rng);
         if i == last_room {
             orc( ecs, spawn_points[1], tags[0]);
@@ -267,7 +416,7 @@ pub fn populate_level_3(ecs: &mut World, rng: &mut RandomNumberGenerator, map: &
 pub fn populate_level_4(ecs: &mut World, rng: &mut RandomNumberGenerator, map: &Map) {
     let last_room = map.rooms.len() - 1;
     for (i,room) in map.rooms.iter().enumerate().skip(1) {
-        let spawn_points = gen_spawn_points(&room, 5, rng);
+        let spawn_points = gen_distinct_spawn_points(&room, 5, rng);
         let tags = gen_tags(10, rng);
         if i == last_room {
             troll( ecs, spawn_points[1], tags[0]);
@@ -308,7 +457,7 @@ pub fn populate_level_4(ecs: &mut World, rng: &mut RandomNumberGenerator, map: &
 pub fn populate_level_5(ecs: &mut World, rng: &mut RandomNumberGenerator, map: &Map) {
     let last_room = map.rooms.len() - 1;
     for (i,room) in map.rooms.iter().enumerate().skip(1) {
-        let spawn_points = gen_spawn_points(&room, 5, rng);
+        let spawn_points = gen_distinct_spawn_points(&room, 5, rng);
         let tags = gen_tags(5, rng);
 
         // let (x,y) = spawn_points[0];
@@ -351,7 +500,7 @@ pub fn populate_level_5(ecs: &mut World, rng: &mut RandomNumberGenerator, map: &
 pub fn populate_level_6(ecs: &mut World, rng: &mut RandomNumberGenerator, map: &Map) {
     let last_room = map.rooms.len() - 1;
     for (i,room) in map.rooms.iter().enumerate().skip(1) {
-        let spawn_points = gen_spawn_points(&room, 5, rng);
+        let spawn_points = gen_distinct_spawn_points(&room, 5, rng);
         let tags = gen_tags(5, rng);
 
         // let (x,y) = spawn_points[0];
@@ -394,4 +543,79 @@ pub fn populate_level_6(ecs: &mut World, rng: &mut RandomNumberGenerator, map: &
             barrel( ecs, spawn_points[3], tags[4]);
         }
     }
-}
\ No newline at end of file
+}
+/// Side branches have their own monsters, and the last room of a branch's bottom level holds
+/// the hoard that makes the detour worth it
+pub fn populate_branch(ecs: &mut World, rng: &mut RandomNumberGenerator, map: &Map) {
+    let last_room = map.rooms.len() - 1;
+    let bottom = map.level_id().down().is_none();
+    for (i,room) in map.rooms.iter().enumerate().skip(1) {
+        let spawn_points = gen_distinct_spawn_points(&room, 5, rng);
+        let tags = gen_tags(10, rng);
+
+        if i == last_room && bottom {
+            match map.branch {
+                Branch::Crypt => {
+                    wight( ecs, spawn_points[1], tags[0]);
+                    skeleton( ecs, spawn_points[2], tags[1]);
+                }
+                _ => {
+                    troll( ecs, spawn_points[1], tags[0]);
+                    kobold( ecs, spawn_points[2], tags[1]);
+                }
+            }
+            coins( ecs, spawn_points[3], tags[2], 30 + map.depth * 10);
+            treasure( ecs, spawn_points[3], tags[3]);
+            coins( ecs, spawn_points[4], tags[4], 30 + map.depth * 10);
+            treasure( ecs, spawn_points[4], tags[5]);
+            continue;
+        }
+
+        let roll = rng.roll_dice(1, 6);
+        match map.branch {
+            Branch::Crypt => {
+                match roll {
+                    1 => { wight(ecs, spawn_points[0], tags[0]) }
+                    2 | 3 => {
+                        skeleton(ecs, spawn_points[0], tags[0]);
+                        skeleton(ecs, spawn_points[1], tags[1]);
+                    }
+                    4 => { skeleton(ecs, spawn_points[0], tags[0]) }
+                    _ => {}
+                }
+            }
+            _ => {
+                match roll {
+                    1 => { troll(ecs, spawn_points[0], tags[0]) }
+                    2 | 3 => {
+                        kobold(ecs, spawn_points[0], tags[0]);
+                        kobold(ecs, spawn_points[1], tags[1]);
+                    }
+                    4 => { orc(ecs, spawn_points[0], tags[0]) }
+                    _ => {}
+                }
+            }
+        }
+
+        let coin_amount = rng.range(5, 11) + map.depth * 2;
+        coins( ecs, spawn_points[2], tags[2], coin_amount);
+        barrel( ecs, spawn_points[3], tags[3]);
+    }
+}
+
+/// The darkness that follows the Amulet out: each time the player comes back through a level
+/// while escaping, another wave of shades rises in its rooms, and each wave is larger.
+pub fn darkness(ecs: &mut World, rng: &mut RandomNumberGenerator, map: &Map, wave: i32) {
+    for room in map.rooms.iter().skip(1) {
+        if rng.roll_dice(1, 6) > wave + 1 { continue; }
+        let spawn_points = gen_distinct_spawn_points(&room, 5, rng);
+        let tags = gen_tags(10, rng);
+        let shades = i32::min(1 + wave / 2, 4) as usize;
+        for i in 0..shades {
+            shade(ecs, spawn_points[i], tags[i]);
+        }
+        if wave >= 4 {
+            wight(ecs, spawn_points[4], tags[4]);
+        }
+    }
+}
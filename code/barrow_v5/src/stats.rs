@@ -0,0 +1,65 @@
+use std::collections::{HashMap, HashSet};
+use rltk::RandomNumberGenerator;
+use super::LevelId;
+
+/// Statistics for the current run, updated by the systems as the game is played
+pub struct RunStats {
+    pub seed: u64,
+    pub levels_generated: u64,
+    pub turns: i32,
+    pub deepest_depth: i32,
+    pub visited: HashSet<LevelId>,
+    pub kills: HashMap<String, i32>,
+    pub coins_earned: i32,
+    pub food_eaten: i32,
+    pub last_hit_by: Option<String>,
+    pub cause_of_death: Option<String>,
+    pub victory: bool,
+    pub recorded: bool
+}
+
+impl RunStats {
+    pub fn new(seed: u64) -> RunStats {
+        RunStats {
+            seed: seed,
+            levels_generated: 0,
+            turns: 0,
+            deepest_depth: 0,
+            visited: HashSet::new(),
+            kills: HashMap::new(),
+            coins_earned: 0,
+            food_eaten: 0,
+            last_hit_by: None,
+            cause_of_death: None,
+            victory: false,
+            recorded: false
+        }
+    }
+
+    /// Level seeds are derived from the run seed, so a run can be replayed from its seed
+    pub fn next_level_seed(&mut self) -> u64 {
+        self.levels_generated += 1;
+        let mut rng = RandomNumberGenerator::seeded(self.seed.wrapping_add(self.levels_generated));
+        rng.next_u64()
+    }
+
+    pub fn record_kill(&mut self, name: &str) {
+        *self.kills.entry(name.to_string()).or_insert(0) += 1;
+    }
+
+    pub fn total_kills(&self) -> i32 {
+        self.kills.values().sum()
+    }
+
+    /// Kills sorted by count, most killed first
+    pub fn kill_list(&self) -> Vec<(String, i32)> {
+        let mut kills : Vec<(String, i32)> = self.kills.iter().map(|(k, v)| (k.clone(), *v)).collect();
+        kills.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        kills
+    }
+
+    pub fn kill_summary(&self) -> String {
+        let kills : Vec<String> = self.kill_list().iter().map(|(name, count)| format!("{} x{}", name, count)).collect();
+        if kills.is_empty() { String::from("none") } else { kills.join(", ") }
+    }
+}
@@ -0,0 +1,326 @@
+use rltk::{ RGB, RGBA, Rltk, VirtualKeyCode };
+use bracket_terminal::prelude::TextAlign;
+
+/// A rectangle of cells on a console.  Widgets are drawn into regions rather than at fixed
+/// coordinates, so the screen can be laid out from the size of the console.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct Region {
+    pub x: i32,
+    pub y: i32,
+    pub w: i32,
+    pub h: i32
+}
+
+impl Region {
+    pub fn new(x: i32, y: i32, w: i32, h: i32) -> Region {
+        Region { x, y, w: i32::max(0, w), h: i32::max(0, h) }
+    }
+
+    pub fn right(&self) -> i32 { self.x + self.w - 1 }
+    pub fn bottom(&self) -> i32 { self.y + self.h - 1 }
+
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x && x <= self.right() && y >= self.y && y <= self.bottom()
+    }
+
+    /// The region shrunk by `n` cells on every side
+    pub fn inset(&self, n: i32) -> Region {
+        Region::new(self.x + n, self.y + n, self.w - 2 * n, self.h - 2 * n)
+    }
+
+    /// Splits off the first `w` columns, returning them and the columns that are left
+    pub fn split_columns(&self, w: i32) -> (Region, Region) {
+        let w = i32::min(w, self.w);
+        (Region::new(self.x, self.y, w, self.h), Region::new(self.x + w, self.y, self.w - w, self.h))
+    }
+
+    /// A region of at most the given size in the middle of this one
+    pub fn centered(&self, w: i32, h: i32) -> Region {
+        let (w, h) = (i32::min(w, self.w), i32::min(h, self.h));
+        Region::new(self.x + (self.w - w) / 2, self.y + (self.h - h) / 2, w, h)
+    }
+
+    /// The same area on a console whose cells are `cols` times narrower and `rows` times shorter
+    pub fn scaled(&self, cols: i32, rows: i32) -> Region {
+        Region::new(self.x * cols, self.y * rows, self.w * cols, self.h * rows)
+    }
+}
+
+/// Where the game screen's panels sit on the UI console.  Everything is worked out from the
+/// console's size, so a larger or smaller console moves the panels rather than breaking them.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub struct ScreenLayout {
+    pub screen: Region,
+    /// The part of the UI console that lies over the map viewport
+    pub map: Region,
+    pub sidebar: Region,
+    pub log: Region,
+    /// Where sidebar tooltips open, centred over the map
+    pub popup: Region
+}
+
+impl ScreenLayout {
+    pub const SIDEBAR_WIDTH : i32 = 29;
+    pub const LOG_HEIGHT : i32 = 9;
+    /// The largest a tooltip popup gets; on a small console it shrinks to fit the map panel
+    pub const POPUP_WIDTH : i32 = 35;
+    pub const POPUP_HEIGHT : i32 = 19;
+
+    pub fn new(width: i32, height: i32) -> ScreenLayout {
+        let screen = Region::new(0, 0, width, height);
+        let log_top = height - ScreenLayout::LOG_HEIGHT;
+        let sidebar_left = width - ScreenLayout::SIDEBAR_WIDTH;
+        let map = Region::new(0, 0, sidebar_left - 1, log_top);
+        ScreenLayout {
+            screen: screen,
+            map: map,
+            sidebar: Region::new(sidebar_left, 0, ScreenLayout::SIDEBAR_WIDTH, log_top),
+            log: Region::new(1, log_top, width - 2, ScreenLayout::LOG_HEIGHT),
+            popup: map.inset(1).centered(ScreenLayout::POPUP_WIDTH, ScreenLayout::POPUP_HEIGHT)
+        }
+    }
+
+    /// The layout for whichever console is active, which should be the UI console
+    pub fn for_console(ctx: &Rltk) -> ScreenLayout {
+        let (width, height) = ctx.get_char_size();
+        ScreenLayout::new(width as i32, height as i32)
+    }
+}
+
+/// Hands out the rows of a region from the top down, so widgets can be stacked without
+/// counting rows by hand
+pub struct VStack {
+    region: Region,
+    next: i32
+}
+
+impl VStack {
+    pub fn new(region: Region) -> VStack {
+        VStack { region, next: region.y }
+    }
+
+    /// The next `h` rows, cut short if the region runs out
+    pub fn take(&mut self, h: i32) -> Region {
+        let h = i32::min(h, self.region.bottom() + 1 - self.next);
+        let taken = Region::new(self.region.x, self.next, self.region.w, h);
+        self.next += i32::max(0, h);
+        taken
+    }
+
+    pub fn row(&mut self) -> Region {
+        self.take(1)
+    }
+
+    pub fn gap(&mut self, rows: i32) {
+        self.next += rows;
+    }
+
+    /// Everything below the rows taken so far
+    pub fn rest(&self) -> Region {
+        Region::new(self.region.x, self.next, self.region.w, self.region.bottom() + 1 - self.next)
+    }
+}
+
+pub fn transparent() -> RGBA {
+    RGBA::from_f32(0.0, 0.0, 0.0, 0.0)
+}
+
+/// Plain text on the first row of a region, clipped to its width
+pub fn label<S: ToString>(ctx: &mut Rltk, at: Region, fg: RGB, text: S) {
+    if at.h <= 0 { return; }
+    let text : String = text.to_string().chars().take(at.w as usize).collect();
+    ctx.print_color(at.x, at.y, fg, RGB::named(rltk::BLACK), text);
+}
+
+/// Text with `#[colour]` markup on the first row of a region
+pub fn markup<S: ToString>(ctx: &mut Rltk, at: Region, text: S) {
+    if at.h <= 0 { return; }
+    ctx.printer(at.x, at.y, text, TextAlign::Left, Some(RGBA::named(rltk::BLACK)));
+}
+
+/// Text centred on the first row of a region
+pub fn centered<S: ToString>(ctx: &mut Rltk, at: Region, fg: RGB, text: S) {
+    if at.h <= 0 { return; }
+    ctx.print_color_centered_at(at.x + at.w / 2, at.y, fg, RGB::named(rltk::BLACK), text);
+}
+
+/// Newline separated text, one line per row, as far down as the region goes
+pub fn text_block(ctx: &mut Rltk, at: Region, fg: RGB, text: &str) {
+    let mut rows = VStack::new(at);
+    for line in text.split('\n') {
+        let row = rows.row();
+        if row.h == 0 { break; }
+        label(ctx, row, fg, line);
+    }
+}
+
+/// A meter filled to `value` out of `max`, with its text written over it
+pub struct Bar {
+    pub value: i32,
+    pub max: i32,
+    pub colour: RGB,
+    pub text: String
+}
+
+impl Bar {
+    pub fn draw(&self, ctx: &mut Rltk, at: Region) {
+        if at.h <= 0 { return; }
+        ctx.draw_bar_horizontal(at.x, at.y, at.w, self.value, self.max, self.colour, RGB::named(rltk::BLACK));
+        ctx.print_color(at.x + 3, at.y, RGB::named(rltk::WHITE), transparent(), &self.text);
+    }
+}
+
+/// A bordered box with an optional title set into its top edge.  Returns the region inside
+/// the border for the panel's contents.
+pub struct Panel<'a> {
+    pub title: Option<&'a str>
+}
+
+impl<'a> Panel<'a> {
+    pub fn draw(&self, ctx: &mut Rltk, at: Region) -> Region {
+        ctx.draw_box(at.x, at.y, at.w - 1, at.h - 1, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK));
+        if let Some(title) = self.title {
+            ctx.print_color(at.x + 2, at.y, RGB::named(rltk::YELLOW), RGB::named(rltk::BLACK), format!(" {} ", title));
+        }
+        at.inset(1)
+    }
+}
+
+/// A column of entries with one of them highlighted.  Long lists scroll to keep the
+/// highlighted entry in view, and blank rows can be left after chosen entries to group them.
+pub struct List<'a> {
+    pub entries: &'a [(String, RGB)],
+    pub selected: Option<usize>,
+    pub highlight: RGB,
+    /// Entries after which a blank row is left
+    pub gaps_after: &'a [usize],
+    pub centered: bool
+}
+
+impl<'a> List<'a> {
+    pub fn new(entries: &'a [(String, RGB)], selected: Option<usize>) -> List<'a> {
+        List { entries, selected, highlight: RGB::named(rltk::MAGENTA), gaps_after: &[], centered: false }
+    }
+
+    pub fn draw(&self, ctx: &mut Rltk, at: Region) {
+        let visible = i32::max(1, at.h - self.gaps_after.len() as i32) as usize;
+        let first = match self.selected {
+            Some(selected) if selected >= visible => selected + 1 - visible,
+            _ => 0
+        };
+        let mut rows = VStack::new(at);
+        for (i, (text, colour)) in self.entries.iter().enumerate().skip(first) {
+            let row = rows.row();
+            if row.h == 0 { break; }
+            let colour = if self.selected == Some(i) { self.highlight } else { *colour };
+            if self.centered { centered(ctx, row, colour, text) } else { label(ctx, row, colour, text) }
+            if self.gaps_after.contains(&i) { rows.gap(1); }
+        }
+    }
+}
+
+/// A dialog drawn over the rest of the screen, with a hint about its keys along the bottom.
+/// It only draws; the screen that opens it reads the keys, with `list_focus` for a list.
+/// Returns the region left for its contents.
+pub struct Modal<'a> {
+    pub title: Option<&'a str>,
+    pub hint: Option<&'a str>
+}
+
+impl<'a> Modal<'a> {
+    pub fn draw(&self, ctx: &mut Rltk, at: Region) -> Region {
+        let inner = Panel { title: self.title }.draw(ctx, at);
+        match self.hint {
+            Some(hint) => {
+                label(ctx, Region::new(inner.x + 1, inner.bottom(), inner.w - 1, 1), RGB::named(rltk::GREY), hint);
+                Region::new(inner.x, inner.y, inner.w, inner.h - 1)
+            }
+            None => inner
+        }
+    }
+}
+
+/// What a key press does to a list that has the keyboard focus
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum Focus { Unchanged, Moved(usize), Activated(usize), Cancelled }
+
+/// Up and Down move through the list, wrapping at the ends; Enter activates the focused entry
+/// and Escape gives the focus back
+pub fn list_focus(key: Option<VirtualKeyCode>, focused: usize, len: usize) -> Focus {
+    match key {
+        Some(VirtualKeyCode::Escape) => Focus::Cancelled,
+        _ if len == 0 => Focus::Unchanged,
+        Some(VirtualKeyCode::Up) => Focus::Moved((focused + len - 1) % len),
+        Some(VirtualKeyCode::Down) => Focus::Moved((focused + 1) % len),
+        Some(VirtualKeyCode::Return) => Focus::Activated(focused),
+        _ => Focus::Unchanged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rltk::VirtualKeyCode::*;
+
+    #[test]
+    fn regions_inset_split_and_centre() {
+        let region = Region::new(2, 3, 20, 10);
+        assert_eq!(region.inset(1), Region::new(3, 4, 18, 8));
+        assert_eq!((region.right(), region.bottom()), (21, 12));
+        // insetting past the middle leaves an empty region rather than a negative one
+        assert_eq!((region.inset(6).w, region.inset(6).h), (8, 0));
+
+        let (left, right) = region.split_columns(5);
+        assert_eq!((left, right), (Region::new(2, 3, 5, 10), Region::new(7, 3, 15, 10)));
+        let (all, none) = region.split_columns(30);
+        assert_eq!((all, none.w), (region, 0));
+
+        assert_eq!(region.centered(10, 4), Region::new(7, 6, 10, 4));
+        assert_eq!(region.centered(40, 40), region);
+        assert_eq!(region.scaled(1, 2), Region::new(2, 6, 20, 20));
+    }
+
+    #[test]
+    fn a_stack_hands_out_rows_until_it_runs_out() {
+        let mut rows = VStack::new(Region::new(1, 1, 10, 5));
+        assert_eq!(rows.row(), Region::new(1, 1, 10, 1));
+        assert_eq!(rows.take(2), Region::new(1, 2, 10, 2));
+        rows.gap(1);
+        assert_eq!(rows.rest(), Region::new(1, 5, 10, 1));
+        // a request bigger than what is left is cut short, and after that rows come back empty
+        assert_eq!(rows.take(3), Region::new(1, 5, 10, 1));
+        assert_eq!(rows.row().h, 0);
+        assert_eq!(rows.rest().h, 0);
+    }
+
+    #[test]
+    fn list_focus_wraps_and_gives_the_focus_back() {
+        assert_eq!(list_focus(Some(Down), 0, 3), Focus::Moved(1));
+        assert_eq!(list_focus(Some(Down), 2, 3), Focus::Moved(0));
+        assert_eq!(list_focus(Some(Up), 0, 3), Focus::Moved(2));
+        assert_eq!(list_focus(Some(Return), 1, 3), Focus::Activated(1));
+        assert_eq!(list_focus(Some(Escape), 1, 3), Focus::Cancelled);
+        assert_eq!(list_focus(Some(A), 1, 3), Focus::Unchanged);
+        assert_eq!(list_focus(None, 1, 3), Focus::Unchanged);
+
+        // an empty list can still be left, but there is nothing to move to or activate
+        assert_eq!(list_focus(Some(Escape), 0, 0), Focus::Cancelled);
+        assert_eq!(list_focus(Some(Down), 0, 0), Focus::Unchanged);
+        assert_eq!(list_focus(Some(Return), 0, 0), Focus::Unchanged);
+    }
+
+    #[test]
+    fn the_layout_keeps_its_panels_apart() {
+        for &(width, height) in [(80, 30), (100, 40), (60, 24)].iter() {
+            let layout = ScreenLayout::new(width, height);
+            assert_eq!(layout.sidebar.right(), layout.screen.right());
+            assert!(layout.map.right() < layout.sidebar.x);
+            assert!(layout.map.bottom() < layout.log.y && layout.sidebar.bottom() < layout.log.y);
+            assert_eq!(layout.log.bottom(), layout.screen.bottom());
+
+            let popup = layout.popup;
+            assert!(popup.w > 0 && popup.h > 0);
+            assert!(layout.map.contains(popup.x, popup.y) && layout.map.contains(popup.right(), popup.bottom()));
+        }
+    }
+}
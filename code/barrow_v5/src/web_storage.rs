@@ -0,0 +1,15 @@
+/// The browser's localStorage, which stands in for files on disk in the web build
+fn storage() -> Option<web_sys::Storage> {
+    web_sys::window()?.local_storage().ok()?
+}
+
+pub fn read(key: &str) -> Option<String> {
+    storage()?.get_item(key).ok()?
+}
+
+pub fn write(key: &str, value: &str) -> bool {
+    match storage() {
+        Some(storage) => storage.set_item(key, value).is_ok(),
+        None => false
+    }
+}
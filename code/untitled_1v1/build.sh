@@ -1,3 +1,3 @@
 #!/bin/bash
 cargo build --release --target wasm32-unknown-unknown
-wasm-bindgen target/wasm32-unknown-unknown/release/$(basename $(pwd)).wasm --out-dir wasm --no-modules --no-typescript
+wasm-bindgen ../target/wasm32-unknown-unknown/release/$(basename $(pwd)).wasm --out-dir wasm --no-modules --no-typescript
@@ -1,20 +1,11 @@
-use rltk::{ RGB, RandomNumberGenerator };
+use rltk::RandomNumberGenerator;
 use specs::prelude::*;
-use super::{CombatStats, Player, Renderable, Name, Position, Viewshed, Monster, BlocksTile};
+use barrow_core::spawner;
+use super::CombatStats;
 
 /// Spawns the player and returns his/her entity object.
 pub fn player(ecs : &mut World, player_x : i32, player_y : i32) -> Entity {
-    ecs
-        .create_entity()
-        .with(Position { x: player_x, y: player_y })
-        .with(Renderable {
-            glyph: rltk::to_cp437('@'),
-            fg: RGB::named(rltk::YELLOW),
-            bg: RGB::named(rltk::BLACK),
-        })
-        .with(Player{})
-        .with(Viewshed{ visible_tiles : Vec::new(), range: 8, dirty: true })
-        .with(Name{name: "Player".to_string() })
+    spawner::player(ecs, player_x, player_y)
         .with(CombatStats{ max_hp: 30, hp: 30, defense: 2, power: 5, current_target: None, visible_targets: vec![] })
         .build()
 }
@@ -36,17 +27,7 @@ fn orc(ecs: &mut World, x: i32, y: i32) { monster(ecs, x, y, rltk::to_cp437('o')
 fn goblin(ecs: &mut World, x: i32, y: i32) { monster(ecs, x, y, rltk::to_cp437('g'), "Goblin"); }
 
 fn monster<S : ToString>(ecs: &mut World, x: i32, y: i32, glyph : rltk::FontCharType, name : S) {
-    ecs.create_entity()
-        .with(Position{ x, y })
-        .with(Renderable{
-            glyph,
-            fg: RGB::named(rltk::RED),
-            bg: RGB::named(rltk::BLACK),
-        })
-        .with(Viewshed{ visible_tiles : Vec::new(), range: 8, dirty: true })
-        .with(Monster{})
-        .with(Name{ name : name.to_string() })
-        .with(BlocksTile{})
+    spawner::monster(ecs, x, y, glyph, name)
         .with(CombatStats{ max_hp: 16, hp: 16, defense: 1, power: 4, current_target: None, visible_targets: vec![] })
         .build();
-}
\ No newline at end of file
+}
@@ -1,5 +1,5 @@
 use specs::prelude::*;
-use super::{CombatStats, SufferDamage, Player, Name, gamelog::GameLog, RunState};
+use super::{CombatStats, SufferDamage};
 
 pub struct DamageSystem {}
 
@@ -27,41 +27,3 @@ impl<'a> System<'a> for DamageSystem {
         damage.clear();
     }
 }
-
-pub fn delete_the_dead(ecs : &mut World) {
-    let mut dead : Vec<Entity> = Vec::new();
-    // Using a scope to make the borrow checker happy
-    {
-        let combat_stats = ecs.read_storage::<CombatStats>();
-        let players = ecs.read_storage::<Player>();
-        let names = ecs.read_storage::<Name>();
-        let entities = ecs.entities();
-        let mut log = ecs.write_resource::<GameLog>();
-        for (entity, stats) in (&entities, &combat_stats).join() {
-            if stats.hp < 1 {
-                let player = players.get(entity);
-                match player {
-                    None => {
-                        let victim_name = names.get(entity);
-                        if let Some(victim_name) = victim_name {
-                            log.entries.push(format!("{} is dead", &victim_name.name));
-                        }
-                        dead.push(entity)
-                    }
-                    Some(_) => {
-                        let mut runstate = ecs.write_resource::<RunState>();
-                        if *runstate != RunState::GameOver {
-                            log.entries.push(format!("You died!"));
-                            log.entries.push(format!("Press ESCAPE to return to the menu."));
-                            *runstate = RunState::GameOver;
-                        }
-                    }
-                }
-            }
-        }
-    }
-
-    for victim in dead {
-        ecs.delete_entity(victim).expect("Unable to delete");
-    }
-}
@@ -0,0 +1,76 @@
+use specs::prelude::*;
+use barrow_core::intent::{Intent, IntentAction, IntentEvent, IntentModel, IntentStats};
+use super::{CombatStats, CombatIntent, CombatIntents, CombatStance, Name, gamelog::GameLog, monster_ai_system::MonsterAI, damage_system::DamageSystem};
+
+/// Monsters close in and trade blows, and every blow is weighed against energy and stance
+pub struct Intents;
+
+impl IntentModel for Intents {
+    type Stats = CombatStats;
+    type Action = CombatIntent;
+
+    fn run_ai(&self, ecs: &World) {
+        let mut mob = MonsterAI{};
+        mob.run_now(ecs);
+    }
+
+    fn report(&self, ecs: &World, events: &[IntentEvent]) {
+        let mut log = ecs.write_resource::<GameLog>();
+        let names = ecs.read_storage::<Name>();
+        let name = |entity: Entity| names.get(entity).map_or(String::new(), |n| n.name.clone());
+
+        for event in events {
+            match *event {
+                IntentEvent::Attempted { entity, strong: false } => log.push(format!("{} will try to attack", name(entity))),
+                IntentEvent::Attempted { entity, strong: true } => log.push(format!("{} will try to STRONG attack", name(entity))),
+                IntentEvent::Hit { attacker, target, strong: false, damage } => log.push(format!("{} hits {}, for {} hp.", name(attacker), name(target), damage)),
+                IntentEvent::Hit { attacker, target, strong: true, damage } => log.push(format!("{} hits {} fiercely for {} hp.", name(attacker), name(target), damage)),
+                IntentEvent::Exhausted { entity } => log.push(format!("{} cannot attack, insufficient energy, resting instead", name(entity))),
+                IntentEvent::Rested { .. } | IntentEvent::GuardChanged { .. } => {}
+            }
+        }
+    }
+
+    fn settle(&self, ecs: &World, resolved: &[Entity]) {
+        {
+            let mut log = ecs.write_resource::<GameLog>();
+            let entities = ecs.entities();
+            let names = ecs.read_storage::<Name>();
+            let mut combat_intents = ecs.write_storage::<CombatIntent>();
+            for (entity, _intent, name) in (&entities, &combat_intents, &names).join() {
+                if !resolved.contains(&entity) {
+                    log.push(format!("Anomaly: {} has an incoherent intent", name.name));
+                }
+            }
+            combat_intents.clear();
+        }
+        let mut damage = DamageSystem{};
+        damage.run_now(ecs);
+    }
+}
+
+impl IntentStats for CombatStats {
+    fn hp(&self) -> i32 { self.hp }
+    fn ep(&self) -> i32 { self.ep }
+    fn power(&self) -> i32 { self.power }
+    fn defense(&self) -> i32 { self.defense }
+    fn attack_cost(&self) -> i32 { self.attack_cost }
+    fn hp_regen(&self) -> i32 { self.hp_regen }
+    fn ep_regen(&self) -> i32 { self.ep_regen }
+    fn in_combat(&self) -> bool { self.current_target.is_some() }
+    fn guarding(&self) -> bool { self.stance == CombatStance::GuardUp }
+    fn stunned(&self) -> bool { false }
+    fn set_guard(&mut self, up: bool) { self.stance = if up { CombatStance::GuardUp } else { CombatStance::GuardDown }; }
+}
+
+impl IntentAction for CombatIntent {
+    fn intent(&self) -> Option<Intent> {
+        match *self {
+            CombatIntent { intent: CombatIntents::Melee, target: Some(target) } => Some(Intent::Attack { target, strong: false }),
+            CombatIntent { intent: CombatIntents::StrongMelee, target: Some(target) } => Some(Intent::Attack { target, strong: true }),
+            CombatIntent { intent: CombatIntents::Wait, target: None } => Some(Intent::Rest),
+            CombatIntent { intent: CombatIntents::Move, target: None } => Some(Intent::Move),
+            _ => None
+        }
+    }
+}
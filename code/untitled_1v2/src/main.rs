@@ -1,228 +1,62 @@
-use rltk::{GameState, Rltk, Point};
+use rltk::{Rltk, VirtualKeyCode};
 use specs::prelude::*;
 mod components;
 pub use components::*;
 mod map;
 pub use map::*;
 mod player;
-use player::*;
-mod rect;
-pub use rect::Rect;
-mod visibility_system;
-use visibility_system::VisibilitySystem;
+pub use barrow_core::Rect;
 mod monster_ai_system;
-use monster_ai_system::MonsterAI;
-mod map_indexing_system;
-use map_indexing_system::MapIndexingSystem;
-mod melee_combat_system;
-use melee_combat_system::MeleeCombatSystem;
+mod intent_rules;
+use intent_rules::Intents;
 mod damage_system;
-use damage_system::DamageSystem;
 mod gui;
-mod gamelog;
-use gamelog::GameLog;
+pub use barrow_core::gamelog;
+pub use barrow_core::shell::RunState;
+use barrow_core::shell::{Game, Screen};
+use barrow_core::Ruleset;
+use barrow_core::intent::IntentRules;
 mod spawner;
 
-#[derive(PartialEq, Copy, Clone)]
-pub enum RunState { 
-    AwaitingInput, 
-    PreRun, 
-    PlayerTurn, 
-    MonsterTurn,
-    MainMenu { menu_selection : gui::MainMenuSelection },
-    GameOver
+struct Untitled {
+    rules: IntentRules<Intents>
 }
 
-pub struct State {
-    pub ecs: World,
-}
+impl Game for Untitled {
+    type Map = Map;
+    type Stats = CombatStats;
 
-impl State {
-    fn run_systems(&mut self) {
-        let mut vis = VisibilitySystem{};
-        vis.run_now(&self.ecs);
-        let mut mob = MonsterAI{};
-        mob.run_now(&self.ecs);
-        let mut mapindex = MapIndexingSystem{};
-        mapindex.run_now(&self.ecs);
-        let mut melee = MeleeCombatSystem{};
-        melee.run_now(&self.ecs);
-        let mut damage = DamageSystem{};
-        damage.run_now(&self.ecs);
-        self.ecs.maintain();
-    }
+    const TITLE: &'static str = "richard's untitled roguelike";
+    const WELCOME: &'static str = "Welcome to richard's untitled roguelike";
+    const SCREEN: Screen = Screen::Boxed;
 
-    fn game_over_cleanup(&mut self) {
-        // Delete everything
-        let mut to_delete = Vec::new();
-        for e in self.ecs.entities().join() {
-            to_delete.push(e);
-        }
-        for del in to_delete.iter() {
-            self.ecs.delete_entity(*del).expect("Deletion failed");
-        }
-    
-        // Build a new map and place the player
-        let worldmap;
-        {
-            let mut worldmap_resource = self.ecs.write_resource::<Map>();
-            *worldmap_resource = Map::new_map_rooms_and_corridors();
-            worldmap = worldmap_resource.clone();
-        }
-    
-        // Spawn bad guys
-        for room in worldmap.rooms.iter().skip(1) {
-            let (x,y) = room.center();
-            spawner::random_monster(&mut self.ecs, x, y);
-        }
-    
-        // Place the player and update resources
-        let (player_x, player_y) = worldmap.rooms[0].center();
-        let player_entity = spawner::player(&mut self.ecs, player_x, player_y);
-        let mut player_position = self.ecs.write_resource::<Point>();
-        *player_position = Point::new(player_x, player_y);
-        let mut position_components = self.ecs.write_storage::<Position>();
-        let mut player_entity_writer = self.ecs.write_resource::<Entity>();
-        *player_entity_writer = player_entity;
-        let player_pos_comp = position_components.get_mut(player_entity);
-        if let Some(player_pos_comp) = player_pos_comp {
-            player_pos_comp.x = player_x;
-            player_pos_comp.y = player_y;
-        }
-        let mut log = self.ecs.write_resource::<GameLog>();
-        log.entries.clear();
-    
-        // Mark the player's visibility as dirty
-        let mut viewshed_components = self.ecs.write_storage::<Viewshed>();
-        let vs = viewshed_components.get_mut(player_entity);
-        if let Some(vs) = vs {
-            vs.dirty = true;
-        }                                               
+    fn ruleset(&self) -> &dyn Ruleset { &self.rules }
+
+    fn register(&self, ecs: &mut World) {
+        ecs.register::<CombatStats>();
+        ecs.register::<CombatIntent>();
+        ecs.register::<SufferDamage>();
     }
-}
 
-impl GameState for State {
-    fn tick(&mut self, ctx : &mut Rltk) {
-        let mut newrunstate;
-        {
-            let runstate = self.ecs.fetch::<RunState>();
-            newrunstate = *runstate;
-        }
+    fn new_map(&self) -> Map { new_map_rooms_and_corridors() }
 
-        ctx.cls();
+    fn spawn_player(&self, ecs: &mut World, x: i32, y: i32) -> Entity {
+        spawner::player(ecs, x, y)
+    }
 
-        match newrunstate {
-            RunState::MainMenu{..} => {}
-            _ => {
-                draw_map(&self.ecs, ctx);
-                {
-                    let positions = self.ecs.read_storage::<Position>();
-                    let renderables = self.ecs.read_storage::<Renderable>();
-                    let map = self.ecs.fetch::<Map>();
+    fn spawn_monster(&self, ecs: &mut World, _room: usize, _rooms: usize, x: i32, y: i32) {
+        spawner::random_monster(ecs, x, y);
+    }
 
-                    let data = (&positions, &renderables).join().collect::<Vec<_>>();
-                    // make above let mut
-                    // data.sort_by(|&a, &b| b.1.render_order.cmp(&a.1.render_order) );
-                    for (pos, render) in data.iter() {
-                        let idx = map.xy_idx(pos.x, pos.y);
-                        if map.visible_tiles[idx] { ctx.set(pos.x, pos.y, render.fg, render.bg, render.glyph) }
-                    }
-                    player::update_targeting(&self.ecs, ctx);
-                    gui::draw_ui(&self.ecs, ctx);
-                }
-            }
-        }
+    fn draw_map(&self, ecs: &World, ctx: &mut Rltk) { draw_map(ecs, ctx); }
 
-        match newrunstate {
-            RunState::PreRun => {
-                self.run_systems();
-                self.ecs.maintain();
-                newrunstate = RunState::AwaitingInput;
-            }
-            RunState::AwaitingInput => {
-                newrunstate = player_input(self, ctx);
-            }
-            RunState::PlayerTurn => {
-                self.run_systems();
-                self.ecs.maintain();
-                newrunstate = RunState::MonsterTurn;
-            }
-            RunState::MonsterTurn => {
-                self.run_systems();
-                self.ecs.maintain();
-                newrunstate = RunState::AwaitingInput;
-            }
-            RunState::MainMenu{ .. } => {
-                let result = gui::main_menu(self, ctx);
-                match result {
-                    gui::MainMenuResult::NoSelection{ selected } => newrunstate = RunState::MainMenu{ menu_selection: selected },
-                    gui::MainMenuResult::Selected{ selected } => {
-                        match selected {
-                            gui::MainMenuSelection::NewGame => newrunstate = RunState::PreRun,
-                            gui::MainMenuSelection::Quit => { ::std::process::exit(0); }
-                        }
-                    }
-                }
-            }
-            RunState::GameOver => {
-                let result = gui::game_over(ctx);
-                match result {
-                    gui::GameOverResult::NoSelection => {}
-                    gui::GameOverResult::QuitToMenu => {
-                        self.game_over_cleanup();
-                        newrunstate = RunState::MainMenu{ menu_selection: gui::MainMenuSelection::NewGame };
-                    }
-                }
-            }
-        }
+    fn draw_sidebar(&self, ecs: &World, ctx: &mut Rltk) { gui::draw_sidebar(ecs, ctx); }
 
-        {
-            let mut runwriter = self.ecs.write_resource::<RunState>();
-            *runwriter = newrunstate;
-        }
-        damage_system::delete_the_dead(&mut self.ecs);
+    fn player_input(&self, ecs: &mut World, key: VirtualKeyCode) -> RunState {
+        player::player_input(ecs, key)
     }
 }
 
-
 fn main() -> rltk::BError {
-    use rltk::RltkBuilder;
-    let context = RltkBuilder::simple(80,60)
-        .unwrap()
-        .with_title("richard's untitled roguelike")
-        .build()?;
-    // context.with_post_scanlines(true);
-    let mut gs = State {
-        ecs: World::new(),
-    };
-    gs.ecs.register::<Position>();
-    gs.ecs.register::<Renderable>();
-    gs.ecs.register::<Player>();
-    gs.ecs.register::<Viewshed>();
-    gs.ecs.register::<Monster>();
-    gs.ecs.register::<Name>();
-    gs.ecs.register::<BlocksTile>();
-    gs.ecs.register::<CombatStats>();
-    gs.ecs.register::<CombatIntent>();
-    gs.ecs.register::<SufferDamage>();
-
-    let map : Map = Map::new_map_rooms_and_corridors();
-    let (player_x, player_y) = map.rooms[0].center();
-
-    let player_entity = spawner::player(&mut gs.ecs, player_x, player_y);
-
-    gs.ecs.insert(rltk::RandomNumberGenerator::new());
-    for room in map.rooms.iter().skip(1) {
-        let (x,y) = room.center();
-        spawner::random_monster(&mut gs.ecs, x, y);
-    }
-
-    gs.ecs.insert(map);
-    gs.ecs.insert(Point::new(player_x, player_y));
-    gs.ecs.insert(rltk::RandomNumberGenerator::new());
-    gs.ecs.insert(player_entity);
-    gs.ecs.insert(RunState::MainMenu{ menu_selection: gui::MainMenuSelection::NewGame });
-    gs.ecs.insert(gamelog::GameLog{ entries : vec!["Welcome to richard's untitled roguelike".to_string()] });
-
-    rltk::main_loop(context, gs)
+    barrow_core::shell::run(Untitled { rules: IntentRules(Intents) })
 }